@@ -106,12 +106,12 @@ impl AlertStorage for DatabaseConnection {
             .as_nanos();
         let req = req.user_alert.ok_or("Missing user alert")?;
 
-        let mut query = db_entities::user_alert::Entity::find()
+        let query = db_entities::user_alert::Entity::find()
             .filter(db_entities::user_alert::Column::Id.eq(req.id.parse::<i32>()?));
 
-        let mut alertExists = query.filter(db_entities::user_alert::Column::DeletedAt.is_null());
+        let alert_exists = query.filter(db_entities::user_alert::Column::DeletedAt.is_null());
 
-        if (alertExists.one(self).await?.is_none()) {
+        if alert_exists.one(self).await?.is_none() {
             return Err("Alert not found".into());
         }
 
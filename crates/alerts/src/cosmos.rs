@@ -1,19 +1,34 @@
 use std::collections::HashMap;
 
 use cosmrs::tx::Msg;
-use cw20_base::msg::ExecuteMsg;
 
 use mempools_api::api::{
-    alert_cosmos_tx_outcome::CosmosTxOutcome, alert_notification_data::AlertNotificationData,
-    monitor_funds_coin_notification_data::CoinAmount, AlertCosmosMonitorFunds,
-    AlertCosmosSendFunds, AlertCosmosSmartContractEvents, AlertCosmosTxOutcome,
+    alert_cosmos_tx_outcome::CosmosTxOutcome,
+    alert_cosmos_validator::Event as ValidatorEvent,
+    alert_notification_data::AlertNotificationData,
+    governance_notification_data::{TallyResult, VoteOption},
+    monitor_funds_coin_notification_data::CoinAmount,
+    AlertCosmosBlockEvents, AlertCosmosCw721Transfers, AlertCosmosGovernance, AlertCosmosIbcPacket,
+    AlertCosmosMonitorFunds, AlertCosmosSendFunds, AlertCosmosSmartContractEvents,
+    AlertCosmosStaking, AlertCosmosTxOutcome, AlertCosmosValidator, BlockEventNotificationData,
+    Cw721TransferNotificationData, GovernanceNotificationData, IbcPacketNotificationData,
     MonitorFundsCoinNotificationData, MonitorFundsTokenNotificationData,
-    SmartContractEventsNotificationData, TxOutcomeNotificationData,
+    SmartContractEventsNotificationData, StakingNotificationData, TxOutcomeNotificationData,
+    ValidatorNotificationData,
 };
 
-use util::{get_signers_from_tx, service_registry::ProcessAlertSourceRequeust, Result};
+use mempools_api::api::AlertEthMonitorFunds;
+use util::{
+    ethermint::{ethereum_tx_failed, evm_address},
+    get_signers_from_tx,
+    service_registry::ProcessAlertSourceRequeust,
+    Result,
+};
 
-use crate::AlertSourceFilter;
+use crate::{
+    cosmwasm::{cw20_movement, cw721_movement},
+    AlertSourceFilter,
+};
 
 impl AlertSourceFilter for AlertCosmosTxOutcome {
     fn filter(&self, alert_source: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
@@ -22,26 +37,37 @@ impl AlertSourceFilter for AlertCosmosTxOutcome {
         if !self.signer.is_empty() {
             let signers =
                 get_signers_from_tx(ctx.chain_data.bech32_prefix, cosmrs::Tx::try_from(ctx.tx)?);
-            if !signers.contains(&self.signer) {
+            if !signers.contains(&self.signer.to_ascii_lowercase()) {
                 return Err("signer not found".into());
             }
         }
 
-        let matched =
-            match CosmosTxOutcome::from_i32(self.outcome).ok_or("invalid i32 val for enum")? {
-                CosmosTxOutcome::Succeeded => ctx.tx_resp.code == 0,
-                CosmosTxOutcome::Failed => ctx.tx_resp.code != 0,
-            };
+        // The outcome of a pending tx is not known yet
+        let outcome = match &ctx.tx_resp {
+            Some(tx_resp) => {
+                // A reverted evm tx is still a successful cosmos tx
+                let succeeded = tx_resp.code == 0 && !ethereum_tx_failed(tx_resp);
+                let matched = match CosmosTxOutcome::from_i32(self.outcome)
+                    .ok_or("invalid i32 val for enum")?
+                {
+                    CosmosTxOutcome::Succeeded => succeeded,
+                    CosmosTxOutcome::Failed => !succeeded,
+                };
+
+                if !matched {
+                    return Err("tx outcome mismatch".into());
+                }
 
-        if !matched {
-            return Err("tx outcome mismatch".into());
-        }
+                self.outcome().as_str_name().to_string()
+            }
+            None => "PENDING".to_string(),
+        };
 
         Ok(AlertNotificationData::TxOutcome(
             TxOutcomeNotificationData {
                 signer: self.signer.clone(),
-                outcome: self.outcome().as_str_name().to_string(),
-                tx_hash: alert_source.get_cosmos_tx()?.tx_hash,
+                outcome,
+                tx_hash: ctx.tx_hash,
             },
         ))
     }
@@ -49,9 +75,45 @@ impl AlertSourceFilter for AlertCosmosTxOutcome {
 
 impl AlertSourceFilter for AlertCosmosMonitorFunds {
     fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        // Evm txs and their logs match the same way they do for evm alerts on the chain
+        let eth_alert = AlertEthMonitorFunds {
+            address: evm_address(&self.address),
+        };
+        if let Ok(event) = req.get_cosmos_msg_event() {
+            if event.msg.eth_tx.is_some() {
+                let log = event.eth_log_source()?;
+                return eth_alert.filter(&ProcessAlertSourceRequeust::EthLog(Box::new(log)));
+            }
+        }
+
+        if let Some(movement) = cw20_movement(req) {
+            if !self.address.is_empty()
+                && self.address != movement.from
+                && self.address != movement.to
+            {
+                return Err("address not related to cw20 movement".into());
+            }
+
+            return Ok(AlertNotificationData::MonitorFundsToken(
+                MonitorFundsTokenNotificationData {
+                    from: movement.from,
+                    to: movement.to,
+                    amount: movement.amount,
+                    tx_hash: movement.tx_hash,
+                    contract_addr: movement.contract,
+                    action: movement.action,
+                    log_index: None,
+                },
+            ));
+        }
+
         let ctx = req.get_cosmos_msg()?;
         let msg = &ctx.msg;
 
+        if let Ok(eth_tx) = ctx.eth_tx_source() {
+            return eth_alert.filter(&ProcessAlertSourceRequeust::EthTx(Box::new(eth_tx)));
+        }
+
         if let Ok(bank_send) = cosmrs::bank::MsgSend::from_any(msg) {
             let notification =
                 AlertNotificationData::MonitorFundsCoin(MonitorFundsCoinNotificationData {
@@ -67,6 +129,7 @@ impl AlertSourceFilter for AlertCosmosMonitorFunds {
                         .collect(),
                     tx_hash: ctx.tx_hash.clone(),
                     monitored_address: dbg!(self.address.to_string()),
+                    internal: false,
                 });
 
             if self.address.is_empty() {
@@ -80,37 +143,35 @@ impl AlertSourceFilter for AlertCosmosMonitorFunds {
             }
         }
 
-        if let Ok(exec_contract) = cosmrs::cosmwasm::MsgExecuteContract::from_any(msg) {
-            let cw_20_exec_msg: ExecuteMsg = serde_json::from_slice(&exec_contract.msg)?;
-            if let ExecuteMsg::Transfer { recipient, amount } = cw_20_exec_msg {
-                let notification =
-                    AlertNotificationData::MonitorFundsToken(MonitorFundsTokenNotificationData {
-                        from: exec_contract.sender.to_string(),
-                        to: recipient.clone(),
-                        amount: amount.to_string(),
-                        tx_hash: ctx.tx_hash.clone(),
-                        contract_addr: exec_contract.contract.to_string(),
-                    });
-
-                if self.address.is_empty() {
-                    return Ok(notification);
-                }
-
-                if self.address == exec_contract.sender.to_string() || self.address == recipient {
-                    return Ok(notification);
-                }
-            }
-        }
-
         Err("address not related to message".into())
     }
 }
 
 impl AlertSourceFilter for AlertCosmosSendFunds {
     fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        if let Some(movement) = cw20_movement(req) {
+            if (!self.from.is_empty() && movement.from != self.from)
+                || (!self.to.is_empty() && movement.to != self.to)
+            {
+                return Err("cw20 movement does not match the send funds alert".into());
+            }
+
+            return Ok(AlertNotificationData::MonitorFundsToken(
+                MonitorFundsTokenNotificationData {
+                    from: movement.from,
+                    to: movement.to,
+                    amount: movement.amount,
+                    tx_hash: movement.tx_hash,
+                    contract_addr: movement.contract,
+                    action: movement.action,
+                    log_index: None,
+                },
+            ));
+        }
+
         let ctx = req.get_cosmos_msg()?;
         let msg = &ctx.msg;
-        let tx_hash = ctx.tx_hash;
+        let tx_hash = ctx.tx_hash.clone();
 
         if let Ok(bank_send) = cosmrs::bank::MsgSend::from_any(msg) {
             if !self.from.is_empty() && bank_send.from_address.to_string() != self.from {
@@ -143,43 +204,12 @@ impl AlertSourceFilter for AlertCosmosSendFunds {
                         .collect(),
                     tx_hash,
                     monitored_address: bank_send.from_address.to_string(),
+                    internal: false,
                 });
 
             return Ok(notification);
         }
 
-        if let Ok(exec_contract) = cosmrs::cosmwasm::MsgExecuteContract::from_any(msg) {
-            let cw_20_exec_msg: ExecuteMsg = serde_json::from_slice(&exec_contract.msg)?;
-            if let ExecuteMsg::Transfer { recipient, amount } = cw_20_exec_msg {
-                if !self.from.is_empty() && exec_contract.sender.to_string() != self.from {
-                    return Err(format!(
-                        "filter_send_funds failed, wrong from address - expected - {}, got - {}",
-                        exec_contract.sender, self.from
-                    )
-                    .into());
-                }
-
-                if !self.to.is_empty() && recipient != self.to {
-                    return Err(format!(
-                        "filter_send_funds failed, wrong to address - expected - {}, got - {}",
-                        recipient, self.to
-                    )
-                    .into());
-                }
-
-                let notification =
-                    AlertNotificationData::MonitorFundsToken(MonitorFundsTokenNotificationData {
-                        from: exec_contract.sender.to_string(),
-                        to: recipient,
-                        amount: amount.to_string(),
-                        tx_hash,
-                        contract_addr: exec_contract.contract.to_string(),
-                    });
-
-                return Ok(notification);
-            }
-        }
-
         Err("incorrect message type".into())
     }
 }
@@ -229,8 +259,213 @@ impl AlertSourceFilter for AlertCosmosSmartContractEvents {
         Err("msg not related to contract".into())
     }
 }
+impl AlertSourceFilter for AlertCosmosBlockEvents {
+    fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        let ctx = req.get_cosmos_block_event()?;
+
+        if ctx.event.r#type != self.event_type {
+            return Err("event type mismatch".into());
+        }
+
+        let attrs: HashMap<String, String> = ctx
+            .event
+            .attributes
+            .iter()
+            .map(|attr| (attr.key.clone(), attr.value.clone()))
+            .collect();
+
+        for (k, v) in &self.event_attributes {
+            if attrs.get(k) != Some(v) {
+                return Err("event attributes mismatch".into());
+            }
+        }
+
+        Ok(AlertNotificationData::BlockEvent(
+            BlockEventNotificationData {
+                height: ctx.height,
+                phase: ctx.phase.as_str().to_string(),
+                event_type: ctx.event.r#type,
+                event_attributes: attrs,
+            },
+        ))
+    }
+}
+
+impl AlertSourceFilter for AlertCosmosIbcPacket {
+    fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        let ctx = req.get_cosmos_ibc_packet()?;
+        let packet = ctx.packet;
+
+        if !self.stages.is_empty() && !self.stages.contains(&(ctx.stage as i32)) {
+            return Err("packet stage mismatch".into());
+        }
+        if !self.sender.is_empty() && packet.sender != self.sender {
+            return Err("packet sender mismatch".into());
+        }
+        if !self.receiver.is_empty() && packet.receiver != self.receiver {
+            return Err("packet receiver mismatch".into());
+        }
+        if !self.channel.is_empty()
+            && packet.src_channel != self.channel
+            && packet.dst_channel != self.channel
+        {
+            return Err("packet channel mismatch".into());
+        }
+        if !self.denom.is_empty() && packet.denom != self.denom {
+            return Err("packet denom mismatch".into());
+        }
+
+        Ok(AlertNotificationData::IbcPacket(
+            IbcPacketNotificationData {
+                stage: ctx.stage.as_str_name().to_string(),
+                chain_id: ctx.chain_id,
+                tx_hash: ctx.tx_hash,
+                sequence: packet.sequence,
+                src_port: packet.src_port,
+                src_channel: packet.src_channel,
+                dst_port: packet.dst_port,
+                dst_channel: packet.dst_channel,
+                sender: packet.sender,
+                receiver: packet.receiver,
+                denom: packet.denom,
+                amount: packet.amount,
+                error: ctx.error.unwrap_or_default(),
+            },
+        ))
+    }
+}
+
+impl AlertSourceFilter for AlertCosmosGovernance {
+    fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        let ctx = req.get_cosmos_governance()?;
+        let update = ctx.update;
+
+        if !self.events.is_empty() && !self.events.contains(&(ctx.event as i32)) {
+            return Err("governance event mismatch".into());
+        }
+        if self.proposal_id != 0 && update.proposal_id != self.proposal_id {
+            return Err("proposal id mismatch".into());
+        }
+        if !self.voter.is_empty() && update.voter != self.voter {
+            return Err("voter mismatch".into());
+        }
+
+        Ok(AlertNotificationData::Governance(
+            GovernanceNotificationData {
+                event: ctx.event.as_str_name().to_string(),
+                proposal_id: update.proposal_id,
+                height: ctx.height,
+                tx_hash: ctx.tx_hash.unwrap_or_default(),
+                title: update.title,
+                proposer: update.proposer,
+                voter: update.voter,
+                options: update
+                    .options
+                    .into_iter()
+                    .map(|(option, weight)| VoteOption { option, weight })
+                    .collect(),
+                result: update.result,
+                tally: update.tally.map(|tally| TallyResult {
+                    yes: tally.yes,
+                    abstain: tally.abstain,
+                    no: tally.no,
+                    no_with_veto: tally.no_with_veto,
+                }),
+            },
+        ))
+    }
+}
+
+impl AlertSourceFilter for AlertCosmosStaking {
+    fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        let ctx = req.get_cosmos_staking()?;
+
+        if !self.actions.is_empty() && !self.actions.contains(&(ctx.action as i32)) {
+            return Err("staking action mismatch".into());
+        }
+        if !self.delegator.is_empty() && ctx.delegator != self.delegator {
+            return Err("delegator mismatch".into());
+        }
+        if !self.validator.is_empty()
+            && ctx.validator != self.validator
+            && ctx.validator_dst.as_ref() != Some(&self.validator)
+        {
+            return Err("validator mismatch".into());
+        }
+
+        let amount = ctx.amount.unwrap_or_default();
+        Ok(AlertNotificationData::Staking(StakingNotificationData {
+            action: ctx.action.as_str_name().to_string(),
+            delegator: ctx.delegator,
+            validator: ctx.validator,
+            validator_dst: ctx.validator_dst.unwrap_or_default(),
+            amount: amount.amount,
+            denom: amount.denom,
+            tx_hash: ctx.tx_hash,
+        }))
+    }
+}
+
+impl AlertSourceFilter for AlertCosmosValidator {
+    fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        let ctx = req.get_cosmos_validator()?;
+        let update = ctx.update;
+
+        if update.validator != self.validator {
+            return Err("validator mismatch".into());
+        }
+        if !self.events.is_empty() && !self.events.contains(&(ctx.event as i32)) {
+            return Err("validator event mismatch".into());
+        }
+        // Fires once each time the counter climbs to the threshold
+        if ctx.event == ValidatorEvent::MissedBlocks
+            && (self.missed_blocks_threshold == 0
+                || update.missed_blocks != self.missed_blocks_threshold)
+        {
+            return Err("missed blocks not at threshold".into());
+        }
+
+        Ok(AlertNotificationData::Validator(
+            ValidatorNotificationData {
+                event: ctx.event.as_str_name().to_string(),
+                validator: update.validator,
+                height: ctx.height,
+                tx_hash: ctx.tx_hash.unwrap_or_default(),
+                moniker: update.moniker,
+                commission_rate: update.commission_rate,
+                reason: update.slash_reason,
+                power: update.power,
+                burned_coins: update.burned_coins,
+                missed_blocks: update.missed_blocks,
+            },
+        ))
+    }
+}
+
 // impl AlertSourceFilter for AlertCosmosBroadcast {
 //     fn filter(&self, _req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
 //         Err("Broadcast Alert filters nothing".into())
 //     }
 // }
+
+impl AlertSourceFilter for AlertCosmosCw721Transfers {
+    fn filter(&self, req: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        let movement = cw721_movement(req).ok_or("msg did not move an nft")?;
+        if (!self.contract_addr.is_empty() && movement.contract != self.contract_addr)
+            || (!self.token_id.is_empty() && movement.token_id != self.token_id)
+        {
+            return Err("msg did not move a matching nft".into());
+        }
+
+        Ok(AlertNotificationData::Cw721Transfer(
+            Cw721TransferNotificationData {
+                action: movement.action,
+                contract_addr: movement.contract,
+                token_id: movement.token_id,
+                from: movement.from,
+                to: movement.to,
+                tx_hash: movement.tx_hash,
+            },
+        ))
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use cosmrs::tx::Msg;
+use cw20_base::msg::ExecuteMsg;
+use serde_json::Value;
+use util::{
+    service_registry::{AlertSourceCosmosMsg, ProcessAlertSourceRequeust},
+    wasm::CONTRACT_ADDRESS,
+};
+
+/// Tokens moved by a cw20 contract
+pub struct Cw20Movement {
+    pub action: String,
+    pub contract: String,
+    // Empty for mints
+    pub from: String,
+    // Empty for burns
+    pub to: String,
+    pub amount: String,
+    pub tx_hash: String,
+}
+
+/// Token moved by a cw721 collection
+pub struct Cw721Movement {
+    pub action: String,
+    pub contract: String,
+    pub token_id: String,
+    // Empty for mints
+    pub from: String,
+    // Empty for burns
+    pub to: String,
+    pub tx_hash: String,
+}
+
+/// cw20 tokens moved by the source. Included msgs have a source for each contract execution in
+/// their log so tokens moved by other contracts are found too
+pub fn cw20_movement(req: &ProcessAlertSourceRequeust) -> Option<Cw20Movement> {
+    match req {
+        ProcessAlertSourceRequeust::CosmosMsgEvent(event) => {
+            cw20_event(&event.wasm_event().ok()?, &event.msg.tx_hash)
+        }
+        ProcessAlertSourceRequeust::CosmosMsg(ctx) if without_events(ctx) => cw20_execute_msg(ctx),
+        _ => None,
+    }
+}
+
+/// cw721 token moved by the source, found the same way as cw20 movements
+pub fn cw721_movement(req: &ProcessAlertSourceRequeust) -> Option<Cw721Movement> {
+    match req {
+        ProcessAlertSourceRequeust::CosmosMsgEvent(event) => {
+            cw721_event(&event.wasm_event().ok()?, &event.msg.tx_hash)
+        }
+        ProcessAlertSourceRequeust::CosmosMsg(ctx) if without_events(ctx) => cw721_execute_msg(ctx),
+        _ => None,
+    }
+}
+
+// The events of included nested msgs are already in the log of the msg wrapping them
+fn without_events(ctx: &AlertSourceCosmosMsg) -> bool {
+    ctx.msg_log.is_none() && (ctx.path.is_empty() || ctx.pending)
+}
+
+fn cw20_event(event: &HashMap<&str, &str>, tx_hash: &str) -> Option<Cw20Movement> {
+    let attr = |key: &str| event.get(key).map(|v| v.to_string()).unwrap_or_default();
+    let action = *event.get("action")?;
+    // cw721 collections mint and burn too, but move tokens rather than amounts
+    if event.contains_key("token_id") {
+        return None;
+    }
+    if !matches!(
+        action,
+        "transfer" | "send" | "transfer_from" | "send_from" | "mint" | "burn" | "burn_from"
+    ) {
+        return None;
+    }
+
+    Some(Cw20Movement {
+        action: action.to_string(),
+        contract: attr(CONTRACT_ADDRESS),
+        from: attr("from"),
+        to: attr("to"),
+        amount: event.get("amount")?.to_string(),
+        tx_hash: tx_hash.to_string(),
+    })
+}
+
+fn cw721_event(event: &HashMap<&str, &str>, tx_hash: &str) -> Option<Cw721Movement> {
+    let attr = |key: &str| event.get(key).map(|v| v.to_string()).unwrap_or_default();
+    let action = *event.get("action")?;
+    let (from, to) = match action {
+        "transfer_nft" | "send_nft" => (attr("sender"), attr("recipient")),
+        "mint" => ("".to_string(), attr("owner")),
+        "burn" => (attr("sender"), "".to_string()),
+        _ => return None,
+    };
+
+    Some(Cw721Movement {
+        action: action.to_string(),
+        contract: attr(CONTRACT_ADDRESS),
+        token_id: event.get("token_id")?.to_string(),
+        from,
+        to,
+        tx_hash: tx_hash.to_string(),
+    })
+}
+
+// Msgs without events, such as pending ones, only match a direct execution of the contract
+fn cw20_execute_msg(ctx: &AlertSourceCosmosMsg) -> Option<Cw20Movement> {
+    let exec_contract = cosmrs::cosmwasm::MsgExecuteContract::from_any(&ctx.msg).ok()?;
+    let sender = exec_contract.sender.to_string();
+    let (action, from, to, amount) = match serde_json::from_slice(&exec_contract.msg).ok()? {
+        ExecuteMsg::Transfer { recipient, amount } => ("transfer", sender, recipient, amount),
+        ExecuteMsg::Send {
+            contract, amount, ..
+        } => ("send", sender, contract, amount),
+        ExecuteMsg::TransferFrom {
+            owner,
+            recipient,
+            amount,
+        } => ("transfer_from", owner, recipient, amount),
+        ExecuteMsg::SendFrom {
+            owner,
+            contract,
+            amount,
+            ..
+        } => ("send_from", owner, contract, amount),
+        ExecuteMsg::Mint { recipient, amount } => ("mint", "".to_string(), recipient, amount),
+        ExecuteMsg::Burn { amount } => ("burn", sender, "".to_string(), amount),
+        ExecuteMsg::BurnFrom { owner, amount } => ("burn_from", owner, "".to_string(), amount),
+        _ => return None,
+    };
+
+    Some(Cw20Movement {
+        action: action.to_string(),
+        contract: exec_contract.contract.to_string(),
+        from,
+        to,
+        amount: amount.to_string(),
+        tx_hash: ctx.tx_hash.clone(),
+    })
+}
+
+fn cw721_execute_msg(ctx: &AlertSourceCosmosMsg) -> Option<Cw721Movement> {
+    let exec_contract = cosmrs::cosmwasm::MsgExecuteContract::from_any(&ctx.msg).ok()?;
+    let sender = exec_contract.sender.to_string();
+    let msg: HashMap<String, Value> = serde_json::from_slice(&exec_contract.msg).ok()?;
+    let (action, args) = msg.iter().next()?;
+    let arg = |key: &str| args.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let (from, to) = match action.as_str() {
+        "transfer_nft" => (sender, arg("recipient")?),
+        "send_nft" => (sender, arg("contract")?),
+        "mint" => ("".to_string(), arg("owner")?),
+        "burn" => (sender, "".to_string()),
+        _ => return None,
+    };
+
+    Some(Cw721Movement {
+        action: action.clone(),
+        contract: exec_contract.contract.to_string(),
+        token_id: arg("token_id")?,
+        from,
+        to,
+        tx_hash: ctx.tx_hash.clone(),
+    })
+}
@@ -4,21 +4,33 @@ use base64::Engine;
 
 use mempools_api::api::{
     alert_eth_tx_outcome::EthTxOutcome, alert_notification_data::AlertNotificationData,
-    monitor_funds_coin_notification_data::CoinAmount, AlertEthMonitorFunds,
-    AlertEthSmartContractEvents, AlertEthTxOutcome, MonitorFundsCoinNotificationData,
-    MonitorFundsTokenNotificationData, SmartContractEventsNotificationData,
-    TxOutcomeNotificationData,
+    monitor_funds_coin_notification_data::CoinAmount, nft_transfer_notification_data::TokenAmount,
+    AlertEthMonitorFunds, AlertEthNftTransfers, AlertEthSmartContractEvents, AlertEthTxOutcome,
+    MonitorFundsCoinNotificationData, MonitorFundsTokenNotificationData,
+    NftTransferNotificationData, SmartContractEventsNotificationData, TxOutcomeNotificationData,
 };
 
-use web3::ethabi::RawLog;
+use web3::{
+    ethabi::{RawLog, Token},
+    types::{Log, U256},
+};
 
 use util::HashString;
-use util::{service_registry::ProcessAlertSourceRequeust, Result};
+use util::{
+    service_registry::{
+        AlertSourceEthInternalTransfer, AlertSourceEthTx, ProcessAlertSourceRequeust,
+    },
+    Result,
+};
 
 use crate::AlertSourceFilter;
 
 const ERC20_ABI: &str = r#"[{"constant":true,"inputs":[],"name":"name","outputs":[{"name":"","type":"string"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"_spender","type":"address"},{"name":"_value","type":"uint256"}],"name":"approve","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[],"name":"totalSupply","outputs":[{"name":"","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"_from","type":"address"},{"name":"_to","type":"address"},{"name":"_value","type":"uint256"}],"name":"transferFrom","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[],"name":"decimals","outputs":[{"name":"","type":"uint8"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"_owner","type":"address"}],"name":"balanceOf","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"symbol","outputs":[{"name":"","type":"string"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"_to","type":"address"},{"name":"_value","type":"uint256"}],"name":"transfer","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"_owner","type":"address"},{"name":"_spender","type":"address"}],"name":"allowance","outputs":[{"name":"","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"payable":true,"stateMutability":"payable","type":"fallback"},{"anonymous":false,"inputs":[{"indexed":true,"name":"owner","type":"address"},{"indexed":true,"name":"spender","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Approval","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Transfer","type":"event"}]"#;
 
+// Transfer shares the signature of the ERC-20 event but indexes the token id
+const ERC721_ABI: &str = r#"[{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":true,"name":"tokenId","type":"uint256"}],"name":"Transfer","type":"event"}]"#;
+const ERC1155_ABI: &str = r#"[{"anonymous":false,"inputs":[{"indexed":true,"name":"operator","type":"address"},{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"id","type":"uint256"},{"indexed":false,"name":"value","type":"uint256"}],"name":"TransferSingle","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"operator","type":"address"},{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"ids","type":"uint256[]"},{"indexed":false,"name":"values","type":"uint256[]"}],"name":"TransferBatch","type":"event"}]"#;
+
 impl AlertSourceFilter for AlertEthSmartContractEvents {
     fn filter(&self, alert_source: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
         let ctx = alert_source.get_eth_log()?;
@@ -67,80 +79,65 @@ impl AlertSourceFilter for AlertEthSmartContractEvents {
 
 impl AlertSourceFilter for AlertEthMonitorFunds {
     fn filter(&self, alert_source: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
-        let ctx = alert_source.get_eth_tx()?;
-        let from = ctx.tx_resp.from.hash_string()?;
-
-        if ctx.tx_resp.status.unwrap_or_default().as_u64() == 0 {
-            return Err("transaction did not succeed".into());
+        if let Ok(transfer) = alert_source.get_eth_internal_transfer() {
+            return filter_internal_transfer(self, transfer);
         }
 
-        let address = self.address.to_ascii_lowercase();
+        // Every Transfer log of an included tx is notified on its own
+        if let Ok(ctx) = alert_source.get_eth_log() {
+            let mut transfer = erc20_transfer(&ctx.log)?.ok_or("log is not an erc20 transfer")?;
+            transfer.log_index = Some(
+                ctx.log
+                    .log_index
+                    .map(|i| i.as_u64())
+                    .unwrap_or(ctx.log_index),
+            );
+            return filter_token_transfer(self, transfer, ctx.tx_hash);
+        }
 
-        if ctx.tx.input.0.is_empty() && !ctx.tx.value.is_zero() {
-            let to = ctx
-                .tx_resp
-                .to
-                .ok_or("could not find to addr in eth tx")?
-                .hash_string()?;
-
-            let notification =
-                AlertNotificationData::MonitorFundsCoin(MonitorFundsCoinNotificationData {
-                    from: from.clone(),
-                    to: to.clone(),
-                    amount: vec![CoinAmount {
-                        amount: ctx.tx.value.to_string(),
-                        denom: String::new(),
-                    }],
-                    tx_hash: ctx.tx_hash,
-                    monitored_address: self.address.to_string(),
-                });
-
-            if address.is_empty() {
-                return Ok(notification);
-            }
+        filter_eth_tx(self, alert_source.get_eth_tx()?)
+    }
+}
 
-            if address == from || address == to {
-                return Ok(notification);
-            }
+/// Matches the funds moved by an eth tx, token transfers of included txs come in as logs of their
+/// own
+fn filter_eth_tx(
+    alert: &AlertEthMonitorFunds,
+    ctx: AlertSourceEthTx,
+) -> Result<AlertNotificationData> {
+    let from = match &ctx.tx_resp {
+        Some(tx_resp) => tx_resp.from,
+        None => ctx.tx.from.ok_or("could not find from addr in eth tx")?,
+    }
+    .hash_string()?;
 
-            return Err("address not related to coin call".into());
+    if let Some(tx_resp) = &ctx.tx_resp {
+        if tx_resp.status.unwrap_or_default().as_u64() == 0 {
+            return Err("transaction did not succeed".into());
         }
+    }
 
-        let input_data = ctx.tx.input.clone().0;
+    let address = alert.address.to_ascii_lowercase();
 
-        let erc_20 = web3::ethabi::Contract::load(ERC20_ABI.as_bytes())?;
-        let function = erc_20.function("transfer")?;
-        let sig = function.short_signature();
-        let args = function.decode_input(
-            input_data
-                .strip_prefix(&sig)
-                .ok_or("invalid function sig")?,
-        )?;
-        let to = args
-            .get(0)
-            .ok_or("could not get to address")?
-            .clone()
-            .into_address()
-            .ok_or("could not convert token into address")?
-            .hash_string()?;
-        let amount = args
-            .get(1)
-            .ok_or("could not get to address")?
-            .clone()
-            .into_uint()
-            .ok_or("could not convert token into address")?;
+    if ctx.tx.input.0.is_empty() && !ctx.tx.value.is_zero() {
+        let to = match &ctx.tx_resp {
+            Some(tx_resp) => tx_resp.to,
+            None => ctx.tx.to,
+        }
+        .ok_or("could not find to addr in eth tx")?
+        .hash_string()?;
 
         let notification =
-            AlertNotificationData::MonitorFundsToken(MonitorFundsTokenNotificationData {
+            AlertNotificationData::MonitorFundsCoin(MonitorFundsCoinNotificationData {
                 from: from.clone(),
                 to: to.clone(),
-                amount: amount.to_string(),
+                amount: vec![CoinAmount {
+                    amount: ctx.tx.value.to_string(),
+                    denom: String::new(),
+                }],
                 tx_hash: ctx.tx_hash,
-                contract_addr: ctx
-                    .tx
-                    .to
-                    .ok_or("could not find eth contract addr")?
-                    .hash_string()?,
+                monitored_address: alert.address.to_string(),
+                internal: false,
             });
 
         if address.is_empty() {
@@ -151,40 +148,320 @@ impl AlertSourceFilter for AlertEthMonitorFunds {
             return Ok(notification);
         }
 
-        Err("address not related to token call".into())
+        return Err("address not related to coin call".into());
+    }
+
+    if ctx.tx_resp.is_none() {
+        if let Some(transfer) = pending_erc20_transfer(&ctx)? {
+            return filter_token_transfer(alert, transfer, ctx.tx_hash);
+        }
+    }
+
+    Err("address not related to token transfers".into())
+}
+
+fn filter_token_transfer(
+    alert: &AlertEthMonitorFunds,
+    transfer: TokenTransfer,
+    tx_hash: String,
+) -> Result<AlertNotificationData> {
+    let address = alert.address.to_ascii_lowercase();
+    if !address.is_empty() && address != transfer.from && address != transfer.to {
+        return Err("address not related to token transfer".into());
+    }
+
+    Ok(AlertNotificationData::MonitorFundsToken(
+        MonitorFundsTokenNotificationData {
+            from: transfer.from,
+            to: transfer.to,
+            amount: transfer.amount.to_string(),
+            tx_hash,
+            contract_addr: transfer.contract,
+            action: transfer.action.to_string(),
+            log_index: transfer.log_index,
+        },
+    ))
+}
+
+/// ERC-20 tokens moved during an eth tx
+struct TokenTransfer {
+    action: &'static str,
+    contract: String,
+    from: String,
+    to: String,
+    amount: U256,
+    log_index: Option<u64>,
+}
+
+fn token_address(token: Option<&Token>) -> Result<String> {
+    token
+        .cloned()
+        .and_then(Token::into_address)
+        .ok_or("could not convert token into address")?
+        .hash_string()
+}
+
+fn token_amount(token: Option<&Token>) -> Result<U256> {
+    Ok(token
+        .cloned()
+        .and_then(Token::into_uint)
+        .ok_or("could not convert token into amount")?)
+}
+
+// Pending txs only have the calldata of a direct call to the token
+fn pending_erc20_transfer(ctx: &AlertSourceEthTx) -> Result<Option<TokenTransfer>> {
+    let erc_20 = web3::ethabi::Contract::load(ERC20_ABI.as_bytes())?;
+    let contract = match ctx.tx.to {
+        Some(to) => to.hash_string()?,
+        None => return Ok(None),
+    };
+    let input = ctx.tx.input.0.as_slice();
+    let transfer = erc_20.function("transfer")?;
+    if let Some(args) = input.strip_prefix(&transfer.short_signature()) {
+        let args = transfer.decode_input(args)?;
+        return Ok(Some(TokenTransfer {
+            action: "transfer",
+            contract,
+            from: token_address(ctx.tx.from.as_ref().map(|f| Token::Address(*f)).as_ref())?,
+            to: token_address(args.first())?,
+            amount: token_amount(args.get(1))?,
+            log_index: None,
+        }));
+    }
+    let transfer_from = erc_20.function("transferFrom")?;
+    if let Some(args) = input.strip_prefix(&transfer_from.short_signature()) {
+        let args = transfer_from.decode_input(args)?;
+        return Ok(Some(TokenTransfer {
+            action: "transfer_from",
+            contract,
+            from: token_address(args.first())?,
+            to: token_address(args.get(1))?,
+            amount: token_amount(args.get(2))?,
+            log_index: None,
+        }));
+    }
+
+    Ok(None)
+}
+
+// Transfer logs catch every movement whatever contract started it, None for any other log
+fn erc20_transfer(log: &Log) -> Result<Option<TokenTransfer>> {
+    let erc_20 = web3::ethabi::Contract::load(ERC20_ABI.as_bytes())?;
+    let event = erc_20.event("Transfer")?;
+    // ERC-721 transfers share the signature but index the token id as well
+    if log.topics.len() != 3 || log.topics.first() != Some(&event.signature()) {
+        return Ok(None);
+    }
+    let parsed = match event.parse_log(RawLog {
+        topics: log.topics.clone(),
+        data: log.data.0.clone(),
+    }) {
+        Ok(parsed) => parsed,
+        Err(_) => return Ok(None),
+    };
+    let param = |name: &str| {
+        parsed
+            .params
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| &p.value)
+    };
+
+    Ok(Some(TokenTransfer {
+        action: "transfer",
+        contract: log.address.hash_string()?,
+        from: token_address(param("from"))?,
+        to: token_address(param("to"))?,
+        amount: token_amount(param("value"))?,
+        log_index: log.log_index.map(|i| i.as_u64()),
+    }))
+}
+
+fn filter_internal_transfer(
+    alert: &AlertEthMonitorFunds,
+    transfer: AlertSourceEthInternalTransfer,
+) -> Result<AlertNotificationData> {
+    let from = transfer.from.hash_string()?;
+    let to = transfer.to.hash_string()?;
+    let address = alert.address.to_ascii_lowercase();
+    if !address.is_empty() && address != from && address != to {
+        return Err("address not related to internal transfer".into());
     }
+
+    Ok(AlertNotificationData::MonitorFundsCoin(
+        MonitorFundsCoinNotificationData {
+            from,
+            to,
+            amount: vec![CoinAmount {
+                amount: transfer.value.to_string(),
+                denom: String::new(),
+            }],
+            tx_hash: transfer.tx_hash,
+            monitored_address: alert.address.to_string(),
+            internal: true,
+        },
+    ))
 }
 
 impl AlertSourceFilter for AlertEthTxOutcome {
     fn filter(&self, alert_source: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
         let ctx = alert_source.get_eth_tx()?;
-        let from = ctx.tx_resp.from.hash_string()?;
+        let from = match &ctx.tx_resp {
+            Some(tx_resp) => tx_resp.from,
+            None => ctx.tx.from.ok_or("could not find from addr in eth tx")?,
+        }
+        .hash_string()?;
         let signer = self.signer.to_ascii_lowercase();
         if !signer.is_empty() && from != signer {
             return Err("signer mismatch".into());
         }
 
-        let status = ctx
-            .tx_resp
-            .status
-            .ok_or("could not find status code in eth tx")?
-            .as_u64();
-        let matched =
-            match EthTxOutcome::from_i32(self.outcome).ok_or("invalid i32 val for enum")? {
-                EthTxOutcome::Succeeded => status == 1,
-                EthTxOutcome::Failed => status == 0,
-            };
+        // The outcome of a pending tx is not known yet
+        let outcome = match &ctx.tx_resp {
+            Some(tx_resp) => {
+                let status = tx_resp
+                    .status
+                    .ok_or("could not find status code in eth tx")?
+                    .as_u64();
+                let matched =
+                    match EthTxOutcome::from_i32(self.outcome).ok_or("invalid i32 val for enum")? {
+                        EthTxOutcome::Succeeded => status == 1,
+                        EthTxOutcome::Failed => status == 0,
+                    };
 
-        if !matched {
-            return Err("tx outcome mismatch".into());
-        }
+                if !matched {
+                    return Err("tx outcome mismatch".into());
+                }
+
+                self.outcome().as_str_name().to_string()
+            }
+            None => "PENDING".to_string(),
+        };
 
         Ok(AlertNotificationData::TxOutcome(
             TxOutcomeNotificationData {
                 signer: self.signer.clone(),
-                outcome: self.outcome().as_str_name().to_string(),
+                outcome,
                 tx_hash: ctx.tx_hash,
             },
         ))
     }
 }
+
+impl AlertSourceFilter for AlertEthNftTransfers {
+    fn filter(&self, alert_source: &ProcessAlertSourceRequeust) -> Result<AlertNotificationData> {
+        let ctx = alert_source.get_eth_log()?;
+        let mut transfer = nft_transfer(&ctx.log)?.ok_or("log is not an nft transfer")?;
+
+        let contract_addr = self.contract_addr.to_ascii_lowercase();
+        if !contract_addr.is_empty() && contract_addr != transfer.contract_addr {
+            return Err("nft transfer does not belong to collection".into());
+        }
+
+        let address = self.address.to_ascii_lowercase();
+        if !address.is_empty() && address != transfer.from && address != transfer.to {
+            return Err("address not related to nft transfer".into());
+        }
+
+        if !self.token_id.is_empty() {
+            let token_id = match self.token_id.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16)?,
+                None => U256::from_dec_str(&self.token_id)?,
+            }
+            .to_string();
+            if !transfer.tokens.iter().any(|t| t.token_id == token_id) {
+                return Err("nft transfer did not move token".into());
+            }
+        }
+
+        transfer.tx_hash = ctx.tx_hash;
+        transfer.log_index = ctx
+            .log
+            .log_index
+            .map(|i| i.as_u64())
+            .unwrap_or(ctx.log_index);
+
+        Ok(AlertNotificationData::NftTransfer(transfer))
+    }
+}
+
+// Tokens moved by an ERC-721 or ERC-1155 transfer log, None for any other log
+fn nft_transfer(log: &Log) -> Result<Option<NftTransferNotificationData>> {
+    let erc_721 = web3::ethabi::Contract::load(ERC721_ABI.as_bytes())?;
+    let erc_1155 = web3::ethabi::Contract::load(ERC1155_ABI.as_bytes())?;
+    let signature = match log.topics.first() {
+        Some(signature) => *signature,
+        None => return Ok(None),
+    };
+
+    let (standard, event) = match [
+        ("ERC721", erc_721.event("Transfer")?),
+        ("ERC1155", erc_1155.event("TransferSingle")?),
+        ("ERC1155", erc_1155.event("TransferBatch")?),
+    ]
+    .into_iter()
+    .find(|(_, event)| {
+        event.signature() == signature && log.topics.len() == indexed_inputs(event) + 1
+    }) {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+
+    let parsed = event.parse_log(RawLog {
+        topics: log.topics.clone(),
+        data: log.data.0.clone(),
+    })?;
+    let param = |name: &str| -> Result<Token> {
+        Ok(parsed
+            .params
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or("could not find nft transfer param")?
+            .value
+            .clone())
+    };
+    let address = |name: &str| -> Result<String> {
+        param(name)?
+            .into_address()
+            .ok_or("could not convert token into address")?
+            .hash_string()
+    };
+    let uints = |name: &str| -> Result<Vec<U256>> {
+        match param(name)? {
+            Token::Array(tokens) => tokens
+                .into_iter()
+                .map(|t| Ok(t.into_uint().ok_or("could not convert token into uint")?))
+                .collect(),
+            token => Ok(vec![token
+                .into_uint()
+                .ok_or("could not convert token into uint")?]),
+        }
+    };
+
+    let (ids, amounts, operator) = match event.name.as_str() {
+        "Transfer" => (uints("tokenId")?, vec![U256::one()], "".to_string()),
+        "TransferSingle" => (uints("id")?, uints("value")?, address("operator")?),
+        _ => (uints("ids")?, uints("values")?, address("operator")?),
+    };
+
+    Ok(Some(NftTransferNotificationData {
+        standard: standard.to_string(),
+        contract_addr: log.address.hash_string()?,
+        from: address("from")?,
+        to: address("to")?,
+        tokens: ids
+            .into_iter()
+            .zip(amounts)
+            .map(|(token_id, amount)| TokenAmount {
+                token_id: token_id.to_string(),
+                amount: amount.to_string(),
+            })
+            .collect(),
+        operator,
+        ..Default::default()
+    }))
+}
+
+fn indexed_inputs(event: &web3::ethabi::Event) -> usize {
+    event.inputs.iter().filter(|i| i.indexed).count()
+}
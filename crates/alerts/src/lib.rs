@@ -6,6 +6,7 @@ use mempools_api::api::{
 use util::{service_registry::ProcessAlertSourceRequeust, Result};
 
 pub mod cosmos;
+pub mod cosmwasm;
 pub mod eth;
 
 pub trait AlertSourceFilter: Send + Sync {
@@ -33,6 +34,14 @@ impl TryInto<Box<dyn AlertSourceFilter>> for UserAlert {
                         Box::new(a)
                     }
                     mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosTxOutcome(a) => Box::new(a),
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosBlockEvents(a) => Box::new(a),
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosIbcPacket(a) => Box::new(a),
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosGovernance(a) => Box::new(a),
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosStaking(a) => Box::new(a),
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosValidator(a) => Box::new(a),
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosCw721Transfers(a) => {
+                        Box::new(a)
+                    }
                 }
             }
             mempools_api::api::alert::ChainAlert::CosmosEvmAlert(a) => {
@@ -53,6 +62,24 @@ impl TryInto<Box<dyn AlertSourceFilter>> for UserAlert {
                     mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosTxOutcome(a) => {
                         Box::new(a)
                     }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosBlockEvents(a) => {
+                        Box::new(a)
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosIbcPacket(a) => {
+                        Box::new(a)
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosGovernance(a) => {
+                        Box::new(a)
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosStaking(a) => {
+                        Box::new(a)
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosValidator(a) => {
+                        Box::new(a)
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertEthNftTransfers(a) => {
+                        Box::new(a)
+                    }
                 }
             }
             mempools_api::api::alert::ChainAlert::EthAlert(a) => {
@@ -65,6 +92,7 @@ impl TryInto<Box<dyn AlertSourceFilter>> for UserAlert {
                     }
                     mempools_api::api::eth_alert::EthAlert::AlertEthMonitorFunds(a) => Box::new(a),
                     mempools_api::api::eth_alert::EthAlert::AlertEthTxOutcome(a) => Box::new(a),
+                    mempools_api::api::eth_alert::EthAlert::AlertEthNftTransfers(a) => Box::new(a),
                 }
             }
             mempools_api::api::alert::ChainAlert::ArchwayBroadcastAlert(a) => Box::new(a),
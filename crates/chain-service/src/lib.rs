@@ -1,9 +1,12 @@
 use mempools_api::api::{
-    CreateChainRequest, CreateChainResponse, GetChainsResponse, UpdateChainRequest,
-    UpdateChainResponse,
+    ChainData, CosmosChainData, CreateChainRequest, CreateChainResponse, GetChainsResponse,
+    UpdateChainRequest, UpdateChainResponse,
 };
 use util::{
-    clients::new_eth_client, clients::CosmosClient, service_registry::ChainServiceInterface,
+    clients::new_eth_client,
+    clients::{CometRpcClient, CosmosClient},
+    endpoints::endpoint_urls,
+    service_registry::ChainServiceInterface,
 };
 
 use util::Result;
@@ -29,44 +32,27 @@ impl ChainServiceInterface for ChainService {
     }
     /// Admin endpoints
     async fn create_chain(&self, req: &CreateChainRequest) -> Result<CreateChainResponse> {
-        match req
-            .clone()
-            .chain_data
-            .ok_or("could not find chain data")?
-            .chain_data
-            .ok_or("could not find chain data")?
-        {
-            mempools_api::api::chain_data::ChainData::CosmosChainData(data) => {
-                CosmosClient::new(data.grpc_endpoint.clone()).await?;
-            }
-            mempools_api::api::chain_data::ChainData::CosmosEvmChainData(data) => {
-                CosmosClient::new(
-                    data.cosmos_chain_data
-                        .ok_or("could not get cosmos chain data")?
-                        .grpc_endpoint
-                        .clone(),
-                )
-                .await?;
-                new_eth_client(
-                    &data
-                        .eth_chain_data
-                        .ok_or("could not get eth chain data")?
-                        .eth_rpc_endpoint,
-                )
-                .await?;
-            }
-            mempools_api::api::chain_data::ChainData::EthChainData(data) => {
-                new_eth_client(&data.eth_rpc_endpoint).await?;
-            }
-        }
+        let mut req = req.clone();
+        check_chain_data(req.chain_data.as_mut().ok_or("could not find chain data")?).await?;
 
         Ok(CreateChainResponse {
-            chain: Some(self.store.create_chain(req).await?),
+            chain: Some(self.store.create_chain(&req).await?),
         })
     }
     async fn update_chain(&self, req: &UpdateChainRequest) -> Result<UpdateChainResponse> {
+        let mut req = req.clone();
+        check_chain_data(
+            req.chain
+                .as_mut()
+                .ok_or("could not find chain")?
+                .chain_data
+                .as_mut()
+                .ok_or("could not find chain data")?,
+        )
+        .await?;
+
         Ok(UpdateChainResponse {
-            chain: Some(self.store.update_chain(req).await?),
+            chain: Some(self.store.update_chain(&req).await?),
         })
     }
 }
@@ -78,3 +64,63 @@ impl ChainService {
         }
     }
 }
+
+// Chains are only stored once their endpoints answer
+async fn check_chain_data(chain_data: &mut ChainData) -> Result<()> {
+    match chain_data
+        .chain_data
+        .as_mut()
+        .ok_or("could not find chain data")?
+    {
+        mempools_api::api::chain_data::ChainData::CosmosChainData(data) => {
+            check_cosmos_endpoints(data).await?;
+        }
+        mempools_api::api::chain_data::ChainData::CosmosEvmChainData(data) => {
+            check_cosmos_endpoints(
+                data.cosmos_chain_data
+                    .as_mut()
+                    .ok_or("could not get cosmos chain data")?,
+            )
+            .await?;
+            new_eth_client(
+                data.eth_chain_data
+                    .as_ref()
+                    .ok_or("could not get eth chain data")?,
+            )
+            .await?;
+        }
+        mempools_api::api::chain_data::ChainData::EthChainData(data) => {
+            new_eth_client(data).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Every grpc endpoint has to answer, as the client keeps unreachable ones around. Also fills in
+// the network id when it was left empty
+async fn check_cosmos_endpoints(data: &mut CosmosChainData) -> Result<()> {
+    for url in endpoint_urls(&data.grpc_endpoint, &data.fallback_grpc_endpoints) {
+        let client = CosmosClient::new(&CosmosChainData {
+            grpc_endpoint: url.clone(),
+            fallback_grpc_endpoints: vec![],
+            ..data.clone()
+        })
+        .await?;
+        let network_id = client
+            .network_id()
+            .await
+            .map_err(|err| format!("grpc endpoint {} is unreachable - {}", url, err))?;
+        if data.network_id.is_empty() {
+            data.network_id = network_id;
+        }
+    }
+    if data.watch_mempool && data.rpc_endpoint.is_empty() {
+        return Err("watching the mempool requires an rpc endpoint".into());
+    }
+    if !data.rpc_endpoint.is_empty() {
+        CometRpcClient::new(&data.rpc_endpoint).await?;
+    }
+
+    Ok(())
+}
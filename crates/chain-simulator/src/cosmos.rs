@@ -0,0 +1,1269 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::Engine;
+use cosmrs::{
+    proto::{
+        cosmos::{
+            authz::v1beta1::MsgExec,
+            bank::v1beta1::MsgSend,
+            base::{
+                abci::v1beta1::{AbciMessageLog, Attribute, StringEvent, TxResponse},
+                query::v1beta1::PageResponse,
+                tendermint::v1beta1::{
+                    self as tm,
+                    service_server::{
+                        Service as TendermintService, ServiceServer as TendermintServer,
+                    },
+                },
+                v1beta1::Coin,
+            },
+            crypto::ed25519,
+            gov::v1beta1::{
+                self as gov,
+                query_server::{Query as GovQuery, QueryServer as GovServer},
+                Proposal, TallyResult, VoteOption,
+            },
+            staking::v1beta1::{
+                self as staking,
+                query_server::{Query as StakingQuery, QueryServer as StakingServer},
+                MsgDelegate, Validator,
+            },
+            tx::v1beta1::{
+                self as tx,
+                service_server::{Service as TxService, ServiceServer as TxServer},
+                AuthInfo, Tx, TxBody,
+            },
+        },
+        cosmwasm::wasm::v1::MsgExecuteContract,
+        ibc::{
+            core::{
+                channel::v1::{QueryChannelClientStateRequest, QueryChannelClientStateResponse},
+                client::v1::IdentifiedClientState,
+            },
+            lightclients::tendermint::v1::ClientState,
+        },
+        tendermint::{
+            abci::{Event, EventAttribute},
+            types::{Block, Data, Header},
+        },
+        traits::Message,
+        Any,
+    },
+    tendermint::Time,
+    AccountId,
+};
+use hyper::{Body, Request, Response, StatusCode};
+use mempools_api::api::CosmosChainData;
+use secp256k1::SecretKey;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{
+    codegen::{self, http, BoxFuture, Context, Poll, Service, StdError},
+    server::{Grpc, NamedService, UnaryService},
+    transport::Server,
+    Status,
+};
+use util::{
+    ethermint::{self, LegacyTx, MsgEthereumTx},
+    get_sha256_hash,
+    nested::{CosmosTx, InterchainAccountPacketData, MsgSendTx, MSG_ICA_SEND_TX},
+    service_registry::IbcPacket,
+    Result,
+};
+use web3::{
+    signing::{Key, SecretKeyRef},
+    types::{Address, Log, U256},
+};
+
+use super::serve_http;
+
+const EVM_CHAIN_ID: u64 = 9000;
+// Txs returned per GetBlockWithTxs or GetTxsEvent page when the request does not set a limit
+const DEFAULT_PAGE_LIMIT: u64 = 100;
+
+/// Tx to include in a simulated block
+#[derive(Clone, Default)]
+pub struct SimulatedCosmosTx {
+    pub tx: Tx,
+    /// Non zero codes mark the tx as failed
+    pub code: u32,
+    /// Per msg logs of a successful tx, one empty log per msg is used when left empty
+    pub logs: Vec<AbciMessageLog>,
+}
+
+impl SimulatedCosmosTx {
+    pub fn new(messages: Vec<Any>) -> Self {
+        Self {
+            tx: Tx {
+                body: Some(TxBody {
+                    messages,
+                    ..Default::default()
+                }),
+                auth_info: Some(AuthInfo::default()),
+                signatures: vec![],
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn bank_send(from: &str, to: &str, amount: u64, denom: &str) -> Self {
+        Self::new(vec![Any {
+            type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(),
+            value: MsgSend {
+                from_address: from.to_string(),
+                to_address: to.to_string(),
+                amount: vec![Coin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                }],
+            }
+            .encode_to_vec(),
+        }])
+    }
+
+    /// Ethermint tx sending `value` of the evm denom from the evm account at `from`, signed the
+    /// way wallets sign evm txs
+    pub fn ethereum_transfer(from: u8, nonce: u64, to: Address, value: U256) -> Self {
+        let mut tx = LegacyTx {
+            nonce,
+            gas_price: "1000000000".to_string(),
+            gas: 21_000,
+            to: format!("{:#x}", to),
+            value: value.to_string(),
+            v: trim_be_bytes(EVM_CHAIN_ID * 2 + 35),
+            ..Default::default()
+        };
+        let data = |tx: &LegacyTx| Any {
+            type_url: ethermint::LEGACY_TX.to_string(),
+            value: tx.encode_to_vec(),
+        };
+
+        let signing_hash = ethermint::signing_hash(&data(&tx)).unwrap();
+        let signature = SecretKeyRef::new(&evm_key(from))
+            .sign(signing_hash.as_bytes(), Some(EVM_CHAIN_ID))
+            .unwrap();
+        tx.v = trim_be_bytes(signature.v);
+        tx.r = signature.r.as_bytes().to_vec();
+        tx.s = signature.s.as_bytes().to_vec();
+
+        Self::new(vec![Any {
+            type_url: ethermint::MSG_ETHEREUM_TX.to_string(),
+            value: MsgEthereumTx {
+                hash: format!("{:#x}", ethermint::tx_hash(&data(&tx)).unwrap()),
+                data: Some(data(&tx)),
+                ..Default::default()
+            }
+            .encode_to_vec(),
+        }])
+    }
+
+    /// Has the evm tx of an Ethermint tx emit `logs`, each as a `tx_log` attribute of its msg log
+    pub fn with_evm_logs(mut self, logs: Vec<Log>) -> Self {
+        self.logs = vec![AbciMessageLog {
+            msg_index: 0,
+            log: "".to_string(),
+            events: vec![StringEvent {
+                r#type: "tx_log".to_string(),
+                attributes: logs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, log)| Attribute {
+                        key: "txLog".to_string(),
+                        value: json!({
+                            "address": log.address,
+                            "topics": log.topics,
+                            "data": base64::prelude::BASE64_STANDARD.encode(log.data.0),
+                            "logIndex": i,
+                        })
+                        .to_string(),
+                    })
+                    .collect(),
+            }],
+        }];
+        self
+    }
+
+    pub fn delegate(delegator: &str, validator: &str, amount: u64, denom: &str) -> Self {
+        Self::new(vec![Any {
+            type_url: "/cosmos.staking.v1beta1.MsgDelegate".to_string(),
+            value: MsgDelegate {
+                delegator_address: delegator.to_string(),
+                validator_address: validator.to_string(),
+                amount: Some(Coin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                }),
+            }
+            .encode_to_vec(),
+        }])
+    }
+
+    /// Contract execution whose contracts emitted `wasm_attributes`, each contract's attributes
+    /// starting with its `_contract_address` the way the msg log merges them
+    pub fn wasm_execute(
+        sender: &str,
+        contract: &str,
+        msg: Value,
+        wasm_attributes: Vec<(&str, &str)>,
+    ) -> Self {
+        let mut tx = Self::new(vec![Any {
+            type_url: "/cosmwasm.wasm.v1.MsgExecuteContract".to_string(),
+            value: MsgExecuteContract {
+                sender: sender.to_string(),
+                contract: contract.to_string(),
+                msg: msg.to_string().into_bytes(),
+                funds: vec![],
+            }
+            .encode_to_vec(),
+        }]);
+        tx.logs = vec![AbciMessageLog {
+            msg_index: 0,
+            log: "".to_string(),
+            events: vec![StringEvent {
+                r#type: "wasm".to_string(),
+                attributes: wasm_attributes
+                    .into_iter()
+                    .map(|(key, value)| Attribute {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+            }],
+        }];
+        tx
+    }
+
+    /// Authz exec of the msgs of `tx` by `grantee` on behalf of their signers
+    pub fn authz_exec(grantee: &str, tx: SimulatedCosmosTx) -> Self {
+        Self::new(vec![Any {
+            type_url: "/cosmos.authz.v1beta1.MsgExec".to_string(),
+            value: MsgExec {
+                grantee: grantee.to_string(),
+                msgs: tx.tx.body.map(|b| b.messages).unwrap_or_default(),
+            }
+            .encode_to_vec(),
+        }])
+    }
+
+    /// Interchain account tx sending the msgs of `tx` to be run on the host chain of `owner`
+    pub fn ica_send_tx(owner: &str, connection_id: &str, tx: SimulatedCosmosTx) -> Self {
+        let messages = tx.tx.body.map(|b| b.messages).unwrap_or_default();
+        Self::new(vec![Any {
+            type_url: MSG_ICA_SEND_TX.to_string(),
+            value: MsgSendTx {
+                owner: owner.to_string(),
+                connection_id: connection_id.to_string(),
+                packet_data: Some(InterchainAccountPacketData {
+                    r#type: 1,
+                    data: CosmosTx { messages }.encode_to_vec(),
+                    memo: "".to_string(),
+                }),
+            }
+            .encode_to_vec(),
+        }])
+    }
+
+    /// v1beta1 vote on a proposal
+    pub fn vote(voter: &str, proposal_id: u64, option: VoteOption) -> Self {
+        Self::new(vec![Any {
+            type_url: "/cosmos.gov.v1beta1.MsgVote".to_string(),
+            value: gov::MsgVote {
+                proposal_id,
+                voter: voter.to_string(),
+                option: option as i32,
+            }
+            .encode_to_vec(),
+        }])
+    }
+
+    /// Transfer msg that sent `packet` when `received` is false, otherwise the relayed msg that
+    /// received it on the other chain. The packet shows up in the msg log like ibc-go emits it
+    pub fn ibc_transfer(packet: &IbcPacket, received: bool) -> Self {
+        let (type_url, event_type) = match received {
+            false => ("/ibc.applications.transfer.v1.MsgTransfer", "send_packet"),
+            true => ("/ibc.core.channel.v1.MsgRecvPacket", "recv_packet"),
+        };
+        let packet_data = json!({
+            "sender": packet.sender,
+            "receiver": packet.receiver,
+            "denom": packet.denom,
+            "amount": packet.amount,
+        });
+        let attributes = [
+            ("packet_data", packet_data.to_string()),
+            ("packet_sequence", packet.sequence.to_string()),
+            ("packet_src_port", packet.src_port.clone()),
+            ("packet_src_channel", packet.src_channel.clone()),
+            ("packet_dst_port", packet.dst_port.clone()),
+            ("packet_dst_channel", packet.dst_channel.clone()),
+        ];
+
+        let mut tx = Self::new(vec![Any {
+            type_url: type_url.to_string(),
+            value: vec![],
+        }]);
+        tx.logs = vec![AbciMessageLog {
+            msg_index: 0,
+            log: "".to_string(),
+            events: vec![StringEvent {
+                r#type: event_type.to_string(),
+                attributes: attributes
+                    .into_iter()
+                    .map(|(key, value)| Attribute {
+                        key: key.to_string(),
+                        value,
+                    })
+                    .collect(),
+            }],
+        }];
+        tx
+    }
+}
+
+fn validator_consensus_key(index: u8) -> Vec<u8> {
+    vec![index; 32]
+}
+
+fn evm_key(index: u8) -> SecretKey {
+    let mut key = [1; 32];
+    key[31] = index;
+    SecretKey::from_slice(&key).unwrap()
+}
+
+// Big endian bytes without leading zeros, as ethermint stores signature values
+fn trim_be_bytes(val: u64) -> Vec<u8> {
+    val.to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect()
+}
+
+#[derive(Default)]
+struct CosmosState {
+    blocks: Vec<CosmosBlock>,
+    mempool: Vec<Vec<u8>>,
+    // Final tallies of the proposals whose voting ended
+    tallies: HashMap<u64, TallyResult>,
+    validators: Vec<Validator>,
+    // Network on the other end of each (port, channel)
+    channels: HashMap<(String, String), String>,
+    // Msg logs of new txs are only reported as events, like Cosmos SDK 0.50 and later do
+    events_only_logs: bool,
+    get_tx_requests: usize,
+}
+
+struct CosmosBlock {
+    block: Block,
+    tx_responses: Vec<TxResponse>,
+    end_block_events: Vec<StringEvent>,
+}
+
+impl CosmosState {
+    fn block(&self, height: i64) -> Option<&CosmosBlock> {
+        usize::try_from(height - 1)
+            .ok()
+            .and_then(|i| self.blocks.get(i))
+    }
+}
+
+fn block_not_found(height: i64) -> Status {
+    Status::not_found(format!("block {} not found", height))
+}
+
+/// Cosmos node serving the tendermint, tx, gov, staking and IBC channel grpc services along with the
+/// CometBFT rpc endpoints the crawler uses. Starts with an empty block at height 1
+#[derive(Clone)]
+pub struct SimulatedCosmosChain {
+    state: Arc<RwLock<CosmosState>>,
+    bech32_prefix: String,
+    network_id: String,
+    grpc_addr: SocketAddr,
+    rpc_addr: SocketAddr,
+}
+
+impl SimulatedCosmosChain {
+    pub async fn start(bech32_prefix: &str) -> Result<Self> {
+        let state = Arc::new(RwLock::new(CosmosState::default()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let grpc_addr = listener.local_addr()?;
+        let node = CosmosNode {
+            state: state.clone(),
+        };
+        let grpc_server = Server::builder()
+            .add_service(TendermintServer::new(node.clone()))
+            .add_service(TxServer::new(node.clone()))
+            .add_service(IbcChannelServer(node.clone()))
+            .add_service(GovServer::new(node.clone()))
+            .add_service(StakingServer::new(node.clone()))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        tokio::spawn(grpc_server);
+
+        let rpc_addr = serve_http(move |req| {
+            let node = node.clone();
+            async move { node.handle_rpc(req) }
+        })?;
+
+        let chain = Self {
+            state,
+            bech32_prefix: bech32_prefix.to_string(),
+            network_id: format!("{}-simulated-1", bech32_prefix),
+            grpc_addr,
+            rpc_addr,
+        };
+        chain.push_block(vec![]);
+
+        Ok(chain)
+    }
+
+    /// Chain data pointing the crawler at this node
+    pub fn chain_data(&self) -> CosmosChainData {
+        CosmosChainData {
+            grpc_endpoint: self.grpc_addr.to_string(),
+            bech32_prefix: self.bech32_prefix.clone(),
+            rpc_endpoint: format!("http://{}", self.rpc_addr),
+            ..Default::default()
+        }
+    }
+
+    /// Chain data pointing the crawler at the grpc endpoint only, like chains without a public
+    /// rpc endpoint
+    pub fn grpc_chain_data(&self) -> CosmosChainData {
+        CosmosChainData {
+            rpc_endpoint: "".to_string(),
+            ..self.chain_data()
+        }
+    }
+
+    /// Reports the msg logs of the txs in new blocks the way Cosmos SDK 0.50 and later do, only
+    /// as tx events tagged with the index of their msg
+    pub fn use_events_only_logs(&self) {
+        self.state.write().unwrap().events_only_logs = true;
+    }
+
+    /// Number of txs fetched one by one through GetTx so far
+    pub fn get_tx_requests(&self) -> usize {
+        self.state.read().unwrap().get_tx_requests
+    }
+
+    /// Chain id in the block headers, derived from the bech32 prefix
+    pub fn network_id(&self) -> &str {
+        &self.network_id
+    }
+
+    /// Opens `port`/`channel` on this chain with `counterparty` on its other end
+    pub fn open_channel(&self, port: &str, channel: &str, counterparty: &SimulatedCosmosChain) {
+        self.state.write().unwrap().channels.insert(
+            (port.to_string(), channel.to_string()),
+            counterparty.network_id.clone(),
+        );
+    }
+
+    /// Bech32 address of a made up account, the same index always gives the same address
+    pub fn account(&self, index: u8) -> String {
+        AccountId::new(&self.bech32_prefix, &[index; 20])
+            .map(|a| a.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Address of a made up evm account that can sign ethermint txs, the same index always
+    /// gives the same address
+    pub fn evm_account(index: u8) -> Address {
+        SecretKeyRef::new(&evm_key(index)).address()
+    }
+
+    /// Appends a block with `txs` and returns its height, the txs leave the mempool
+    pub fn push_block(&self, txs: Vec<SimulatedCosmosTx>) -> i64 {
+        self.push_block_with_events(txs, vec![])
+    }
+
+    /// Appends a block like `push_block` that also emits `end_block_events`
+    pub fn push_block_with_events(
+        &self,
+        txs: Vec<SimulatedCosmosTx>,
+        end_block_events: Vec<StringEvent>,
+    ) -> i64 {
+        let mut state = self.state.write().unwrap();
+        let height = state.blocks.len() as i64 + 1;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let time = Time::from_unix_timestamp(now.as_secs() as i64, now.subsec_nanos()).unwrap();
+
+        let mut raw_txs = vec![];
+        let mut tx_responses = vec![];
+        for tx in txs {
+            let raw_tx = tx.tx.encode_to_vec();
+            tx_responses.push(tx_response(
+                height,
+                &time,
+                &raw_tx,
+                tx,
+                state.events_only_logs,
+            ));
+            raw_txs.push(raw_tx);
+        }
+        state.mempool.retain(|raw_tx| !raw_txs.contains(raw_tx));
+
+        state.blocks.push(CosmosBlock {
+            block: Block {
+                header: Some(Header {
+                    chain_id: self.network_id.clone(),
+                    height,
+                    time: Some(time.into()),
+                    ..Default::default()
+                }),
+                data: Some(Data { txs: raw_txs }),
+                ..Default::default()
+            },
+            tx_responses,
+            end_block_events,
+        });
+
+        height
+    }
+
+    /// Adds a validator to the validator set and returns its operator address, the same index
+    /// always gives the same validator
+    pub fn add_validator(&self, index: u8) -> String {
+        let operator_address =
+            AccountId::new(&format!("{}valoper", self.bech32_prefix), &[index; 20])
+                .map(|a| a.to_string())
+                .unwrap_or_default();
+        self.state.write().unwrap().validators.push(Validator {
+            operator_address: operator_address.clone(),
+            consensus_pubkey: Some(Any {
+                type_url: "/cosmos.crypto.ed25519.PubKey".to_string(),
+                value: ed25519::PubKey {
+                    key: validator_consensus_key(index),
+                }
+                .encode_to_vec(),
+            }),
+            ..Default::default()
+        });
+
+        operator_address
+    }
+
+    /// Jails a validator added with `add_validator` for downtime in a new block and returns its
+    /// height
+    pub fn jail_validator(&self, index: u8) -> i64 {
+        let hash = get_sha256_hash(&validator_consensus_key(index));
+        let cons_address = AccountId::new(
+            &format!("{}valcons", self.bech32_prefix),
+            &hex::decode(&hash[..40]).unwrap(),
+        )
+        .map(|a| a.to_string())
+        .unwrap_or_default();
+        let attributes = [
+            ("address", cons_address.clone()),
+            ("power", "100".to_string()),
+            ("reason", "missing_signature".to_string()),
+            ("jailed", cons_address),
+        ];
+
+        self.push_block_with_events(
+            vec![],
+            vec![StringEvent {
+                r#type: "slash".to_string(),
+                attributes: attributes
+                    .into_iter()
+                    .map(|(key, value)| Attribute {
+                        key: key.to_string(),
+                        value,
+                    })
+                    .collect(),
+            }],
+        )
+    }
+
+    /// Ends the voting period of a proposal with `tally` in a new block and returns its height
+    pub fn end_voting(&self, proposal_id: u64, result: &str, tally: TallyResult) -> i64 {
+        self.state
+            .write()
+            .unwrap()
+            .tallies
+            .insert(proposal_id, tally);
+
+        self.push_block_with_events(
+            vec![],
+            vec![StringEvent {
+                r#type: "active_proposal".to_string(),
+                attributes: vec![
+                    Attribute {
+                        key: "proposal_id".to_string(),
+                        value: proposal_id.to_string(),
+                    },
+                    Attribute {
+                        key: "proposal_result".to_string(),
+                        value: result.to_string(),
+                    },
+                ],
+            }],
+        )
+    }
+
+    /// Adds a tx to the mempool until it is included in a block
+    pub fn push_pending_tx(&self, tx: SimulatedCosmosTx) {
+        self.state
+            .write()
+            .unwrap()
+            .mempool
+            .push(tx.tx.encode_to_vec());
+    }
+}
+
+fn tx_response(
+    height: i64,
+    time: &Time,
+    raw_tx: &[u8],
+    tx: SimulatedCosmosTx,
+    events_only_logs: bool,
+) -> TxResponse {
+    let messages = tx.tx.body.map(|b| b.messages).unwrap_or_default();
+    let mut logs = tx.logs;
+    if tx.code == 0 && logs.is_empty() {
+        logs = (0..messages.len())
+            .map(|i| AbciMessageLog {
+                msg_index: i as u32,
+                ..Default::default()
+            })
+            .collect();
+    }
+    // Every msg emits a message event, which is all that tags msgs without events of their own
+    // with their index on Cosmos SDK 0.50 and later
+    if events_only_logs {
+        for (log, msg) in logs.iter_mut().zip(&messages) {
+            log.events.insert(
+                0,
+                StringEvent {
+                    r#type: "message".to_string(),
+                    attributes: vec![Attribute {
+                        key: "action".to_string(),
+                        value: msg.type_url.clone(),
+                    }],
+                },
+            );
+        }
+    }
+
+    // Cosmos SDK versions before 0.50 report the msg logs as json in the raw log
+    let raw_log = if events_only_logs {
+        "".to_string()
+    } else if tx.code == 0 {
+        json!(logs
+            .iter()
+            .map(|log| json!({
+                "msg_index": log.msg_index,
+                "log": log.log,
+                "events": log.events.iter().map(|e| json!({
+                    "type": e.r#type,
+                    "attributes": e.attributes.iter().map(|a| json!({
+                        "key": a.key,
+                        "value": a.value,
+                    })).collect::<Vec<_>>(),
+                })).collect::<Vec<_>>(),
+            }))
+            .collect::<Vec<_>>())
+        .to_string()
+    } else {
+        format!("simulated failure with code {}", tx.code)
+    };
+    let events = logs
+        .iter()
+        .flat_map(|log| log.events.iter().map(|e| (log.msg_index, e)))
+        .map(|(msg_index, e)| {
+            let mut attributes: Vec<EventAttribute> = e
+                .attributes
+                .iter()
+                .map(|a| EventAttribute {
+                    key: a.key.clone().into(),
+                    value: a.value.clone().into(),
+                    index: true,
+                })
+                .collect();
+            if events_only_logs {
+                attributes.push(EventAttribute {
+                    key: "msg_index".into(),
+                    value: msg_index.to_string().into(),
+                    index: true,
+                });
+            }
+            Event {
+                r#type: e.r#type.clone(),
+                attributes,
+            }
+        })
+        .collect();
+
+    TxResponse {
+        height,
+        txhash: get_sha256_hash(raw_tx).to_uppercase(),
+        code: tx.code,
+        raw_log,
+        logs: if tx.code == 0 && !events_only_logs {
+            logs
+        } else {
+            vec![]
+        },
+        gas_wanted: 200_000,
+        gas_used: 100_000,
+        tx: Some(Any {
+            type_url: "/cosmos.tx.v1beta1.Tx".to_string(),
+            value: raw_tx.to_vec(),
+        }),
+        timestamp: time.to_rfc3339(),
+        events,
+        ..Default::default()
+    }
+}
+
+#[derive(Clone)]
+struct CosmosNode {
+    state: Arc<RwLock<CosmosState>>,
+}
+
+impl CosmosNode {
+    fn handle_rpc(&self, req: Request<Body>) -> Response<Body> {
+        let query: HashMap<&str, &str> = req
+            .uri()
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .collect();
+
+        let result = match req.uri().path().trim_start_matches('/') {
+            "status" => Ok(json!({ "node_info": { "version": "0.37.0" } })),
+            "block_results" => self.block_results(&query),
+            "unconfirmed_txs" => Ok(self.unconfirmed_txs(&query)),
+            path => {
+                return Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::from(format!("{} not found", path)))
+                    .unwrap()
+            }
+        };
+
+        let body = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": -1, "result": result }),
+            Err(err) => json!({ "jsonrpc": "2.0", "id": -1, "error": err }),
+        };
+        Response::new(Body::from(body.to_string()))
+    }
+
+    fn block_results(&self, query: &HashMap<&str, &str>) -> std::result::Result<Value, String> {
+        let state = self.state.read().unwrap();
+        let height = query
+            .get("height")
+            .and_then(|h| h.parse().ok())
+            .unwrap_or(state.blocks.len() as i64);
+        let block = state
+            .block(height)
+            .ok_or(format!("block {} not found", height))?;
+
+        let txs_results: Vec<Value> = block
+            .tx_responses
+            .iter()
+            .map(|res| {
+                json!({
+                    "code": res.code,
+                    "data": "",
+                    "log": res.raw_log,
+                    "info": res.info,
+                    "gas_wanted": res.gas_wanted.to_string(),
+                    "gas_used": res.gas_used.to_string(),
+                    "events": res.events.iter().map(|e| json!({
+                        "type": e.r#type,
+                        "attributes": e.attributes.iter().map(|a| json!({
+                            "key": String::from_utf8_lossy(&a.key),
+                            "value": String::from_utf8_lossy(&a.value),
+                            "index": a.index,
+                        })).collect::<Vec<_>>(),
+                    })).collect::<Vec<_>>(),
+                    "codespace": res.codespace,
+                })
+            })
+            .collect();
+
+        Ok(json!({
+            "height": height.to_string(),
+            "txs_results": txs_results,
+            "begin_block_events": [],
+            "end_block_events": block.end_block_events.iter().map(|e| json!({
+                "type": e.r#type,
+                "attributes": e.attributes.iter().map(|a| json!({
+                    "key": a.key,
+                    "value": a.value,
+                    "index": true,
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        }))
+    }
+
+    fn unconfirmed_txs(&self, query: &HashMap<&str, &str>) -> Value {
+        let state = self.state.read().unwrap();
+        let limit = query
+            .get("limit")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(30);
+        let page: usize = query.get("page").and_then(|p| p.parse().ok()).unwrap_or(1);
+        let txs: Vec<String> = state
+            .mempool
+            .iter()
+            .skip(page.saturating_sub(1) * limit)
+            .take(limit)
+            .map(|tx| base64::engine::general_purpose::STANDARD.encode(tx))
+            .collect();
+
+        json!({
+            "n_txs": txs.len().to_string(),
+            "total": state.mempool.len().to_string(),
+            "txs": txs,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl TendermintService for CosmosNode {
+    async fn get_node_info(
+        &self,
+        _: tonic::Request<tm::GetNodeInfoRequest>,
+    ) -> std::result::Result<tonic::Response<tm::GetNodeInfoResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn get_syncing(
+        &self,
+        _: tonic::Request<tm::GetSyncingRequest>,
+    ) -> std::result::Result<tonic::Response<tm::GetSyncingResponse>, Status> {
+        Ok(tonic::Response::new(tm::GetSyncingResponse {
+            syncing: false,
+        }))
+    }
+
+    async fn get_latest_block(
+        &self,
+        _: tonic::Request<tm::GetLatestBlockRequest>,
+    ) -> std::result::Result<tonic::Response<tm::GetLatestBlockResponse>, Status> {
+        let state = self.state.read().unwrap();
+        let height = state.blocks.len() as i64;
+        let block = state.block(height).ok_or_else(|| block_not_found(height))?;
+
+        Ok(tonic::Response::new(tm::GetLatestBlockResponse {
+            block_id: None,
+            block: Some(block.block.clone()),
+        }))
+    }
+
+    async fn get_block_by_height(
+        &self,
+        req: tonic::Request<tm::GetBlockByHeightRequest>,
+    ) -> std::result::Result<tonic::Response<tm::GetBlockByHeightResponse>, Status> {
+        let state = self.state.read().unwrap();
+        let height = req.get_ref().height;
+        let block = state.block(height).ok_or_else(|| block_not_found(height))?;
+
+        Ok(tonic::Response::new(tm::GetBlockByHeightResponse {
+            block_id: None,
+            block: Some(block.block.clone()),
+        }))
+    }
+
+    async fn get_latest_validator_set(
+        &self,
+        _: tonic::Request<tm::GetLatestValidatorSetRequest>,
+    ) -> std::result::Result<tonic::Response<tm::GetLatestValidatorSetResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn get_validator_set_by_height(
+        &self,
+        _: tonic::Request<tm::GetValidatorSetByHeightRequest>,
+    ) -> std::result::Result<tonic::Response<tm::GetValidatorSetByHeightResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+}
+
+#[tonic::async_trait]
+impl TxService for CosmosNode {
+    async fn simulate(
+        &self,
+        _: tonic::Request<tx::SimulateRequest>,
+    ) -> std::result::Result<tonic::Response<tx::SimulateResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn get_tx(
+        &self,
+        req: tonic::Request<tx::GetTxRequest>,
+    ) -> std::result::Result<tonic::Response<tx::GetTxResponse>, Status> {
+        let hash = &req.get_ref().hash;
+        let mut state = self.state.write().unwrap();
+        state.get_tx_requests += 1;
+        let tx_response = state
+            .blocks
+            .iter()
+            .flat_map(|b| b.tx_responses.iter())
+            .find(|res| res.txhash.eq_ignore_ascii_case(hash))
+            .ok_or_else(|| Status::not_found(format!("tx {} not found", hash)))?;
+        let raw_tx = &tx_response.tx.as_ref().unwrap().value;
+
+        Ok(tonic::Response::new(tx::GetTxResponse {
+            tx: Some(Tx::decode(raw_tx.as_slice()).map_err(|e| Status::internal(e.to_string()))?),
+            tx_response: Some(tx_response.clone()),
+        }))
+    }
+
+    async fn broadcast_tx(
+        &self,
+        _: tonic::Request<tx::BroadcastTxRequest>,
+    ) -> std::result::Result<tonic::Response<tx::BroadcastTxResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    // Only the tx.height query of the crawler is simulated
+    async fn get_txs_event(
+        &self,
+        req: tonic::Request<tx::GetTxsEventRequest>,
+    ) -> std::result::Result<tonic::Response<tx::GetTxsEventResponse>, Status> {
+        let state = self.state.read().unwrap();
+        let height: i64 = req
+            .get_ref()
+            .events
+            .iter()
+            .find_map(|e| e.strip_prefix("tx.height="))
+            .and_then(|h| h.parse().ok())
+            .ok_or_else(|| Status::invalid_argument("only tx.height queries are simulated"))?;
+        let block = state.block(height).ok_or_else(|| block_not_found(height))?;
+
+        let (offset, limit) = match &req.get_ref().pagination {
+            Some(page) if page.limit > 0 => (page.offset, page.limit),
+            Some(page) => (page.offset, DEFAULT_PAGE_LIMIT),
+            None => (0, DEFAULT_PAGE_LIMIT),
+        };
+        let mut txs = vec![];
+        let mut tx_responses = vec![];
+        for tx_response in block
+            .tx_responses
+            .iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+        {
+            let raw_tx = &tx_response.tx.as_ref().unwrap().value;
+            txs.push(Tx::decode(raw_tx.as_slice()).map_err(|e| Status::internal(e.to_string()))?);
+            tx_responses.push(tx_response.clone());
+        }
+
+        Ok(tonic::Response::new(tx::GetTxsEventResponse {
+            txs,
+            tx_responses,
+            pagination: Some(PageResponse {
+                next_key: vec![],
+                total: block.tx_responses.len() as u64,
+            }),
+        }))
+    }
+
+    async fn get_block_with_txs(
+        &self,
+        req: tonic::Request<tx::GetBlockWithTxsRequest>,
+    ) -> std::result::Result<tonic::Response<tx::GetBlockWithTxsResponse>, Status> {
+        let state = self.state.read().unwrap();
+        let height = req.get_ref().height;
+        let block = state.block(height).ok_or_else(|| block_not_found(height))?;
+        let raw_txs = &block.block.data.as_ref().unwrap().txs;
+
+        let (offset, limit) = match &req.get_ref().pagination {
+            Some(page) if page.limit > 0 => (page.offset, page.limit),
+            Some(page) => (page.offset, DEFAULT_PAGE_LIMIT),
+            None => (0, DEFAULT_PAGE_LIMIT),
+        };
+        let mut txs = vec![];
+        for raw_tx in raw_txs.iter().skip(offset as usize).take(limit as usize) {
+            txs.push(Tx::decode(raw_tx.as_slice()).map_err(|e| Status::internal(e.to_string()))?);
+        }
+
+        Ok(tonic::Response::new(tx::GetBlockWithTxsResponse {
+            txs,
+            block_id: None,
+            block: Some(block.block.clone()),
+            pagination: Some(PageResponse {
+                next_key: vec![],
+                total: raw_txs.len() as u64,
+            }),
+        }))
+    }
+}
+
+// Only the validator set is simulated
+#[tonic::async_trait]
+impl StakingQuery for CosmosNode {
+    async fn validators(
+        &self,
+        _: tonic::Request<staking::QueryValidatorsRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryValidatorsResponse>, Status> {
+        Ok(tonic::Response::new(staking::QueryValidatorsResponse {
+            validators: self.state.read().unwrap().validators.clone(),
+            pagination: None,
+        }))
+    }
+
+    async fn validator(
+        &self,
+        _: tonic::Request<staking::QueryValidatorRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryValidatorResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn validator_delegations(
+        &self,
+        _: tonic::Request<staking::QueryValidatorDelegationsRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryValidatorDelegationsResponse>, Status>
+    {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn validator_unbonding_delegations(
+        &self,
+        _: tonic::Request<staking::QueryValidatorUnbondingDelegationsRequest>,
+    ) -> std::result::Result<
+        tonic::Response<staking::QueryValidatorUnbondingDelegationsResponse>,
+        Status,
+    > {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn delegation(
+        &self,
+        _: tonic::Request<staking::QueryDelegationRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryDelegationResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn unbonding_delegation(
+        &self,
+        _: tonic::Request<staking::QueryUnbondingDelegationRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryUnbondingDelegationResponse>, Status>
+    {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn delegator_delegations(
+        &self,
+        _: tonic::Request<staking::QueryDelegatorDelegationsRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryDelegatorDelegationsResponse>, Status>
+    {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn delegator_unbonding_delegations(
+        &self,
+        _: tonic::Request<staking::QueryDelegatorUnbondingDelegationsRequest>,
+    ) -> std::result::Result<
+        tonic::Response<staking::QueryDelegatorUnbondingDelegationsResponse>,
+        Status,
+    > {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn redelegations(
+        &self,
+        _: tonic::Request<staking::QueryRedelegationsRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryRedelegationsResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn delegator_validators(
+        &self,
+        _: tonic::Request<staking::QueryDelegatorValidatorsRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryDelegatorValidatorsResponse>, Status>
+    {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn delegator_validator(
+        &self,
+        _: tonic::Request<staking::QueryDelegatorValidatorRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryDelegatorValidatorResponse>, Status>
+    {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn historical_info(
+        &self,
+        _: tonic::Request<staking::QueryHistoricalInfoRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryHistoricalInfoResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn pool(
+        &self,
+        _: tonic::Request<staking::QueryPoolRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryPoolResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn params(
+        &self,
+        _: tonic::Request<staking::QueryParamsRequest>,
+    ) -> std::result::Result<tonic::Response<staking::QueryParamsResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+}
+
+// Only the v1beta1 gov module is simulated, like on chains from before Cosmos SDK 0.46
+#[tonic::async_trait]
+impl GovQuery for CosmosNode {
+    async fn proposal(
+        &self,
+        req: tonic::Request<gov::QueryProposalRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryProposalResponse>, Status> {
+        let proposal_id = req.get_ref().proposal_id;
+        let tally = self
+            .state
+            .read()
+            .unwrap()
+            .tallies
+            .get(&proposal_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("proposal {} not found", proposal_id)))?;
+
+        Ok(tonic::Response::new(gov::QueryProposalResponse {
+            proposal: Some(Proposal {
+                proposal_id,
+                final_tally_result: Some(tally),
+                ..Default::default()
+            }),
+        }))
+    }
+
+    async fn proposals(
+        &self,
+        _: tonic::Request<gov::QueryProposalsRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryProposalsResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn vote(
+        &self,
+        _: tonic::Request<gov::QueryVoteRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryVoteResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn votes(
+        &self,
+        _: tonic::Request<gov::QueryVotesRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryVotesResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn params(
+        &self,
+        _: tonic::Request<gov::QueryParamsRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryParamsResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn deposit(
+        &self,
+        _: tonic::Request<gov::QueryDepositRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryDepositResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn deposits(
+        &self,
+        _: tonic::Request<gov::QueryDepositsRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryDepositsResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+
+    async fn tally_result(
+        &self,
+        _: tonic::Request<gov::QueryTallyResultRequest>,
+    ) -> std::result::Result<tonic::Response<gov::QueryTallyResultResponse>, Status> {
+        Err(Status::unimplemented("not simulated"))
+    }
+}
+
+// cosmos-sdk-proto does not generate servers for the IBC services, so the one channel query the
+// crawler makes is routed by hand
+#[derive(Clone)]
+struct IbcChannelServer(CosmosNode);
+
+impl NamedService for IbcChannelServer {
+    const NAME: &'static str = "ibc.core.channel.v1.Query";
+}
+
+impl<B> Service<http::Request<B>> for IbcChannelServer
+where
+    B: codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let node = self.0.clone();
+        Box::pin(async move {
+            if req.uri().path() != "/ibc.core.channel.v1.Query/ChannelClientState" {
+                return Ok(Status::unimplemented("not simulated").to_http());
+            }
+            Ok(Grpc::new(tonic::codec::ProstCodec::default())
+                .unary(node, req)
+                .await)
+        })
+    }
+}
+
+impl UnaryService<QueryChannelClientStateRequest> for CosmosNode {
+    type Response = QueryChannelClientStateResponse;
+    type Future = BoxFuture<tonic::Response<Self::Response>, Status>;
+
+    fn call(&mut self, req: tonic::Request<QueryChannelClientStateRequest>) -> Self::Future {
+        let req = req.into_inner();
+        let network_id = self
+            .state
+            .read()
+            .unwrap()
+            .channels
+            .get(&(req.port_id.clone(), req.channel_id.clone()))
+            .cloned();
+        Box::pin(async move {
+            let network_id = network_id.ok_or_else(|| {
+                Status::not_found(format!(
+                    "channel {}/{} not found",
+                    req.port_id, req.channel_id
+                ))
+            })?;
+
+            Ok(tonic::Response::new(QueryChannelClientStateResponse {
+                identified_client_state: Some(IdentifiedClientState {
+                    client_id: "07-tendermint-0".to_string(),
+                    client_state: Some(Any {
+                        type_url: "/ibc.lightclients.tendermint.v1.ClientState".to_string(),
+                        value: ClientState {
+                            chain_id: network_id,
+                            ..Default::default()
+                        }
+                        .encode_to_vec(),
+                    }),
+                }),
+                proof: vec![],
+                proof_height: None,
+            }))
+        })
+    }
+}
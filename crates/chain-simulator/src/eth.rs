@@ -0,0 +1,470 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{body::to_bytes, Body, Request, Response};
+use jsonrpc_core::{IoHandler, Params};
+use mempools_api::api::EthChainData;
+use serde_json::{json, Value};
+use util::Result;
+use web3::{
+    ethabi::{encode, Token},
+    signing::keccak256,
+    types::{Address, Block, Bytes, Log, Transaction, TransactionReceipt, H256, U256, U64},
+};
+
+use super::serve_http;
+
+const CHAIN_ID: u64 = 1337;
+const TX_GAS: u64 = 21_000;
+
+/// Tx to include in a simulated block
+#[derive(Clone, Default)]
+pub struct SimulatedEthTx {
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub input: Bytes,
+    pub failed: bool,
+    /// Only the address, topics and data are used, the rest is filled in from the block
+    pub logs: Vec<Log>,
+    /// Value sent by contracts during the tx, served through the tracing methods
+    pub internal_transfers: Vec<SimulatedInternalTransfer>,
+}
+
+#[derive(Clone, Default)]
+pub struct SimulatedInternalTransfer {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    /// The call reverted, so the value never moved
+    pub reverted: bool,
+}
+
+impl SimulatedEthTx {
+    pub fn transfer(from: Address, to: Address, value: U256) -> Self {
+        Self {
+            from,
+            to: Some(to),
+            value,
+            ..Default::default()
+        }
+    }
+}
+
+/// ERC-20 Transfer log of `value` tokens emitted by the `token` contract
+pub fn erc20_transfer_log(token: Address, from: Address, to: Address, value: U256) -> Log {
+    let mut data = [0u8; 32];
+    value.to_big_endian(&mut data);
+    Log {
+        address: token,
+        topics: vec![
+            H256(keccak256(b"Transfer(address,address,uint256)")),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: Bytes(data.to_vec()),
+        block_hash: None,
+        block_number: None,
+        transaction_hash: None,
+        transaction_index: None,
+        log_index: None,
+        transaction_log_index: None,
+        log_type: None,
+        removed: None,
+    }
+}
+
+/// ERC-1155 TransferBatch log of the `collection` contract moving each token id and amount
+pub fn erc1155_transfer_batch_log(
+    collection: Address,
+    operator: Address,
+    from: Address,
+    to: Address,
+    tokens: Vec<(U256, U256)>,
+) -> Log {
+    let (ids, amounts) = tokens
+        .into_iter()
+        .map(|(id, amount)| (Token::Uint(id), Token::Uint(amount)))
+        .unzip();
+    Log {
+        address: collection,
+        topics: vec![
+            H256(keccak256(
+                b"TransferBatch(address,address,address,uint256[],uint256[])",
+            )),
+            H256::from(operator),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: Bytes(encode(&[Token::Array(ids), Token::Array(amounts)])),
+        block_hash: None,
+        block_number: None,
+        transaction_hash: None,
+        transaction_index: None,
+        log_index: None,
+        transaction_log_index: None,
+        log_type: None,
+        removed: None,
+    }
+}
+
+#[derive(Default)]
+struct EthState {
+    // Blocks dropped by a reorg are no longer served, as with most providers
+    blocks: Vec<Block<Transaction>>,
+    // Number of reorgs so far, part of the block hash so replaced blocks get a new one
+    forks: u64,
+    receipts: HashMap<H256, TransactionReceipt>,
+    internal_transfers: HashMap<H256, Vec<SimulatedInternalTransfer>>,
+    pending: Vec<Transaction>,
+    // Distinguishes the hashes of otherwise identical txs
+    tx_count: u64,
+    traces_disabled: bool,
+}
+
+impl EthState {
+    fn new_tx(&mut self, tx: &SimulatedEthTx) -> Transaction {
+        self.tx_count += 1;
+        Transaction {
+            hash: H256(keccak256(&self.tx_count.to_be_bytes())),
+            nonce: self.tx_count.into(),
+            from: Some(tx.from),
+            to: tx.to,
+            value: tx.value,
+            gas_price: Some(1_000_000_000u64.into()),
+            gas: TX_GAS.into(),
+            input: tx.input.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn block_by_tag(&self, tag: &str) -> Option<&Block<Transaction>> {
+        match tag {
+            "latest" | "safe" | "finalized" | "pending" => self.blocks.last(),
+            "earliest" => self.blocks.first(),
+            number => {
+                let number = u64::from_str_radix(number.trim_start_matches("0x"), 16).ok()?;
+                self.blocks.get(number as usize)
+            }
+        }
+    }
+
+    fn block_by_hash(&self, hash: H256) -> Option<&Block<Transaction>> {
+        self.blocks.iter().find(|b| b.hash == Some(hash))
+    }
+}
+
+/// Ethereum node serving the json rpc methods the crawler uses. Starts with an empty block at
+/// height 0
+#[derive(Clone)]
+pub struct SimulatedEthChain {
+    state: Arc<RwLock<EthState>>,
+    addr: SocketAddr,
+}
+
+impl SimulatedEthChain {
+    pub async fn start() -> Result<Self> {
+        let state = Arc::new(RwLock::new(EthState::default()));
+        let io = Arc::new(rpc_handler(state.clone()));
+
+        let addr = serve_http(move |req: Request<Body>| {
+            let io = io.clone();
+            async move {
+                let body = to_bytes(req.into_body()).await.unwrap_or_default();
+                let res = io
+                    .handle_request(&String::from_utf8_lossy(&body))
+                    .await
+                    .unwrap_or_default();
+                Response::new(Body::from(res))
+            }
+        })?;
+
+        let chain = Self { state, addr };
+        chain.push_block(vec![]);
+
+        Ok(chain)
+    }
+
+    /// Chain data pointing the crawler at this node
+    pub fn chain_data(&self) -> EthChainData {
+        EthChainData {
+            eth_rpc_endpoint: format!("http://{}", self.addr),
+            ..Default::default()
+        }
+    }
+
+    /// Address of a made up account, the same index always gives the same address
+    pub fn account(index: u64) -> Address {
+        Address::from_low_u64_be(index)
+    }
+
+    /// Appends a block with `txs` and returns its hash along with the hashes of the txs. The
+    /// txs leave the mempool
+    pub fn push_block(&self, txs: Vec<SimulatedEthTx>) -> (H256, Vec<H256>) {
+        let mut state = self.state.write().unwrap();
+        let number = state.blocks.len() as u64;
+        let hash = H256(keccak256(
+            format!("block-{}-{}", number, state.forks).as_bytes(),
+        ));
+        let parent_hash = state.blocks.last().and_then(|b| b.hash).unwrap_or_default();
+
+        let mut transactions = vec![];
+        for (i, tx) in txs.iter().enumerate() {
+            let mut transaction = state.new_tx(tx);
+            transaction.block_hash = Some(hash);
+            transaction.block_number = Some(number.into());
+            transaction.transaction_index = Some(i.into());
+
+            let logs = tx
+                .logs
+                .iter()
+                .enumerate()
+                .map(|(log_index, log)| Log {
+                    block_hash: Some(hash),
+                    block_number: Some(number.into()),
+                    transaction_hash: Some(transaction.hash),
+                    transaction_index: Some(i.into()),
+                    log_index: Some(log_index.into()),
+                    transaction_log_index: Some(log_index.into()),
+                    removed: Some(false),
+                    ..log.clone()
+                })
+                .collect();
+            state.receipts.insert(
+                transaction.hash,
+                TransactionReceipt {
+                    transaction_hash: transaction.hash,
+                    transaction_index: i.into(),
+                    block_hash: Some(hash),
+                    block_number: Some(number.into()),
+                    from: tx.from,
+                    to: tx.to,
+                    cumulative_gas_used: (TX_GAS * (i as u64 + 1)).into(),
+                    gas_used: Some(TX_GAS.into()),
+                    logs,
+                    status: Some(if tx.failed { 0 } else { 1 }.into()),
+                    effective_gas_price: transaction.gas_price,
+                    ..Default::default()
+                },
+            );
+            state
+                .internal_transfers
+                .insert(transaction.hash, tx.internal_transfers.clone());
+            transactions.push(transaction);
+        }
+        let tx_hashes: Vec<H256> = transactions.iter().map(|tx| tx.hash).collect();
+        state.pending.retain(|tx| !tx_hashes.contains(&tx.hash));
+
+        state.blocks.push(Block {
+            hash: Some(hash),
+            parent_hash,
+            number: Some(number.into()),
+            gas_used: (TX_GAS * transactions.len() as u64).into(),
+            gas_limit: 30_000_000u64.into(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+                .into(),
+            transactions,
+            ..Default::default()
+        });
+
+        (hash, tx_hashes)
+    }
+
+    /// Answers trace requests the way nodes without the debug and trace namespaces do
+    pub fn disable_traces(&self) {
+        self.state.write().unwrap().traces_disabled = true;
+    }
+
+    /// Drops the newest `depth` blocks, the blocks pushed afterwards replace them with new hashes
+    pub fn reorg(&self, depth: usize) {
+        let mut state = self.state.write().unwrap();
+        let height = state.blocks.len().saturating_sub(depth);
+        state.blocks.truncate(height);
+        state.forks += 1;
+    }
+
+    /// Adds a tx to the mempool and returns its hash. Pending txs are mined by `push_block`
+    /// as new txs, so they get a new hash once included
+    pub fn push_pending_tx(&self, tx: SimulatedEthTx) -> H256 {
+        let mut state = self.state.write().unwrap();
+        let transaction = state.new_tx(&tx);
+        let hash = transaction.hash;
+        state.pending.push(transaction);
+
+        hash
+    }
+}
+
+fn rpc_handler(state: Arc<RwLock<EthState>>) -> IoHandler {
+    let mut io = IoHandler::new();
+
+    io.add_sync_method("eth_chainId", |_| Ok(json!(U64::from(CHAIN_ID))));
+    io.add_sync_method("net_version", |_| Ok(json!(CHAIN_ID.to_string())));
+
+    let s = state.clone();
+    io.add_sync_method("eth_blockNumber", move |_| {
+        let state = s.read().unwrap();
+        Ok(json!(U64::from(state.blocks.len().saturating_sub(1))))
+    });
+
+    let s = state.clone();
+    io.add_sync_method("eth_getBlockByNumber", move |params: Params| {
+        let (tag, full): (String, bool) = params.parse()?;
+        let state = s.read().unwrap();
+        Ok(block_json(state.block_by_tag(&tag), full))
+    });
+
+    let s = state.clone();
+    io.add_sync_method("eth_getBlockByHash", move |params: Params| {
+        let (hash, full): (H256, bool) = params.parse()?;
+        let state = s.read().unwrap();
+        Ok(block_json(state.block_by_hash(hash), full))
+    });
+
+    let s = state.clone();
+    io.add_sync_method("eth_getBlockReceipts", move |params: Params| {
+        let (hash,): (H256,) = params.parse()?;
+        let state = s.read().unwrap();
+        let receipts: Option<Vec<&TransactionReceipt>> = state.block_by_hash(hash).map(|block| {
+            block
+                .transactions
+                .iter()
+                .filter_map(|tx| state.receipts.get(&tx.hash))
+                .collect()
+        });
+        Ok(json!(receipts))
+    });
+
+    let s = state.clone();
+    io.add_sync_method("eth_getTransactionReceipt", move |params: Params| {
+        let (hash,): (H256,) = params.parse()?;
+        Ok(json!(s.read().unwrap().receipts.get(&hash)))
+    });
+
+    let s = state.clone();
+    io.add_sync_method("eth_getTransactionByHash", move |params: Params| {
+        let (hash,): (H256,) = params.parse()?;
+        let state = s.read().unwrap();
+        let tx = state
+            .blocks
+            .iter()
+            .flat_map(|b| b.transactions.iter())
+            .chain(state.pending.iter())
+            .find(|tx| tx.hash == hash);
+        Ok(json!(tx))
+    });
+
+    let s = state.clone();
+    io.add_sync_method("debug_traceBlockByHash", move |params: Params| {
+        let (hash, _): (H256, Value) = params.parse()?;
+        let state = s.read().unwrap();
+        if state.traces_disabled {
+            return Err(jsonrpc_core::Error::method_not_found());
+        }
+        let block = state
+            .block_by_hash(hash)
+            .ok_or_else(|| jsonrpc_core::Error::invalid_params("block not found"))?;
+        let traces: Vec<Value> = block
+            .transactions
+            .iter()
+            .map(|tx| {
+                let calls: Vec<Value> = state.internal_transfers[&tx.hash]
+                    .iter()
+                    .map(|t| {
+                        json!({
+                            "type": "CALL",
+                            "from": t.from,
+                            "to": t.to,
+                            "value": t.value,
+                            "error": t.reverted.then_some("execution reverted"),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "txHash": tx.hash,
+                    "result": {
+                        "type": if tx.to.is_some() { "CALL" } else { "CREATE" },
+                        "from": tx.from,
+                        "to": tx.to,
+                        "value": tx.value,
+                        "calls": calls,
+                    },
+                })
+            })
+            .collect();
+        Ok(json!(traces))
+    });
+
+    let s = state.clone();
+    io.add_sync_method("trace_block", move |params: Params| {
+        let (tag,): (String,) = params.parse()?;
+        let state = s.read().unwrap();
+        if state.traces_disabled {
+            return Err(jsonrpc_core::Error::method_not_found());
+        }
+        let block = state
+            .block_by_tag(&tag)
+            .ok_or_else(|| jsonrpc_core::Error::invalid_params("block not found"))?;
+        let mut traces = vec![];
+        for tx in &block.transactions {
+            let trace = |from, to, value, trace_address: Vec<usize>, reverted: bool| {
+                json!({
+                    "type": "call",
+                    "action": { "callType": "call", "from": from, "to": to, "value": value },
+                    "blockHash": block.hash,
+                    "blockNumber": block.number,
+                    "transactionHash": tx.hash,
+                    "traceAddress": trace_address,
+                    "error": reverted.then_some("Reverted"),
+                })
+            };
+            traces.push(trace(tx.from, tx.to, tx.value, vec![], false));
+            for (i, t) in state.internal_transfers[&tx.hash].iter().enumerate() {
+                traces.push(trace(
+                    Some(t.from),
+                    Some(t.to),
+                    t.value,
+                    vec![i],
+                    t.reverted,
+                ));
+            }
+        }
+        Ok(json!(traces))
+    });
+
+    io.add_sync_method("txpool_content", move |_| {
+        let state = state.read().unwrap();
+        let mut pending: BTreeMap<Address, BTreeMap<String, &Transaction>> = BTreeMap::new();
+        for tx in &state.pending {
+            pending
+                .entry(tx.from.unwrap_or_default())
+                .or_default()
+                .insert(tx.nonce.to_string(), tx);
+        }
+        Ok(json!({ "pending": pending, "queued": {} }))
+    });
+
+    io
+}
+
+fn block_json(block: Option<&Block<Transaction>>, full: bool) -> Value {
+    let block = match block {
+        Some(block) => block,
+        None => return Value::Null,
+    };
+
+    let mut value = json!(block);
+    if !full {
+        let hashes: Vec<H256> = block.transactions.iter().map(|tx| tx.hash).collect();
+        value["transactions"] = json!(hashes);
+    }
+
+    value
+}
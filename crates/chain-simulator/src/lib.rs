@@ -0,0 +1,47 @@
+//! In-process stand-ins for chain nodes that serve scripted blocks, so the crawlers can run
+//! without network access in tests and local development
+
+use std::{convert::Infallible, future::Future, net::SocketAddr};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use log::warn;
+use util::Result;
+
+pub use cosmos::{SimulatedCosmosChain, SimulatedCosmosTx};
+pub use eth::{
+    erc1155_transfer_batch_log, erc20_transfer_log, SimulatedEthChain, SimulatedEthTx,
+    SimulatedInternalTransfer,
+};
+
+mod cosmos;
+mod eth;
+
+// Serves `handler` over http on a random local port
+fn serve_http<F, Fut>(handler: F) -> Result<SocketAddr>
+where
+    F: Fn(Request<Body>) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Response<Body>> + Send + 'static,
+{
+    let make_svc = make_service_fn(move |_| {
+        let handler = handler.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let res = handler(req);
+                async move { Ok::<_, Infallible>(res.await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&"127.0.0.1:0".parse()?)?.serve(make_svc);
+    let addr = server.local_addr();
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            warn!("simulated node on {} stopped - {}", addr, err)
+        }
+    });
+
+    Ok(addr)
+}
@@ -0,0 +1,184 @@
+use std::{future::Future, sync::Arc};
+
+use log::warn;
+use mempools_api::api::{
+    chain_data::ChainData, crawler_data::CrawlerData, CosmosCrawlerData, CosmosEvmCrawlerData,
+    EthCrawlerData, FailedBlock,
+};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use util::{pipeline::PipelineConfig, Result};
+
+// Number of blocks processed before the crawler cursor is persisted
+pub const BLOCK_BATCH_SIZE: u64 = 50;
+// Failed blocks are kept around after this many attempts but no longer retried automatically
+pub const MAX_BLOCK_RETRIES: u32 = 10;
+
+/// Limits how many blocks and txs a single chain crawler fetches at once
+#[derive(Clone)]
+pub struct CrawlerWorkers {
+    pub blocks: Arc<Semaphore>,
+    pub txs: Arc<Semaphore>,
+}
+
+impl CrawlerWorkers {
+    pub fn new(config: &PipelineConfig) -> Self {
+        Self {
+            blocks: Arc::new(Semaphore::new(config.block_workers.max(1))),
+            txs: Arc::new(Semaphore::new(config.tx_workers.max(1))),
+        }
+    }
+}
+
+/// Waits for every task in the set, returning the first error encountered
+pub async fn join_all(mut tasks: JoinSet<Result<()>>) -> Result<()> {
+    let mut res = Ok(());
+    while let Some(task_res) = tasks.join_next().await {
+        if let Err(err) = task_res? {
+            if res.is_ok() {
+                res = Err(err);
+            }
+        }
+    }
+
+    res
+}
+
+/// Processes the given blocks concurrently and returns the error of every block that failed
+pub async fn process_blocks<F, Fut>(
+    chain_id: &str,
+    workers: &CrawlerWorkers,
+    heights: Vec<u64>,
+    process_block: F,
+) -> Vec<(u64, String)>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let mut tasks = JoinSet::new();
+    for height in heights {
+        let permit = match workers.blocks.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(err) => {
+                tasks.spawn(async move { (height, Err(err.into())) });
+                continue;
+            }
+        };
+        let fut = process_block(height);
+        tasks.spawn(async move {
+            let res = fut.await;
+            drop(permit);
+            (height, res)
+        });
+    }
+
+    let mut failures = vec![];
+    while let Some(task_res) = tasks.join_next().await {
+        let (height, err) = match task_res {
+            Ok((_, Ok(()))) => continue,
+            Ok((height, Err(err))) => (height, err.to_string()),
+            Err(err) => {
+                warn!("block task in chain {} panicked - {}", chain_id, err);
+                continue;
+            }
+        };
+
+        warn!(
+            "failed to execute block {} in chain {} - {}",
+            height, chain_id, err
+        );
+        failures.push((height, err));
+    }
+
+    failures
+}
+
+/// Splits `[from_height, to_height]` into batches of at most `BLOCK_BATCH_SIZE` heights
+pub fn block_batches(from_height: u64, to_height: u64) -> impl Iterator<Item = Vec<u64>> {
+    (from_height..=to_height)
+        .step_by(BLOCK_BATCH_SIZE as usize)
+        .map(move |start| (start..=to_height.min(start + BLOCK_BATCH_SIZE - 1)).collect())
+}
+
+/// Heights of the failed blocks that are still eligible for an automatic retry
+pub fn retryable_blocks(failed_blocks: &[FailedBlock]) -> Vec<u64> {
+    failed_blocks
+        .iter()
+        .filter(|b| b.attempts < MAX_BLOCK_RETRIES)
+        .map(|b| b.height)
+        .collect()
+}
+
+/// Merges the outcome of processing `attempted` into the failed block list - blocks that
+/// succeeded are dropped, blocks that failed are added or have their attempts bumped
+pub fn update_failed_blocks(
+    failed_blocks: &mut Vec<FailedBlock>,
+    attempted: &[u64],
+    failures: Vec<(u64, String)>,
+) {
+    failed_blocks
+        .retain(|b| !attempted.contains(&b.height) || failures.iter().any(|(h, _)| *h == b.height));
+
+    for (height, error) in failures {
+        if let Some(block) = failed_blocks.iter_mut().find(|b| b.height == height) {
+            block.attempts += 1;
+            block.error = error;
+        } else {
+            failed_blocks.push(FailedBlock {
+                height,
+                attempts: 1,
+                error,
+                ..Default::default()
+            });
+        }
+    }
+
+    failed_blocks.sort_by_key(|b| b.height);
+}
+
+/// Crawler state that continues crawling at `height`. Failed blocks below it are carried over
+/// from `prev`, the rest will be crawled again or were skipped on purpose
+pub fn crawler_data_at(
+    chain_data: &ChainData,
+    prev: Option<CrawlerData>,
+    height: u64,
+) -> CrawlerData {
+    let processed_blocks = height.saturating_sub(1);
+    let failed_below = |failed_blocks: Vec<FailedBlock>| -> Vec<FailedBlock> {
+        failed_blocks
+            .into_iter()
+            .filter(|b| b.height < height)
+            .collect()
+    };
+    let cosmos = |prev: Option<CosmosCrawlerData>| CosmosCrawlerData {
+        processed_blocks,
+        failed_blocks: failed_below(prev.map(|p| p.failed_blocks).unwrap_or_default()),
+    };
+    // Blocks kept for reorg detection no longer line up with the cursor
+    let eth = |prev: Option<EthCrawlerData>| EthCrawlerData {
+        processed_blocks,
+        recent_blocks: vec![],
+        failed_blocks: failed_below(prev.map(|p| p.failed_blocks).unwrap_or_default()),
+    };
+
+    match (chain_data, prev) {
+        (ChainData::CosmosChainData(_), Some(CrawlerData::Cosmos(prev))) => {
+            CrawlerData::Cosmos(cosmos(Some(prev)))
+        }
+        (ChainData::CosmosChainData(_), _) => CrawlerData::Cosmos(cosmos(None)),
+        (ChainData::EthChainData(_), Some(CrawlerData::Ethereum(prev))) => {
+            CrawlerData::Ethereum(eth(Some(prev)))
+        }
+        (ChainData::EthChainData(_), _) => CrawlerData::Ethereum(eth(None)),
+        (ChainData::CosmosEvmChainData(_), Some(CrawlerData::CosmosEvm(prev))) => {
+            CrawlerData::CosmosEvm(CosmosEvmCrawlerData {
+                cosmos: Some(cosmos(prev.cosmos)),
+                ethereum: Some(eth(prev.ethereum)),
+            })
+        }
+        (ChainData::CosmosEvmChainData(_), _) => CrawlerData::CosmosEvm(CosmosEvmCrawlerData {
+            cosmos: Some(cosmos(None)),
+            ethereum: Some(eth(None)),
+        }),
+    }
+}
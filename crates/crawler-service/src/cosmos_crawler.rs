@@ -1,30 +1,143 @@
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use cosmrs::proto::cosmos::{
-    base::tendermint::v1beta1::{GetBlockByHeightRequest, GetLatestBlockRequest},
-    tx::v1beta1::GetTxRequest,
+use cosmrs::{
+    proto::{
+        cosmos::{
+            base::{
+                abci::v1beta1::{AbciMessageLog, StringEvent, TxResponse},
+                query::v1beta1::PageRequest,
+                tendermint::v1beta1::{GetBlockByHeightRequest, GetLatestBlockRequest},
+            },
+            tx::v1beta1::{GetBlockWithTxsRequest, GetTxRequest, Tx},
+        },
+        traits::Message,
+        Any,
+    },
+    tendermint::Time,
+    AccountId,
 };
 use log::warn;
-use mempools_api::api::{crawler_data::CrawlerData, Chain, CosmosChainData, CosmosCrawlerData};
+use mempools_api::api::{
+    alert_cosmos_governance::Event as GovernanceEvent, alert_cosmos_ibc_packet::Stage, chain_data,
+    crawler_data::CrawlerData, BackendUserAlert, Chain, CosmosChainData, CosmosCrawlerData,
+    FailedBlock,
+};
 
 use util::{
-    clients::CosmosClient,
-    get_sha256_hash,
+    clients::{msg_logs_from_events, CometRpcClient, CosmosClient},
+    ethermint, get_sha256_hash,
+    nested::{unwrap_msg, WrappedMsgs},
     service_registry::{
-        AlertSourceCosmosMsg, AlertSourceCosmosTx, ProcessAlertSourceRequeust, ServiceRegistry,
+        AlertSourceCosmosBlockEvent, AlertSourceCosmosGovernance, AlertSourceCosmosIbcPacket,
+        AlertSourceCosmosMsg, AlertSourceCosmosStaking, AlertSourceCosmosTx,
+        AlertSourceCosmosValidator, BlockEventPhase, ProcessAlertSourceRequeust, ServiceRegistry,
+    },
+};
+
+use tokio::{sync::RwLock, task::JoinSet};
+use util::{
+    pipeline::{PipelineConfig, WorkTracker},
+    Result,
+};
+
+use super::{
+    blocks::{
+        self, block_batches, join_all, retryable_blocks, update_failed_blocks, CrawlerWorkers,
+        BLOCK_BATCH_SIZE,
+    },
+    gov::{block_governance_update, msg_governance_updates},
+    heads::{cometbft_new_block_request, HeadSubscription},
+    ibc::ibc_packet_events,
+    mempool::{SeenTxs, MAX_MEMPOOL_TXS, MEMPOOL_PAGE_SIZE, MEMPOOL_POLL_INTERVAL},
+    staking::{
+        block_validator_updates, consensus_address, decode_delegation, msg_validator_updates,
     },
+    status::CrawlerStatusTracker,
+    storage::{CrawlerStorage, LeasedStorage},
+    CrawlChain,
 };
 
-use util::Result;
+// Txs requested per GetBlockWithTxs or GetTxsEvent page
+const BLOCK_TXS_PAGE_SIZE: u64 = 100;
+// Wrapper msgs unwrapped within one another before giving up on a msg
+const MAX_MSG_NESTING: usize = 8;
+// How long the registered chains are matched against IBC counterparties before being reloaded
+const CHAIN_NETWORKS_TTL: Duration = Duration::from_secs(300);
+// Unknown consensus addresses reload the validator set at most this often
+const VALIDATORS_TTL: Duration = Duration::from_secs(60);
+
+/// Registered chain of each network, to find the chain on the other end of an IBC channel
+#[derive(Default)]
+pub struct ChainNetworks {
+    loaded_at: Option<Instant>,
+    chains: HashMap<String, String>,
+    // Networks of the chains registered without one, resolved through their own endpoints
+    resolved: HashMap<String, String>,
+}
+
+impl ChainNetworks {
+    fn is_fresh(&self) -> bool {
+        self.loaded_at
+            .is_some_and(|t| t.elapsed() < CHAIN_NETWORKS_TTL)
+    }
+}
+
+/// Operator address of each validator by its raw consensus address
+#[derive(Default)]
+pub struct ValidatorOperators {
+    loaded_at: Option<Instant>,
+    operators: HashMap<Vec<u8>, String>,
+}
 
-use super::{storage::CrawlerStorage, CrawlChain};
+impl ValidatorOperators {
+    /// Operator of the validator, None when it has to be looked up in the validator set
+    fn get(&self, cons_address: &[u8]) -> Option<Result<String>> {
+        match self.operators.get(cons_address) {
+            Some(operator) => Some(Ok(operator.clone())),
+            // Addresses missing from a recent validator set are not looked up again until it
+            // expires
+            None if self.loaded_at.is_some_and(|t| t.elapsed() < VALIDATORS_TTL) => {
+                Some(Err("could not find validator".into()))
+            }
+            None => None,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct CosmosCrawler<S: CosmosCrawlerStorage> {
     pub chain_data: CosmosChainData,
     pub registry: ServiceRegistry,
     pub store: S,
+    pub workers: CrawlerWorkers,
+    // When set, blocks are only matched against this alert instead of every alert of the chain
+    pub alert: Option<BackendUserAlert>,
+    pub heads: HeadSubscription,
+    pub status: CrawlerStatusTracker,
     pub client: CosmosClient,
+    // Only set when the chain has a CometBFT rpc endpoint to fetch block events and pending
+    // txs from
+    pub rpc_client: Option<CometRpcClient>,
+    // Cleared once the node turns out not to serve GetBlockWithTxs
+    pub block_with_txs: Arc<AtomicBool>,
+    // Cleared once the node turns out not to serve GetTxsEvent, used without an rpc endpoint
+    pub txs_event: Arc<AtomicBool>,
+    // Network on the other end of each (port, channel), which never changes for a channel
+    pub channel_networks: Arc<RwLock<HashMap<(String, String), String>>>,
+    pub chain_networks: Arc<RwLock<ChainNetworks>>,
+    pub validator_operators: Arc<RwLock<ValidatorOperators>>,
+    // Held while reloading the validator set so concurrent misses wait for a single reload
+    pub validators_reload: Arc<tokio::sync::Mutex<()>>,
+    // Notifications of processed blocks still in the pipeline
+    pub work: WorkTracker,
 }
 
 impl<S: CosmosCrawlerStorage> CosmosCrawler<S> {
@@ -32,81 +145,399 @@ impl<S: CosmosCrawlerStorage> CosmosCrawler<S> {
         chain_data: CosmosChainData,
         registry: ServiceRegistry,
         store: S,
+        config: &PipelineConfig,
     ) -> Result<Self> {
-        let client = CosmosClient::new(chain_data.grpc_endpoint.clone()).await?;
+        let client = CosmosClient::new(&chain_data).await?;
+        let rpc_client = if chain_data.rpc_endpoint.is_empty() {
+            None
+        } else {
+            Some(CometRpcClient::new(&chain_data.rpc_endpoint).await?)
+        };
 
         Ok(Self {
             chain_data,
             registry,
             store,
+            workers: CrawlerWorkers::new(config),
+            alert: None,
+            heads: HeadSubscription::default(),
+            status: CrawlerStatusTracker::default(),
             client,
+            rpc_client,
+            block_with_txs: Arc::new(AtomicBool::new(true)),
+            txs_event: Arc::new(AtomicBool::new(true)),
+            channel_networks: Arc::default(),
+            chain_networks: Arc::default(),
+            validator_operators: Arc::default(),
+            validators_reload: Arc::default(),
+            work: WorkTracker::default(),
         })
     }
 
     // Create crawler - remove intialize
     pub async fn initialize_chain(&self, chain_id: &str) -> Result<()> {
-        if self.store.get_last_processed_block(chain_id).await.is_err() {
-            let curr_block_height = self
-                .client
-                .tendermint_client
-                .clone()
-                .get_latest_block(GetLatestBlockRequest {})
-                .await?
-                .get_ref()
-                .clone()
-                .block
-                .ok_or("could not get block")?
-                .header
-                .ok_or("could not find block header")?
-                .height;
+        if self.store.get_crawler_state(chain_id).await.is_err() {
+            let curr_block_height = self.get_head_block_height().await?;
             self.store
-                .set_last_processed_block(chain_id, curr_block_height)
+                .set_crawler_state(
+                    chain_id,
+                    CosmosCrawlerData {
+                        processed_blocks: curr_block_height as u64,
+                        ..Default::default()
+                    },
+                )
                 .await?;
         }
 
         Ok(())
     }
 
-    async fn process_block(&self, chain: Chain, curr_block_height: i64) -> Result<()> {
-        let block = self
+    /// Height of the newest block the crawler is allowed to process, taking the chain's
+    /// confirmation depth into account
+    async fn get_head_block_height(&self) -> Result<i64> {
+        Ok(self
+            .get_chain_head_height()
+            .await?
+            .saturating_sub(self.chain_data.confirmations as i64))
+    }
+
+    async fn get_chain_head_height(&self) -> Result<i64> {
+        Ok(self
             .client
             .tendermint_client
             .clone()
-            .get_block_by_height(GetBlockByHeightRequest {
-                height: curr_block_height,
-            })
+            .get_latest_block(GetLatestBlockRequest {})
             .await?
             .get_ref()
-            .clone();
-
-        let txs = block
+            .clone()
             .block
             .ok_or("could not get block")?
-            .data
-            .ok_or("could not get data for block")?
-            .txs;
+            .header
+            .ok_or("could not find block header")?
+            .height)
+    }
+
+    async fn process_alert_source(&self, req: ProcessAlertSourceRequeust) -> Result<()> {
+        let filter_svc = self.registry.get_services().await?.filter_service;
+        match &self.alert {
+            Some(alert) => {
+                filter_svc
+                    .process_alert_for_alert_source(req, alert.clone(), self.work.token())
+                    .await
+            }
+            None => {
+                filter_svc
+                    .process_alert_source(req, self.work.token())
+                    .await
+            }
+        }
+    }
+
+    /// Processes the events of the block and then its txs. Heights in `events_processed` only
+    /// have their txs processed, heights whose events went out are added to it
+    async fn process_block(
+        &self,
+        chain: Chain,
+        curr_block_height: i64,
+        events_processed: Arc<Mutex<HashSet<u64>>>,
+    ) -> Result<()> {
+        let height = curr_block_height as u64;
+        let (events, tx_results) = match &self.rpc_client {
+            Some(rpc_client) => {
+                let results = rpc_client.block_results(height).await?;
+                (results.events, Some(results.tx_results))
+            }
+            None => (vec![], None),
+        };
+
+        // A block that fails on its txs is retried without resending its block events
+        if !events_processed.lock().unwrap().contains(&height) {
+            self.process_block_events(&chain, height, events).await?;
+            events_processed.lock().unwrap().insert(height);
+        }
+
+        let mut txs = None;
+        match tx_results {
+            Some(tx_results) if self.block_with_txs.load(Ordering::Relaxed) => {
+                txs = self
+                    .get_block_txs(&chain, curr_block_height, tx_results)
+                    .await?;
+            }
+            None if self.txs_event.load(Ordering::Relaxed) => {
+                txs = self
+                    .get_indexed_block_txs(&chain, curr_block_height)
+                    .await?;
+            }
+            _ => {}
+        }
+        // Nodes that cannot return the txs of a block at once have every tx fetched on its own
+        let txs = match txs {
+            Some(txs) => txs,
+            None => self
+                .get_block_tx_hashes(curr_block_height)
+                .await?
+                .into_iter()
+                .map(|tx_hash| (tx_hash, None))
+                .collect(),
+        };
 
-        for tx in txs {
+        let mut tasks = JoinSet::new();
+        for (tx_hash, tx) in txs {
             let svc = self.clone();
             let chain = chain.clone();
-            let tx_hash = get_sha256_hash(&tx);
-            tokio::spawn(async move {
-                if let Err(err) = svc.process_tx(chain.clone(), tx_hash.clone()).await {
+            let permit = self.workers.txs.clone().acquire_owned().await?;
+            tasks.spawn(async move {
+                let _permit = permit;
+                let res = match tx {
+                    Some((tx, tx_resp)) => {
+                        svc.process_tx_source(chain.clone(), tx_hash.clone(), tx, Some(tx_resp))
+                            .await
+                    }
+                    None => svc.process_tx(chain.clone(), tx_hash.clone()).await,
+                };
+                if let Err(err) = &res {
                     warn!(
                         "failed to execute tx {} in chain {} - {}",
                         tx_hash, chain.id, err
                     )
                 }
+                res
             });
         }
 
+        join_all(tasks).await
+    }
+
+    async fn get_block_tx_hashes(&self, height: i64) -> Result<Vec<String>> {
+        let block = self
+            .client
+            .tendermint_client
+            .clone()
+            .get_block_by_height(GetBlockByHeightRequest { height })
+            .await?
+            .into_inner();
+
+        let txs = block
+            .block
+            .ok_or("could not get block")?
+            .data
+            .ok_or("could not get data for block")?
+            .txs;
+
+        Ok(txs.iter().map(|tx| get_sha256_hash(tx)).collect())
+    }
+
+    /// Fetches the decoded txs of a block page by page and pairs them with their results from
+    /// block_results. Returns None when the node does not serve GetBlockWithTxs
+    async fn get_block_txs(
+        &self,
+        chain: &Chain,
+        height: i64,
+        tx_results: Vec<TxResponse>,
+    ) -> Result<Option<Vec<(String, Option<(Tx, TxResponse)>)>>> {
+        let mut txs = vec![];
+        let mut raw_txs = vec![];
+        let mut timestamp = String::new();
+        loop {
+            let res = self
+                .client
+                .tx_client
+                .clone()
+                .get_block_with_txs(GetBlockWithTxsRequest {
+                    height,
+                    pagination: Some(PageRequest {
+                        offset: txs.len() as u64,
+                        limit: BLOCK_TXS_PAGE_SIZE,
+                        ..Default::default()
+                    }),
+                })
+                .await;
+
+            let res = match res {
+                Ok(res) => res.into_inner(),
+                Err(status) if status.code() == tonic::Code::Unimplemented => {
+                    warn!(
+                        "chain {} does not support GetBlockWithTxs - fetching txs one by one",
+                        chain.id
+                    );
+                    self.block_with_txs.store(false, Ordering::Relaxed);
+                    return Ok(None);
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            // Every page carries the whole block
+            if txs.is_empty() {
+                let block = res.block.ok_or("could not get block")?;
+                let time = block
+                    .header
+                    .ok_or("could not find block header")?
+                    .time
+                    .ok_or("could not find block time")?;
+                timestamp = Time::try_from(time)?.to_rfc3339();
+                raw_txs = block.data.ok_or("could not get data for block")?.txs;
+            }
+
+            if res.txs.is_empty() {
+                break;
+            }
+            txs.extend(res.txs);
+            if txs.len() >= raw_txs.len() {
+                break;
+            }
+        }
+
+        if txs.len() != raw_txs.len() || tx_results.len() != raw_txs.len() {
+            return Err(format!(
+                "block {} has {} txs but got {} decoded txs and {} results",
+                height,
+                raw_txs.len(),
+                txs.len(),
+                tx_results.len()
+            )
+            .into());
+        }
+
+        let block_txs = raw_txs
+            .iter()
+            .zip(txs)
+            .zip(tx_results)
+            .map(|((raw_tx, tx), mut tx_resp)| {
+                let tx_hash = get_sha256_hash(raw_tx);
+                tx_resp.txhash = tx_hash.to_uppercase();
+                tx_resp.timestamp = timestamp.clone();
+                tx_resp.tx = Some(Any {
+                    type_url: "/cosmos.tx.v1beta1.Tx".to_string(),
+                    value: raw_tx.clone(),
+                });
+                (tx_hash, Some((tx, tx_resp)))
+            })
+            .collect();
+
+        Ok(Some(block_txs))
+    }
+
+    /// Fetches the txs of a block along with their results from the tx index, for chains without
+    /// an rpc endpoint. Txs missing from the index are left to be fetched on their own. Returns
+    /// None when the node does not serve GetTxsEvent
+    async fn get_indexed_block_txs(
+        &self,
+        chain: &Chain,
+        height: i64,
+    ) -> Result<Option<Vec<(String, Option<(Tx, TxResponse)>)>>> {
+        let tx_hashes = self.get_block_tx_hashes(height).await?;
+        if tx_hashes.is_empty() {
+            return Ok(Some(vec![]));
+        }
+
+        let indexed_txs = match self
+            .client
+            .block_txs(height, tx_hashes.len(), BLOCK_TXS_PAGE_SIZE)
+            .await?
+        {
+            Some(txs) => txs,
+            None => {
+                warn!(
+                    "chain {} does not support GetTxsEvent - fetching txs one by one",
+                    chain.id
+                );
+                self.txs_event.store(false, Ordering::Relaxed);
+                return Ok(None);
+            }
+        };
+        let mut indexed_txs: HashMap<String, (Tx, TxResponse)> = indexed_txs
+            .into_iter()
+            .map(|(tx, tx_resp)| (tx_resp.txhash.to_lowercase(), (tx, tx_resp)))
+            .collect();
+
+        Ok(Some(
+            tx_hashes
+                .into_iter()
+                .map(|tx_hash| {
+                    let tx = indexed_txs.remove(&tx_hash.to_lowercase());
+                    (tx_hash, tx)
+                })
+                .collect(),
+        ))
+    }
+
+    async fn process_block_events(
+        &self,
+        chain: &Chain,
+        height: u64,
+        events: Vec<(BlockEventPhase, StringEvent)>,
+    ) -> Result<()> {
+        for (i, (phase, event)) in events.into_iter().enumerate() {
+            match block_governance_update(&event) {
+                Ok(Some((gov_event, update))) => {
+                    self.process_governance(AlertSourceCosmosGovernance {
+                        chain_id: chain.id.clone(),
+                        chain_data: self.chain_data.clone(),
+                        height,
+                        tx_hash: None,
+                        event_index: i as u64,
+                        event: gov_event,
+                        update,
+                    })
+                    .await?
+                }
+                Ok(None) => {}
+                Err(err) => warn!(
+                    "failed to decode governance event {} in block {} in chain {} - {}",
+                    i, height, chain.id, err
+                ),
+            }
+            match block_validator_updates(&event) {
+                Ok(updates) => {
+                    for (validator_event, cons_address, mut update) in updates {
+                        // Alerts name validators by operator, so nothing else could match
+                        match self.validator_operator(&cons_address).await {
+                            Ok(operator) => update.validator = operator,
+                            Err(err) => {
+                                warn!(
+                                    "could not find operator of validator {} in chain {} - {}",
+                                    cons_address, chain.id, err
+                                );
+                                continue;
+                            }
+                        }
+                        self.process_alert_source(ProcessAlertSourceRequeust::CosmosValidator(
+                            Box::new(AlertSourceCosmosValidator {
+                                chain_id: chain.id.clone(),
+                                chain_data: self.chain_data.clone(),
+                                height,
+                                tx_hash: None,
+                                event_index: i as u64,
+                                event: validator_event,
+                                update,
+                            }),
+                        ))
+                        .await?;
+                    }
+                }
+                Err(err) => warn!(
+                    "failed to decode validator event {} in block {} in chain {} - {}",
+                    i, height, chain.id, err
+                ),
+            }
+
+            self.process_alert_source(ProcessAlertSourceRequeust::CosmosBlockEvent(Box::new(
+                AlertSourceCosmosBlockEvent {
+                    chain_id: chain.id.clone(),
+                    chain_data: self.chain_data.clone(),
+                    height,
+                    phase,
+                    event_index: i as u64,
+                    event,
+                },
+            )))
+            .await?;
+        }
+
         Ok(())
     }
 
     async fn process_tx(&self, chain: Chain, tx_hash: String) -> Result<()> {
-        let registry = self.registry.get_services().await?;
-        let filter_svc = registry.filter_service;
-
         let res = self
             .client
             .clone()
@@ -129,131 +560,711 @@ impl<S: CosmosCrawlerStorage> CosmosCrawler<S> {
             .ok_or("could not find tx in resp")?
             .clone();
 
-        if tx_resp.code == 0 {
+        self.process_tx_source(chain, tx_hash, tx, Some(tx_resp))
+            .await
+    }
+
+    /// Processes a tx and its msgs, `tx_resp` is missing while the tx is pending
+    async fn process_tx_source(
+        &self,
+        chain: Chain,
+        tx_hash: String,
+        tx: Tx,
+        tx_resp: Option<TxResponse>,
+    ) -> Result<()> {
+        // Chains on Cosmos SDK 0.50 and later only tag the events of each msg with its index
+        let tx_resp = tx_resp.map(|mut tx_resp| {
+            if tx_resp.logs.is_empty() && tx_resp.code == 0 {
+                tx_resp.logs = msg_logs_from_events(&tx_resp.events);
+            }
+            tx_resp
+        });
+        let mut tasks = JoinSet::new();
+        let succeeded = tx_resp.as_ref().map(|r| r.code == 0).unwrap_or(true);
+        if succeeded {
             let messages = &tx.body.as_ref().ok_or("could not find tx body")?.messages;
+            let height = tx_resp
+                .as_ref()
+                .map(|r| r.height as u64)
+                .unwrap_or_default();
+            let mut nested = vec![];
             for i in 0..messages.len() {
-                let req = AlertSourceCosmosMsg {
-                    chain_id: chain.id.clone(),
-                    chain_data: self.chain_data.clone(),
-                    tx_hash: tx_hash.clone(),
-                    msg_log: tx_resp.logs.get(i).cloned(),
-                    msg_index: i as u64,
-                    msg: messages
-                        .get(i)
-                        .ok_or("could not find msg at index")?
-                        .clone(),
+                let msg = messages
+                    .get(i)
+                    .ok_or("could not find msg at index")?
+                    .clone();
+                let msg_log = tx_resp.as_ref().and_then(|r| r.logs.get(i).cloned());
+                let eth_tx = match msg.type_url == ethermint::MSG_ETHEREUM_TX {
+                    true => match ethermint::decode_ethereum_tx(&msg) {
+                        Ok(eth_tx) => Some(eth_tx),
+                        Err(err) => {
+                            warn!(
+                                "failed to decode eth tx in msg {} in tx {} in chain {} - {}",
+                                i, tx_hash, chain.id, err
+                            );
+                            None
+                        }
+                    },
+                    false => None,
                 };
-                let svc = self.clone();
-                tokio::spawn(async move {
-                    let tx_hash = req.tx_hash.clone();
-                    let msg_index = req.msg_index;
-                    let chain_id = req.chain_id.clone();
-                    if let Err(err) = svc.process_tx_msg(req).await {
-                        warn!(
-                            "failed to execute msg {} in tx {} in chain {} - {}",
-                            msg_index, tx_hash, chain_id, err
-                        )
-                    }
-                });
+                let msg_nested = self
+                    .nested_msgs(&chain, &tx_hash, height, &msg, msg_log.as_ref())
+                    .await;
+                // Msgs run on the host chain of an interchain account did not change this chain
+                nested.push(
+                    msg_nested
+                        .iter()
+                        .filter(|(_, _, remote)| !remote)
+                        .map(|(_, m, _)| m.clone())
+                        .collect::<Vec<_>>(),
+                );
+
+                // Msgs nested in the msg go through the same filters as the msg itself, the msg
+                // log covers the whole msg so nested msgs go without one
+                let msgs = std::iter::once((vec![], msg, false, eth_tx, msg_log)).chain(
+                    msg_nested
+                        .into_iter()
+                        .map(|(path, m, remote)| (path, m, remote, None, None)),
+                );
+                for (path, msg, remote, eth_tx, msg_log) in msgs {
+                    let tx_events = match (&eth_tx, &tx_resp) {
+                        (Some(_), Some(tx_resp)) => tx_resp.events.clone(),
+                        _ => vec![],
+                    };
+                    let req = AlertSourceCosmosMsg {
+                        chain_id: chain.id.clone(),
+                        chain_data: self.chain_data.clone(),
+                        tx_hash: tx_hash.clone(),
+                        msg_log,
+                        msg_index: i as u64,
+                        path,
+                        remote,
+                        msg,
+                        pending: tx_resp.is_none(),
+                        eth_tx,
+                        tx_events,
+                    };
+                    let svc = self.clone();
+                    tasks.spawn(async move {
+                        let tx_hash = req.tx_hash.clone();
+                        let msg_index = req.msg_index;
+                        let chain_id = req.chain_id.clone();
+                        let res = svc.process_tx_msg(req).await;
+                        if let Err(err) = &res {
+                            warn!(
+                                "failed to execute msg {} in tx {} in chain {} - {}",
+                                msg_index, tx_hash, chain_id, err
+                            )
+                        }
+                        res
+                    });
+                }
+            }
+
+            // Governance and staking msgs are only followed once included, along with their logs
+            for msg_log in tx_resp.iter().flat_map(|r| &r.logs) {
+                let i = msg_log.msg_index as usize;
+                let msg = match messages.get(i) {
+                    Some(msg) => msg,
+                    None => continue,
+                };
+                let msgs: Vec<&Any> = std::iter::once(msg)
+                    .chain(nested.get(i).into_iter().flatten())
+                    .collect();
+                for req in self.msg_alert_sources(&chain, &tx_hash, height, &msgs, msg_log) {
+                    let svc = self.clone();
+                    let chain_id = chain.id.clone();
+                    let tx_hash = tx_hash.clone();
+                    let msg_index = msg_log.msg_index;
+                    tasks.spawn(async move {
+                        let res = svc.process_alert_source(req).await;
+                        if let Err(err) = &res {
+                            warn!(
+                                "failed to execute msg {} in tx {} in chain {} - {}",
+                                msg_index, tx_hash, chain_id, err
+                            )
+                        }
+                        res
+                    });
+                }
+            }
+
+            for msg_log in tx_resp.iter().flat_map(|r| &r.logs) {
+                for event in ibc_packet_events(msg_log) {
+                    let svc = self.clone();
+                    let chain_id = chain.id.clone();
+                    let tx_hash = tx_hash.clone();
+                    let msg_index = msg_log.msg_index as u64;
+                    tasks.spawn(async move {
+                        let res = svc
+                            .process_ibc_packet(AlertSourceCosmosIbcPacket {
+                                chain_id: chain_id.clone(),
+                                chain_data: svc.chain_data.clone(),
+                                tx_hash: tx_hash.clone(),
+                                msg_index,
+                                stage: event.stage,
+                                packet: event.packet,
+                                error: event.error,
+                                counterparty_chain_id: None,
+                            })
+                            .await;
+                        if let Err(err) = &res {
+                            warn!(
+                                "failed to execute ibc packet in msg {} in tx {} in chain {} - {}",
+                                msg_index, tx_hash, chain_id, err
+                            )
+                        }
+                        res
+                    });
+                }
             }
         }
 
-        filter_svc
-            .process_alert_source(ProcessAlertSourceRequeust::CosmosTx(Box::new(
-                AlertSourceCosmosTx {
-                    chain_id: chain.id,
-                    chain_data: self.chain_data.clone(),
-                    tx,
-                    tx_hash,
-                    tx_resp,
-                },
-            )))
+        self.process_alert_source(ProcessAlertSourceRequeust::CosmosTx(Box::new(
+            AlertSourceCosmosTx {
+                chain_id: chain.id,
+                chain_data: self.chain_data.clone(),
+                tx,
+                tx_hash,
+                tx_resp,
+            },
+        )))
+        .await?;
+
+        join_all(tasks).await
+    }
+
+    async fn process_tx_msg(&self, req: AlertSourceCosmosMsg) -> Result<()> {
+        // Each log of an included evm tx is a source of its own, as it is for the eth crawler
+        let events = req.event_sources();
+        self.process_alert_source(ProcessAlertSourceRequeust::CosmosMsg(Box::new(req)))
             .await?;
+        for event in events {
+            self.process_alert_source(ProcessAlertSourceRequeust::CosmosMsgEvent(Box::new(event)))
+                .await?;
+        }
 
         Ok(())
     }
 
-    async fn process_tx_msg(&self, req: AlertSourceCosmosMsg) -> Result<()> {
-        let registry = self.registry.get_services().await?;
-        let filter_svc = registry.filter_service;
+    async fn process_ibc_packet(&self, mut req: AlertSourceCosmosIbcPacket) -> Result<()> {
+        // Received packets came in through the destination end of the packet
+        let (port, channel) = match req.stage {
+            Stage::Recv => (&req.packet.dst_port, &req.packet.dst_channel),
+            _ => (&req.packet.src_port, &req.packet.src_channel),
+        };
+        // The packet still matches the alerts of this chain without its counterparty
+        match self.channel_counterparty(port, channel).await {
+            Ok(counterparty) => req.counterparty_chain_id = counterparty,
+            Err(err) => warn!(
+                "could not find counterparty of channel {}/{} of chain {} - {}",
+                port, channel, req.chain_id, err
+            ),
+        }
 
-        filter_svc
-            .process_alert_source(ProcessAlertSourceRequeust::CosmosMsg(Box::new(req)))
-            .await?;
+        self.process_alert_source(ProcessAlertSourceRequeust::CosmosIbcPacket(Box::new(req)))
+            .await
+    }
+
+    /// Msgs wrapped by `msg` and the wrappers within it, with their path within the wrappers and
+    /// whether an interchain account runs them on its host chain. Wrappers that fail to unwrap
+    /// or sit too deep are skipped
+    async fn nested_msgs(
+        &self,
+        chain: &Chain,
+        tx_hash: &str,
+        height: u64,
+        msg: &Any,
+        msg_log: Option<&AbciMessageLog>,
+    ) -> Vec<(Vec<u64>, Any, bool)> {
+        let mut nested = vec![];
+        let mut wrappers = vec![(vec![], msg.clone(), false)];
+        while let Some((path, msg, remote)) = wrappers.pop() {
+            if path.len() >= MAX_MSG_NESTING {
+                warn!(
+                    "msg {} in tx {} in chain {} is nested too deep to unwrap",
+                    msg.type_url, tx_hash, chain.id
+                );
+                continue;
+            }
+            // The msg log of this chain says nothing about msgs run on the host chain
+            let (msgs, remote) = match unwrap_msg(&msg, msg_log.filter(|_| !remote)) {
+                Ok(Some(WrappedMsgs::Msgs(msgs))) => (msgs, remote),
+                Ok(Some(WrappedMsgs::Remote(msgs))) => (msgs, true),
+                // The proposal is gone once the block executing it is committed
+                Ok(Some(WrappedMsgs::GroupProposal(proposal_id))) => match self
+                    .client
+                    .group_proposal_msgs(proposal_id, height.saturating_sub(1))
+                    .await
+                {
+                    Ok(msgs) => (msgs, remote),
+                    Err(err) => {
+                        warn!(
+                            "could not get msgs of group proposal {} in chain {} - {}",
+                            proposal_id, chain.id, err
+                        );
+                        continue;
+                    }
+                },
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!(
+                        "failed to unwrap msg {} in tx {} in chain {} - {}",
+                        msg.type_url, tx_hash, chain.id, err
+                    );
+                    continue;
+                }
+            };
+            for (j, msg) in msgs.into_iter().enumerate() {
+                let mut path = path.clone();
+                path.push(j as u64);
+                nested.push((path.clone(), msg.clone(), remote));
+                wrappers.push((path, msg, remote));
+            }
+        }
+        nested.sort_by(|a, b| a.0.cmp(&b.0));
+
+        nested
+    }
+
+    /// Governance, staking and validator sources of an included msg and the msgs it wraps, parts
+    /// of the msgs that fail to decode are skipped
+    fn msg_alert_sources(
+        &self,
+        chain: &Chain,
+        tx_hash: &str,
+        height: u64,
+        msgs: &[&Any],
+        msg_log: &AbciMessageLog,
+    ) -> Vec<ProcessAlertSourceRequeust> {
+        let msg_index = msg_log.msg_index as u64;
+        let mut sources = vec![];
+
+        match msg_governance_updates(msgs, msg_log) {
+            Ok(updates) => sources.extend(updates.into_iter().map(|(event, update)| {
+                ProcessAlertSourceRequeust::CosmosGovernance(Box::new(
+                    AlertSourceCosmosGovernance {
+                        chain_id: chain.id.clone(),
+                        chain_data: self.chain_data.clone(),
+                        height,
+                        tx_hash: Some(tx_hash.to_string()),
+                        event_index: msg_index,
+                        event,
+                        update,
+                    },
+                ))
+            })),
+            Err(err) => warn!(
+                "failed to decode governance msg {} in tx {} in chain {} - {}",
+                msg_index, tx_hash, chain.id, err
+            ),
+        }
+
+        for msg in msgs {
+            match decode_delegation(msg) {
+                Ok(Some(delegation)) => sources.push(ProcessAlertSourceRequeust::CosmosStaking(
+                    Box::new(AlertSourceCosmosStaking {
+                        chain_id: chain.id.clone(),
+                        chain_data: self.chain_data.clone(),
+                        tx_hash: tx_hash.to_string(),
+                        msg_index,
+                        action: delegation.action,
+                        delegator: delegation.delegator,
+                        validator: delegation.validator,
+                        validator_dst: delegation.validator_dst,
+                        amount: delegation.amount,
+                    }),
+                )),
+                Ok(None) => {}
+                Err(err) => warn!(
+                    "failed to decode staking msg {} in tx {} in chain {} - {}",
+                    msg_index, tx_hash, chain.id, err
+                ),
+            }
+
+            match msg_validator_updates(msg) {
+                Ok(updates) => sources.extend(updates.into_iter().map(|(event, update)| {
+                    ProcessAlertSourceRequeust::CosmosValidator(Box::new(
+                        AlertSourceCosmosValidator {
+                            chain_id: chain.id.clone(),
+                            chain_data: self.chain_data.clone(),
+                            height,
+                            tx_hash: Some(tx_hash.to_string()),
+                            event_index: msg_index,
+                            event,
+                            update,
+                        },
+                    ))
+                })),
+                Err(err) => warn!(
+                    "failed to decode validator msg {} in tx {} in chain {} - {}",
+                    msg_index, tx_hash, chain.id, err
+                ),
+            }
+        }
+
+        sources
+    }
+
+    async fn process_governance(&self, mut req: AlertSourceCosmosGovernance) -> Result<()> {
+        // Still worth a notification when the node has pruned the state of the block
+        if req.event == GovernanceEvent::VotingEnded {
+            match self
+                .client
+                .proposal_tally(req.update.proposal_id, req.height)
+                .await
+            {
+                Ok(tally) => req.update.tally = Some(tally),
+                Err(err) => warn!(
+                    "could not get tally of proposal {} in chain {} - {}",
+                    req.update.proposal_id, req.chain_id, err
+                ),
+            }
+        }
+
+        self.process_alert_source(ProcessAlertSourceRequeust::CosmosGovernance(Box::new(req)))
+            .await
+    }
+
+    /// Operator address of the validator behind a bech32 consensus address
+    async fn validator_operator(&self, cons_address: &str) -> Result<String> {
+        let key = AccountId::from_str(cons_address)?.to_bytes();
+        if let Some(operator) = self.validator_operators.read().await.get(&key) {
+            return operator;
+        }
+
+        let _reload = self.validators_reload.lock().await;
+        // Another event may have reloaded the validators while waiting for the lock
+        if let Some(operator) = self.validator_operators.read().await.get(&key) {
+            return operator;
+        }
+
+        // Validators that joined since the last reload are picked up here, lookups of known
+        // validators go on meanwhile
+        let mut operators = HashMap::new();
+        for validator in self.client.validators().await? {
+            let pub_key = match &validator.consensus_pubkey {
+                Some(pub_key) => pub_key,
+                None => continue,
+            };
+            match consensus_address(pub_key) {
+                Ok(address) => {
+                    operators.insert(address, validator.operator_address);
+                }
+                Err(err) => warn!(
+                    "could not get consensus address of validator {} - {}",
+                    validator.operator_address, err
+                ),
+            }
+        }
+
+        let mut validators = self.validator_operators.write().await;
+        validators.operators.extend(operators);
+        validators.loaded_at = Some(Instant::now());
+
+        Ok(validators
+            .operators
+            .get(&key)
+            .ok_or("could not find validator")?
+            .clone())
+    }
+
+    /// Registered chain on the other end of one of this chain's channels
+    async fn channel_counterparty(&self, port: &str, channel: &str) -> Result<Option<String>> {
+        let key = (port.to_string(), channel.to_string());
+        let cached = self.channel_networks.read().await.get(&key).cloned();
+        let network_id = match cached {
+            Some(network_id) => network_id,
+            None => {
+                let network_id = self.client.channel_network_id(port, channel).await?;
+                self.channel_networks
+                    .write()
+                    .await
+                    .insert(key, network_id.clone());
+                network_id
+            }
+        };
+
+        {
+            let networks = self.chain_networks.read().await;
+            if networks.is_fresh() {
+                return Ok(networks.chains.get(&network_id).cloned());
+            }
+        }
+
+        let mut networks = self.chain_networks.write().await;
+        // Another packet may have reloaded them while waiting for the lock
+        if !networks.is_fresh() {
+            self.load_chain_networks(&mut networks).await?;
+        }
+
+        Ok(networks.chains.get(&network_id).cloned())
+    }
+
+    async fn load_chain_networks(&self, networks: &mut ChainNetworks) -> Result<()> {
+        let chains = self
+            .registry
+            .get_services()
+            .await?
+            .chain_service
+            .get_chains()
+            .await?
+            .chains;
+
+        networks.chains.clear();
+        for chain in chains {
+            let chain_data = match cosmos_chain_data(&chain) {
+                Some(chain_data) => chain_data,
+                None => continue,
+            };
+            let network_id = if !chain_data.network_id.is_empty() {
+                chain_data.network_id.clone()
+            } else if let Some(network_id) = networks.resolved.get(&chain.id) {
+                network_id.clone()
+            } else {
+                // Chains registered before network ids were stored only have their endpoints
+                let resolved = match CosmosClient::new(chain_data).await {
+                    Ok(client) => client.network_id().await,
+                    Err(err) => Err(err),
+                };
+                match resolved {
+                    Ok(network_id) => {
+                        networks
+                            .resolved
+                            .insert(chain.id.clone(), network_id.clone());
+                        network_id
+                    }
+                    Err(err) => {
+                        warn!("could not find network of chain {} - {}", chain.id, err);
+                        continue;
+                    }
+                }
+            };
+            networks.chains.insert(network_id, chain.id);
+        }
+        networks.loaded_at = Some(Instant::now());
 
         Ok(())
     }
-}
 
-#[tonic::async_trait]
-impl<S: CosmosCrawlerStorage> CrawlChain for CosmosCrawler<S> {
-    async fn try_crawl_chain(&self, chain: &Chain) -> Result<()> {
-        self.initialize_chain(&chain.id).await?;
+    /// Polls the node's mempool and processes every tx the first time it shows up there
+    async fn watch_mempool(&self, chain: &Chain) -> Result<()> {
+        let rpc_client = self
+            .rpc_client
+            .as_ref()
+            .ok_or("watching the mempool requires an rpc endpoint")?;
+
+        let mut seen_txs = SeenTxs::default();
         loop {
-            let latest_block_height = self
-                .client
-                .tendermint_client
-                .clone()
-                .get_latest_block(GetLatestBlockRequest {})
-                .await?
-                .get_ref()
-                .clone()
-                .block
-                .ok_or("could not get block")?
-                .header
-                .ok_or("could not find block header")?
-                .height;
-
-            let curr_block_height = self.store.get_last_processed_block(&chain.id).await?;
-            if curr_block_height != latest_block_height {
-                for i in (curr_block_height + 1)..=latest_block_height {
-                    let svc = self.clone();
-                    let chain = chain.clone();
-                    tokio::spawn(async move {
-                        if let Err(err) = svc.process_block(chain.clone(), i).await {
+            match rpc_client
+                .unconfirmed_txs(MEMPOOL_PAGE_SIZE, MAX_MEMPOOL_TXS)
+                .await
+            {
+                Ok(raw_txs) => {
+                    for raw_tx in raw_txs {
+                        let tx_hash = get_sha256_hash(&raw_tx);
+                        if !seen_txs.insert(&tx_hash) {
+                            continue;
+                        }
+
+                        if let Err(err) = self.process_pending_tx(chain, &tx_hash, &raw_tx).await {
                             warn!(
-                                "failed to execute block {} in chain {} - {}",
-                                i, chain.id, err
-                            );
+                                "failed to execute pending tx {} in chain {} - {}",
+                                tx_hash, chain.id, err
+                            )
                         }
-                    });
+                    }
                 }
+                Err(err) => warn!("failed to poll mempool of chain {} - {}", chain.id, err),
+            }
 
-                self.store
-                    .set_last_processed_block(&chain.id, latest_block_height)
-                    .await?;
+            tokio::time::sleep(MEMPOOL_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn process_pending_tx(&self, chain: &Chain, tx_hash: &str, raw_tx: &[u8]) -> Result<()> {
+        let tx = Tx::decode(raw_tx)?;
+        self.process_tx_source(chain.clone(), tx_hash.to_string(), tx, None)
+            .await
+    }
+}
+
+#[tonic::async_trait]
+impl<S: CosmosCrawlerStorage> CrawlChain for CosmosCrawler<S> {
+    async fn try_crawl_chain(&self, chain: &Chain, stop: &AtomicBool) -> Result<()> {
+        self.initialize_chain(&chain.id).await?;
+
+        // The subscription only wakes up the crawl loop early, polling continues regardless
+        let subscription = async {
+            if self.chain_data.websocket_endpoint.is_empty() {
+                return std::future::pending().await;
+            }
+            self.heads
+                .run(
+                    &self.chain_data.websocket_endpoint,
+                    cometbft_new_block_request(),
+                )
+                .await
+        };
+        let mempool = async {
+            if !self.chain_data.watch_mempool {
+                return std::future::pending().await;
+            }
+            // Pending txs do not hold back the cursor
+            let svc = Self {
+                work: WorkTracker::default(),
+                ..self.clone()
+            };
+            // Block crawling goes on without the mempool
+            if let Err(err) = svc.watch_mempool(chain).await {
+                warn!(
+                    "stopped watching the mempool of chain {} - {}",
+                    chain.id, err
+                );
+            }
+            std::future::pending().await
+        };
+
+        tokio::select! {
+            res = self.crawl_blocks(chain, stop) => res,
+            _ = subscription => Ok(()),
+            () = mempool => Ok(()),
+        }
+    }
+
+    async fn head_height(&self) -> Result<u64> {
+        Ok(self.get_head_block_height().await? as u64)
+    }
+
+    async fn backfill_chain(
+        &self,
+        chain: &Chain,
+        from_height: u64,
+        to_height: u64,
+    ) -> Vec<(u64, String)> {
+        let mut failures = vec![];
+        for heights in block_batches(from_height, to_height) {
+            failures.extend(self.process_blocks(chain, heights, Arc::default()).await);
+        }
+        self.work.wait_idle().await;
+
+        failures
+    }
+}
+
+impl<S: CosmosCrawlerStorage> CosmosCrawler<S> {
+    /// Processes new blocks as they come in, persisting the crawler state once the notifications of
+    /// a batch are sent
+    async fn crawl_blocks(&self, chain: &Chain, stop: &AtomicBool) -> Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            let head_height = self.get_chain_head_height().await? as u64;
+            let latest_block_height = head_height.saturating_sub(self.chain_data.confirmations);
+            let mut state = self.store.get_crawler_state(&chain.id).await?;
+            self.status
+                .report_progress(&chain.id, "cosmos", head_height, state.processed_blocks);
+
+            let events_processed = Arc::new(Mutex::new(
+                state
+                    .failed_blocks
+                    .iter()
+                    .filter(|b| b.events_processed)
+                    .map(|b| b.height)
+                    .collect(),
+            ));
+
+            let retry_heights = retryable_blocks(&state.failed_blocks);
+            if !retry_heights.is_empty() {
+                let failures = self
+                    .process_blocks(chain, retry_heights.clone(), events_processed.clone())
+                    .await;
+                update_failed_blocks(&mut state.failed_blocks, &retry_heights, failures);
+                mark_events_processed(&mut state.failed_blocks, &events_processed);
+            }
+
+            let curr_block_height = state.processed_blocks;
+            if curr_block_height < latest_block_height {
+                let to_height = latest_block_height.min(curr_block_height + BLOCK_BATCH_SIZE);
+                let heights: Vec<u64> = ((curr_block_height + 1)..=to_height).collect();
+                let failures = self
+                    .process_blocks(chain, heights.clone(), events_processed.clone())
+                    .await;
+                update_failed_blocks(&mut state.failed_blocks, &heights, failures);
+                mark_events_processed(&mut state.failed_blocks, &events_processed);
+
+                // The cursor only moves once the notifications of the batch are sent
+                self.work.wait_idle().await;
+                state.processed_blocks = to_height;
+                self.store.set_crawler_state(&chain.id, state).await?;
             } else {
-                tokio::time::sleep(Duration::from_secs(5)).await
+                if !retry_heights.is_empty() {
+                    self.work.wait_idle().await;
+                    self.store.set_crawler_state(&chain.id, state).await?;
+                }
+                self.heads.wait().await;
             }
         }
+
+        Ok(())
+    }
+
+    async fn process_blocks(
+        &self,
+        chain: &Chain,
+        heights: Vec<u64>,
+        events_processed: Arc<Mutex<HashSet<u64>>>,
+    ) -> Vec<(u64, String)> {
+        blocks::process_blocks(&chain.id, &self.workers, heights, |height| {
+            let svc = self.clone();
+            let chain = chain.clone();
+            let events_processed = events_processed.clone();
+            async move {
+                svc.process_block(chain, height as i64, events_processed)
+                    .await
+            }
+        })
+        .await
     }
 }
 
 #[tonic::async_trait]
 pub trait CosmosCrawlerStorage: Send + Sync + Clone + 'static {
-    async fn get_last_processed_block(&self, chain_id: &str) -> Result<i64>;
-    async fn set_last_processed_block(&self, chain_id: &str, height: i64) -> Result<()>;
+    async fn get_crawler_state(&self, chain_id: &str) -> Result<CosmosCrawlerData>;
+    async fn set_crawler_state(&self, chain_id: &str, state: CosmosCrawlerData) -> Result<()>;
 }
 
 #[tonic::async_trait]
-impl<T: CrawlerStorage> CosmosCrawlerStorage for T {
-    async fn get_last_processed_block(&self, chain_id: &str) -> Result<i64> {
+impl<T: CrawlerStorage> CosmosCrawlerStorage for LeasedStorage<T> {
+    async fn get_crawler_state(&self, chain_id: &str) -> Result<CosmosCrawlerData> {
         if let CrawlerData::Cosmos(data) = self.get_crawler_data(chain_id).await? {
-            return Ok(data.processed_blocks as i64);
+            return Ok(data);
         } else {
             return Err("unexpected crawler data".into());
         }
     }
 
-    async fn set_last_processed_block(&self, chain_id: &str, height: i64) -> Result<()> {
-        self.set_crawler_data(
-            chain_id,
-            CrawlerData::Cosmos(CosmosCrawlerData {
-                processed_blocks: height as u64,
-            }),
-        )
-        .await?;
+    async fn set_crawler_state(&self, chain_id: &str, state: CosmosCrawlerData) -> Result<()> {
+        self.set_crawler_data(chain_id, CrawlerData::Cosmos(state))
+            .await?;
 
         Ok(())
     }
 }
+
+/// Remembers which failed blocks already sent their block events
+fn mark_events_processed(
+    failed_blocks: &mut [FailedBlock],
+    events_processed: &Mutex<HashSet<u64>>,
+) {
+    let events_processed = events_processed.lock().unwrap();
+    for block in failed_blocks {
+        block.events_processed = events_processed.contains(&block.height);
+    }
+}
+
+fn cosmos_chain_data(chain: &Chain) -> Option<&CosmosChainData> {
+    match chain.chain_data.as_ref()?.chain_data.as_ref()? {
+        chain_data::ChainData::CosmosChainData(data) => Some(data),
+        chain_data::ChainData::CosmosEvmChainData(data) => data.cosmos_chain_data.as_ref(),
+        chain_data::ChainData::EthChainData(_) => None,
+    }
+}
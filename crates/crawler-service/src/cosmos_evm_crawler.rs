@@ -1,15 +1,18 @@
 use super::{
     cosmos_crawler::{CosmosCrawler, CosmosCrawlerStorage},
     eth_crawler::{EthCrawler, EthCrawlerStorage},
-    storage::CrawlerStorage,
+    storage::{CrawlerStorage, LeasedStorage},
     CrawlChain,
 };
+use std::sync::{atomic::AtomicBool, Arc};
+
 use mempools_api::api::{
     crawler_data::CrawlerData, Chain, CosmosCrawlerData, CosmosEvmChainData, CosmosEvmCrawlerData,
     EthCrawlerData,
 };
+use tokio::sync::Mutex;
 
-use util::{service_registry::ServiceRegistry, Result};
+use util::{pipeline::PipelineConfig, service_registry::ServiceRegistry, Result};
 
 #[derive(Clone)]
 pub struct CosmosEvmCrawler<S: CrawlerStorage> {
@@ -21,14 +24,17 @@ impl<S: CrawlerStorage> CosmosEvmCrawler<S> {
     pub async fn new(
         chain_data: CosmosEvmChainData,
         registry: ServiceRegistry,
-        store: S,
+        store: LeasedStorage<S>,
+        config: &PipelineConfig,
     ) -> Result<Self> {
+        let store = CosmosEvmStorage::new(store);
         let cosmos_crawler = CosmosCrawler::new(
             chain_data
                 .cosmos_chain_data
                 .ok_or("could not find cosmos chain data")?,
             registry.clone(),
-            CosmosEvmStorage(store.clone()),
+            store.clone(),
+            config,
         )
         .await?;
         let eth_crawler = EthCrawler::new(
@@ -36,7 +42,8 @@ impl<S: CrawlerStorage> CosmosEvmCrawler<S> {
                 .eth_chain_data
                 .ok_or("could not find eth chain data")?,
             registry,
-            CosmosEvmStorage(store),
+            store,
+            config,
         )
         .await?;
 
@@ -49,35 +56,62 @@ impl<S: CrawlerStorage> CosmosEvmCrawler<S> {
 
 #[tonic::async_trait]
 impl<S: CrawlerStorage> CrawlChain for CosmosEvmCrawler<S> {
-    async fn try_crawl_chain(&self, chain: &Chain) -> Result<()> {
-        let h1 = self.cosmos_crawler.try_crawl_chain(chain);
-        let h2 = self.eth_crawler.try_crawl_chain(chain);
+    async fn try_crawl_chain(&self, chain: &Chain, stop: &AtomicBool) -> Result<()> {
+        let h1 = self.cosmos_crawler.try_crawl_chain(chain, stop);
+        let h2 = self.eth_crawler.try_crawl_chain(chain, stop);
 
         tokio::try_join!(h1, h2)?;
 
         Ok(())
     }
+
+    async fn head_height(&self) -> Result<u64> {
+        let (cosmos_height, eth_height) = tokio::try_join!(
+            self.cosmos_crawler.head_height(),
+            self.eth_crawler.head_height()
+        )?;
+
+        Ok(cosmos_height.min(eth_height))
+    }
+
+    async fn backfill_chain(
+        &self,
+        chain: &Chain,
+        from_height: u64,
+        to_height: u64,
+    ) -> Vec<(u64, String)> {
+        let h1 = self
+            .cosmos_crawler
+            .backfill_chain(chain, from_height, to_height);
+        let h2 = self
+            .eth_crawler
+            .backfill_chain(chain, from_height, to_height);
+
+        let (mut failures, eth_failures) = tokio::join!(h1, h2);
+        failures.extend(eth_failures);
+
+        failures
+    }
 }
 
 #[derive(Clone)]
-pub struct CosmosEvmStorage<S: CrawlerStorage>(S);
+pub struct CosmosEvmStorage<S: CrawlerStorage> {
+    store: LeasedStorage<S>,
+    // Both crawlers read-modify-write the same crawler row
+    lock: Arc<Mutex<()>>,
+}
 
-#[tonic::async_trait]
-impl<S: CrawlerStorage> CosmosCrawlerStorage for CosmosEvmStorage<S> {
-    async fn get_last_processed_block(&self, chain_id: &str) -> Result<i64> {
-        if let CrawlerData::CosmosEvm(data) = self.0.get_crawler_data(chain_id).await? {
-            return Ok(data
-                .cosmos
-                .ok_or("could not find cosmos data")?
-                .processed_blocks as i64);
-        } else {
-            return Err("unexpected crawler data".into());
+impl<S: CrawlerStorage> CosmosEvmStorage<S> {
+    fn new(store: LeasedStorage<S>) -> Self {
+        Self {
+            store,
+            lock: Arc::new(Mutex::new(())),
         }
     }
 
-    async fn set_last_processed_block(&self, chain_id: &str, height: i64) -> Result<()> {
-        if let CrawlerData::CosmosEvm(mut data) =
-            self.0
+    async fn get_cosmos_evm_crawler_data(&self, chain_id: &str) -> Result<CosmosEvmCrawlerData> {
+        if let CrawlerData::CosmosEvm(data) =
+            self.store
                 .get_crawler_data(chain_id)
                 .await
                 .unwrap_or(CrawlerData::CosmosEvm(CosmosEvmCrawlerData {
@@ -85,55 +119,55 @@ impl<S: CrawlerStorage> CosmosCrawlerStorage for CosmosEvmStorage<S> {
                     ethereum: None,
                 }))
         {
-            data.cosmos = Some(CosmosCrawlerData {
-                processed_blocks: height as u64,
-            });
-
-            self.0
-                .set_crawler_data(chain_id, CrawlerData::CosmosEvm(data))
-                .await?;
-
-            return Ok(());
+            Ok(data)
         } else {
-            return Err("unexpected crawler data".into());
+            Err("unexpected crawler data".into())
         }
     }
 }
 
 #[tonic::async_trait]
-impl<S: CrawlerStorage> EthCrawlerStorage for CosmosEvmStorage<S> {
-    async fn get_last_processed_block(&self, chain_id: &str) -> Result<i64> {
-        if let CrawlerData::CosmosEvm(data) = self.0.get_crawler_data(chain_id).await? {
-            return Ok(data
-                .ethereum
-                .ok_or("could not find cosmos data")?
-                .processed_blocks as i64);
+impl<S: CrawlerStorage> CosmosCrawlerStorage for CosmosEvmStorage<S> {
+    async fn get_crawler_state(&self, chain_id: &str) -> Result<CosmosCrawlerData> {
+        if let CrawlerData::CosmosEvm(data) = self.store.get_crawler_data(chain_id).await? {
+            return Ok(data.cosmos.ok_or("could not find cosmos data")?);
         } else {
             return Err("unexpected crawler data".into());
         }
     }
 
-    async fn set_last_processed_block(&self, chain_id: &str, height: i64) -> Result<()> {
-        if let CrawlerData::CosmosEvm(mut data) =
-            self.0
-                .get_crawler_data(chain_id)
-                .await
-                .unwrap_or(CrawlerData::CosmosEvm(CosmosEvmCrawlerData {
-                    cosmos: None,
-                    ethereum: None,
-                }))
-        {
-            data.ethereum = Some(EthCrawlerData {
-                processed_blocks: height as u64,
-            });
+    async fn set_crawler_state(&self, chain_id: &str, state: CosmosCrawlerData) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.get_cosmos_evm_crawler_data(chain_id).await?;
+        data.cosmos = Some(state);
 
-            self.0
-                .set_crawler_data(chain_id, CrawlerData::CosmosEvm(data))
-                .await?;
+        self.store
+            .set_crawler_data(chain_id, CrawlerData::CosmosEvm(data))
+            .await?;
 
-            return Ok(());
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl<S: CrawlerStorage> EthCrawlerStorage for CosmosEvmStorage<S> {
+    async fn get_crawler_state(&self, chain_id: &str) -> Result<EthCrawlerData> {
+        if let CrawlerData::CosmosEvm(data) = self.store.get_crawler_data(chain_id).await? {
+            return Ok(data.ethereum.ok_or("could not find eth data")?);
         } else {
             return Err("unexpected crawler data".into());
         }
     }
+
+    async fn set_crawler_state(&self, chain_id: &str, state: EthCrawlerData) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.get_cosmos_evm_crawler_data(chain_id).await?;
+        data.ethereum = Some(state);
+
+        self.store
+            .set_crawler_data(chain_id, CrawlerData::CosmosEvm(data))
+            .await?;
+
+        Ok(())
+    }
 }
@@ -1,30 +1,81 @@
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 use log::warn;
-use mempools_api::api::{crawler_data::CrawlerData, Chain, EthChainData, EthCrawlerData};
+use mempools_api::api::{
+    crawler_data::CrawlerData,
+    eth_chain_data::{BlockTag, TraceMode},
+    BackendUserAlert, Chain, EthBlockRef, EthChainData, EthCrawlerData, FailedBlock,
+};
 
 use util::{
-    clients::new_eth_client,
+    clients::{new_eth_client, EthClient},
     service_registry::{
-        AlertSourceEthLog, AlertSourceEthTx, ProcessAlertSourceRequeust, ServiceRegistry,
+        AlertSourceEthInternalTransfer, AlertSourceEthLog, AlertSourceEthTx,
+        ProcessAlertSourceRequeust, ServiceRegistry,
     },
     HashString,
 };
 use web3::{
-    transports::Http,
-    types::{BlockNumber, Transaction},
+    types::{
+        Block, BlockId, BlockNumber, Transaction, TransactionId, TransactionReceipt, H256, U64,
+    },
+    BatchTransport, Transport,
 };
 
-use util::Result;
+use util::{
+    pipeline::{PipelineConfig, WorkTracker},
+    Result,
+};
 
-use super::{storage::CrawlerStorage, CrawlChain};
+use tokio::{sync::mpsc, task::JoinSet};
+
+use super::{
+    blocks::{
+        self, block_batches, join_all, retryable_blocks, update_failed_blocks, CrawlerWorkers,
+        BLOCK_BATCH_SIZE,
+    },
+    heads::{eth_new_heads_request, subscribe, HeadSubscription},
+    mempool::{eth_pending_txs_request, SeenTxs, MEMPOOL_POLL_INTERVAL},
+    status::CrawlerStatusTracker,
+    storage::{CrawlerStorage, LeasedStorage},
+    traces::{self, InternalTransfer},
+    CrawlChain,
+};
+
+// Number of processed block hashes kept around to find the fork point of a reorg
+const MAX_REORG_DEPTH: usize = 64;
+// Receipts requested per json rpc batch when eth_getBlockReceipts is not available
+const RECEIPT_BATCH_SIZE: usize = 100;
+// Block traces take far longer than other requests on large blocks
+const TRACE_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+// Pending tx hashes buffered from the subscription, newer hashes are dropped while it is full
+const PENDING_TX_QUEUE_SIZE: usize = 10_000;
+
+// Txs of each block whose notifications went out, so block retries do not send them again
+type ProcessedTxs = Arc<Mutex<HashMap<u64, HashSet<String>>>>;
 
 #[derive(Clone)]
 pub struct EthCrawler<S: EthCrawlerStorage> {
     pub chain_data: EthChainData,
     pub registry: ServiceRegistry,
     pub store: S,
-    pub client: web3::Web3<Http>,
+    pub workers: CrawlerWorkers,
+    // When set, blocks are only matched against this alert instead of every alert of the chain
+    pub alert: Option<BackendUserAlert>,
+    pub heads: HeadSubscription,
+    pub status: CrawlerStatusTracker,
+    pub client: EthClient,
+    // Cleared once the node turns out not to support eth_getBlockReceipts
+    pub block_receipts: Arc<AtomicBool>,
+    // Notifications of processed blocks still in the pipeline
+    pub work: WorkTracker,
 }
 
 impl<S: EthCrawlerStorage> EthCrawler<S> {
@@ -32,74 +83,420 @@ impl<S: EthCrawlerStorage> EthCrawler<S> {
         chain_data: EthChainData,
         registry: ServiceRegistry,
         store: S,
+        config: &PipelineConfig,
     ) -> Result<Self> {
-        let client = new_eth_client(&chain_data.eth_rpc_endpoint).await?;
+        let client = new_eth_client(&chain_data).await?;
 
         Ok(Self {
             chain_data,
             registry,
             store,
+            workers: CrawlerWorkers::new(config),
+            alert: None,
+            heads: HeadSubscription::default(),
+            status: CrawlerStatusTracker::default(),
             client,
+            block_receipts: Arc::new(AtomicBool::new(true)),
+            work: WorkTracker::default(),
         })
     }
 
     // Create crawler - remove intialize
     pub async fn initialize_chain(&self, chain_id: &str) -> Result<()> {
-        if self.store.get_last_processed_block(chain_id).await.is_err() {
-            let curr_block_height = self.client.eth().block_number().await?.as_u64();
+        if self.store.get_crawler_state(chain_id).await.is_err() {
+            let curr_block_height = self.get_head_block_height().await?;
             self.store
-                .set_last_processed_block(chain_id, curr_block_height as i64)
+                .set_crawler_state(
+                    chain_id,
+                    EthCrawlerData {
+                        processed_blocks: curr_block_height,
+                        ..Default::default()
+                    },
+                )
                 .await?;
         }
 
         Ok(())
     }
 
-    async fn process_block(&self, chain: Chain, curr_block_height: u64) -> Result<()> {
+    /// Height of the newest block the crawler is allowed to process, taking the chain's block tag
+    /// and confirmation depth into account
+    async fn get_head_block_height(&self) -> Result<u64> {
+        Ok(self.get_head_heights().await?.1)
+    }
+
+    /// Height of the chain head along with the newest block the crawler is allowed to process
+    async fn get_head_heights(&self) -> Result<(u64, u64)> {
+        let head = self.client.eth().block_number().await?;
+        let tagged_head = match self.chain_data.block_tag() {
+            BlockTag::Latest => head,
+            BlockTag::Safe => self.get_tagged_block_height("safe").await?,
+            BlockTag::Finalized => self.get_tagged_block_height("finalized").await?,
+        };
+
+        Ok((
+            head.as_u64(),
+            tagged_head
+                .as_u64()
+                .saturating_sub(self.chain_data.confirmations),
+        ))
+    }
+
+    // web3 does not support the safe/finalized block tags yet
+    async fn get_tagged_block_height(&self, tag: &str) -> Result<U64> {
+        let block: Block<H256> = web3::helpers::decode(
+            self.client
+                .transport()
+                .execute(
+                    "eth_getBlockByNumber",
+                    vec![
+                        web3::helpers::serialize(&tag),
+                        web3::helpers::serialize(&false),
+                    ],
+                )
+                .await?,
+        )?;
+
+        Ok(block.number.ok_or("could not find tagged block number")?)
+    }
+
+    async fn get_block_ref(&self, height: u64) -> Result<(EthBlockRef, H256)> {
         let block = self
             .client
             .eth()
-            .block_with_txs(web3::types::BlockId::Number(BlockNumber::Number(
-                curr_block_height.into(),
-            )))
+            .block(BlockId::Number(BlockNumber::Number(height.into())))
             .await?
             .ok_or("could not find block at height")?;
 
+        let mut tx_hashes = vec![];
+        for tx_hash in block.transactions {
+            tx_hashes.push(tx_hash.hash_string()?);
+        }
+        let block_ref = EthBlockRef {
+            number: height,
+            hash: block
+                .hash
+                .ok_or("could not find block hash")?
+                .hash_string()?,
+            tx_hashes,
+        };
+
+        Ok((block_ref, block.parent_hash))
+    }
+
+    /// Walks back through the recently processed blocks until one is found that is still part of
+    /// the canonical chain, retracting notifications for every orphaned block on the way.
+    /// Returns the height of the fork point.
+    async fn handle_reorg(
+        &self,
+        chain: &Chain,
+        recent_blocks: &mut Vec<EthBlockRef>,
+    ) -> Result<u64> {
+        let mut orphaned_blocks = vec![];
+        while let Some(block) = recent_blocks.last() {
+            let (canonical_block, _) = self.get_block_ref(block.number).await?;
+            if canonical_block.hash == block.hash {
+                break;
+            }
+
+            orphaned_blocks.push(recent_blocks.pop().ok_or("could not find recent block")?);
+        }
+
+        let fork_height = match recent_blocks.last() {
+            Some(block) => block.number,
+            None => {
+                warn!(
+                    "reorg in chain {} is deeper than {} blocks, some orphaned blocks will not be retracted",
+                    chain.id, MAX_REORG_DEPTH
+                );
+                orphaned_blocks
+                    .last()
+                    .ok_or("could not find orphaned block")?
+                    .number
+                    - 1
+            }
+        };
+
+        warn!(
+            "detected reorg in chain {} - {} orphaned blocks, reprocessing from block {}",
+            chain.id,
+            orphaned_blocks.len(),
+            fork_height + 1
+        );
+
+        // Retractions have to reach the webhook after the notifications they retract
+        self.work.wait_idle().await;
+        for block in orphaned_blocks {
+            if let Err(err) = self.retract_block(chain, &block).await {
+                warn!(
+                    "failed to retract notifications for block {} ({}) in chain {} - {}",
+                    block.number, block.hash, chain.id, err
+                );
+            }
+        }
+
+        Ok(fork_height)
+    }
+
+    async fn retract_block(&self, chain: &Chain, block: &EthBlockRef) -> Result<()> {
+        self.registry
+            .get_services()
+            .await?
+            .notification_service
+            .retract_notifications(
+                chain.id.clone(),
+                block.tx_hashes.clone(),
+                block.hash.clone(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_alert_source(&self, req: ProcessAlertSourceRequeust) -> Result<()> {
+        let filter_svc = self.registry.get_services().await?.filter_service;
+        match &self.alert {
+            Some(alert) => {
+                filter_svc
+                    .process_alert_for_alert_source(req, alert.clone(), self.work.token())
+                    .await
+            }
+            None => {
+                filter_svc
+                    .process_alert_source(req, self.work.token())
+                    .await
+            }
+        }
+    }
+
+    /// Processes the txs of the block along with their logs and internal transfers. Txs in
+    /// `processed_txs` are skipped, txs are added to it once all of their sources went out
+    async fn process_block(
+        &self,
+        chain: Chain,
+        height: u64,
+        block_hash: H256,
+        processed_txs: ProcessedTxs,
+    ) -> Result<()> {
+        let block = self
+            .client
+            .eth()
+            .block_with_txs(BlockId::Hash(block_hash))
+            .await?
+            .ok_or("could not find block with hash")?;
+
+        let mut receipts = self.get_block_receipts(&block).await?;
+        let mut transfers: HashMap<H256, Vec<AlertSourceEthInternalTransfer>> = HashMap::new();
+        // Txs and logs still go out when the node cannot trace the block
+        let internal_transfers = match self.get_internal_transfers(&block).await {
+            Ok(internal_transfers) => internal_transfers,
+            Err(err) => {
+                warn!(
+                    "could not trace block {} in chain {}, skipping its internal transfers - {}",
+                    height, chain.id, err
+                );
+                vec![]
+            }
+        };
+        for transfer in internal_transfers {
+            let tx_transfers = transfers.entry(transfer.tx_hash).or_default();
+            tx_transfers.push(AlertSourceEthInternalTransfer {
+                chain_id: chain.id.clone(),
+                chain_data: self.chain_data.clone(),
+                tx_hash: transfer.tx_hash.hash_string()?,
+                from: transfer.from,
+                to: transfer.to,
+                value: transfer.value,
+                transfer_index: tx_transfers.len() as u64,
+            });
+        }
+
+        let processed = processed_txs
+            .lock()
+            .unwrap()
+            .get(&height)
+            .cloned()
+            .unwrap_or_default();
+        let mut tasks = JoinSet::new();
         for tx in block.transactions {
+            let tx_hash = tx.hash.hash_string()?;
+            if processed.contains(&tx_hash) {
+                continue;
+            }
+
             let svc = self.clone();
             let chain = chain.clone();
-            tokio::spawn(async move {
-                let tx_hash = tx.hash;
-                if let Err(err) = svc.process_tx(chain.clone(), tx).await {
+            let tx_resp = receipts
+                .remove(&tx.hash)
+                .ok_or("could not find reciept for transaction")?;
+            let tx_transfers = transfers.remove(&tx.hash).unwrap_or_default();
+            let processed_txs = processed_txs.clone();
+            let permit = self.workers.txs.clone().acquire_owned().await?;
+            tasks.spawn(async move {
+                let _permit = permit;
+                let mut res = svc.process_tx(chain.clone(), tx, Some(tx_resp)).await;
+                if let Err(err) = &res {
                     warn!(
                         "failed to execute tx {} in chain {} - {}",
                         tx_hash, chain.id, err
                     )
                 }
+                for transfer in tx_transfers {
+                    let transfer_res = svc
+                        .process_alert_source(ProcessAlertSourceRequeust::EthInternalTransfer(
+                            Box::new(transfer.clone()),
+                        ))
+                        .await;
+                    if let Err(err) = transfer_res {
+                        warn!(
+                            "failed to process internal transfer {} in tx {} in chain {} - {}",
+                            transfer.transfer_index, transfer.tx_hash, transfer.chain_id, err
+                        );
+                        res = res.and(Err(err));
+                    }
+                }
+                if res.is_ok() {
+                    processed_txs
+                        .lock()
+                        .unwrap()
+                        .entry(height)
+                        .or_default()
+                        .insert(tx_hash);
+                }
+                res
             });
         }
 
-        Ok(())
+        join_all(tasks).await
     }
 
-    async fn process_tx(&self, chain: Chain, tx: Transaction) -> Result<()> {
-        let tx_hash = tx.hash;
+    /// Value transfers made by contracts in the block, only traced when the chain has a trace
+    /// mode set
+    async fn get_internal_transfers(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<InternalTransfer>> {
+        if block.transactions.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let block_hash = block.hash.ok_or("could not find block hash")?;
+        match self.chain_data.trace_mode() {
+            TraceMode::None => Ok(vec![]),
+            TraceMode::DebugTraceBlock => {
+                let res = self
+                    .client
+                    .transport()
+                    .execute_with_timeout(
+                        "debug_traceBlockByHash",
+                        vec![
+                            web3::helpers::serialize(&block_hash),
+                            serde_json::json!({ "tracer": "callTracer" }),
+                        ],
+                        TRACE_REQUEST_TIMEOUT,
+                    )
+                    .await?;
+                let tx_hashes: Vec<H256> = block.transactions.iter().map(|tx| tx.hash).collect();
+                traces::call_tracer_transfers(&tx_hashes, &res)
+            }
+            TraceMode::TraceBlock => {
+                let number = block.number.ok_or("could not find block number")?;
+                let res = self
+                    .client
+                    .transport()
+                    .execute_with_timeout(
+                        "trace_block",
+                        vec![web3::helpers::serialize(&number)],
+                        TRACE_REQUEST_TIMEOUT,
+                    )
+                    .await?;
+                traces::parity_transfers(block_hash, &res)
+            }
+        }
+    }
 
-        let registry = self.registry.get_services().await?;
-        let filter_svc = registry.filter_service;
+    /// Receipts of every tx in the block keyed by tx hash, using eth_getBlockReceipts where the
+    /// node supports it and batched eth_getTransactionReceipt calls otherwise
+    async fn get_block_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<HashMap<H256, TransactionReceipt>> {
+        if block.transactions.is_empty() {
+            return Ok(HashMap::new());
+        }
 
-        let tx_resp = self
-            .client
-            .eth()
-            .transaction_receipt(tx_hash)
-            .await?
-            .ok_or("could not find reciept for transaction")?;
+        if self.block_receipts.load(Ordering::Relaxed) {
+            let block_hash = block.hash.ok_or("could not find block hash")?;
+            let res = self
+                .client
+                .transport()
+                .execute(
+                    "eth_getBlockReceipts",
+                    vec![web3::helpers::serialize(&block_hash)],
+                )
+                .await;
+
+            match res {
+                Ok(receipts) => {
+                    let receipts: Option<Vec<TransactionReceipt>> =
+                        web3::helpers::decode(receipts)?;
+                    return Ok(receipts
+                        .ok_or("could not find receipts for block")?
+                        .into_iter()
+                        .map(|r| (r.transaction_hash, r))
+                        .collect());
+                }
+                Err(web3::Error::Rpc(err)) => {
+                    if method_unsupported(&err) {
+                        warn!(
+                            "eth_getBlockReceipts is not supported by {} - batching receipts instead - {}",
+                            self.chain_data.eth_rpc_endpoint, err
+                        );
+                        self.block_receipts.store(false, Ordering::Relaxed);
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut receipts = HashMap::new();
+        for txs in block.transactions.chunks(RECEIPT_BATCH_SIZE) {
+            let transport = self.client.transport();
+            let requests = txs.iter().map(|tx| {
+                transport.prepare(
+                    "eth_getTransactionReceipt",
+                    vec![web3::helpers::serialize(&tx.hash)],
+                )
+            });
+
+            for res in transport.send_batch(requests).await? {
+                let receipt: Option<TransactionReceipt> = web3::helpers::decode(res?)?;
+                let receipt = receipt.ok_or("could not find reciept for transaction")?;
+                receipts.insert(receipt.transaction_hash, receipt);
+            }
+        }
+
+        Ok(receipts)
+    }
+
+    /// Processes a tx and its logs, `tx_resp` is missing while the tx is pending
+    async fn process_tx(
+        &self,
+        chain: Chain,
+        tx: Transaction,
+        tx_resp: Option<TransactionReceipt>,
+    ) -> Result<()> {
+        let tx_hash = tx.hash;
 
         let chain_id = chain.id.clone();
         let chain_data = self.chain_data.clone();
         let tx_hash = tx_hash.hash_string()?;
 
-        if tx_resp.status.unwrap_or_default().as_u64() != 0 {
+        let mut tasks = JoinSet::new();
+        if let Some(tx_resp) = tx_resp
+            .as_ref()
+            .filter(|r| r.status.unwrap_or_default().as_u64() != 0)
+        {
             for i in 0..tx_resp.logs.len() {
                 let req = AlertSourceEthLog {
                     chain_id: chain_id.clone(),
@@ -113,13 +510,15 @@ impl<S: EthCrawlerStorage> EthCrawler<S> {
                         .clone(),
                 };
                 let svc = self.clone();
-                tokio::spawn(async move {
-                    if let Err(err) = svc.process_eth_log(req.clone()).await {
+                tasks.spawn(async move {
+                    let res = svc.process_eth_log(req.clone()).await;
+                    if let Err(err) = &res {
                         warn!(
                             "failed to process log {} in tx {} in chain {} - {}",
                             req.log_index, req.tx_hash, req.chain_id, err
                         )
                     }
+                    res
                 });
             }
         }
@@ -132,82 +531,344 @@ impl<S: EthCrawlerStorage> EthCrawler<S> {
             tx_resp,
         };
 
-        filter_svc
-            .process_alert_source(ProcessAlertSourceRequeust::EthTx(Box::new(eth_tx)))
-            .await
-            .ok();
+        self.process_alert_source(ProcessAlertSourceRequeust::EthTx(Box::new(eth_tx)))
+            .await?;
 
-        Ok(())
+        join_all(tasks).await
     }
 
     async fn process_eth_log(&self, req: AlertSourceEthLog) -> Result<()> {
-        let registry = self.registry.get_services().await?;
-        let filter_svc = registry.filter_service;
-
-        filter_svc
-            .process_alert_source(ProcessAlertSourceRequeust::EthLog(Box::new(req)))
+        self.process_alert_source(ProcessAlertSourceRequeust::EthLog(Box::new(req)))
             .await?;
 
         Ok(())
     }
+
+    /// Processes pending txs as they enter the node's mempool
+    async fn watch_mempool(&self, chain: &Chain) -> Result<()> {
+        if self.chain_data.eth_ws_endpoint.is_empty() {
+            return self.poll_txpool(chain).await;
+        }
+
+        let (sender, mut receiver) = mpsc::channel(PENDING_TX_QUEUE_SIZE);
+        let subscription = subscribe(
+            &self.chain_data.eth_ws_endpoint,
+            eth_pending_txs_request(),
+            move |msg| {
+                if let Some(tx_hash) = msg["params"]["result"].as_str() {
+                    let _ = sender.try_send(tx_hash.to_string());
+                }
+            },
+        );
+
+        let process = async {
+            let mut seen_txs = SeenTxs::default();
+            while let Some(tx_hash) = receiver.recv().await {
+                if !seen_txs.insert(&tx_hash) {
+                    continue;
+                }
+
+                let svc = self.clone();
+                let chain = chain.clone();
+                let permit = self.workers.txs.clone().acquire_owned().await?;
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    if let Err(err) = svc.process_pending_tx(chain.clone(), &tx_hash).await {
+                        warn!(
+                            "failed to execute pending tx {} in chain {} - {}",
+                            tx_hash, chain.id, err
+                        )
+                    }
+                });
+            }
+
+            Ok(())
+        };
+
+        tokio::select! {
+            _ = subscription => Ok(()),
+            res = process => res,
+        }
+    }
+
+    async fn process_pending_tx(&self, chain: Chain, tx_hash: &str) -> Result<()> {
+        // The tx may have been included or dropped in the meantime
+        let tx = match self
+            .client
+            .eth()
+            .transaction(TransactionId::Hash(tx_hash.parse()?))
+            .await?
+        {
+            Some(tx) if tx.block_hash.is_none() => tx,
+            _ => return Ok(()),
+        };
+
+        self.process_tx(chain, tx, None).await
+    }
+
+    /// Fallback for nodes without a websocket endpoint, polls the pending part of the txpool
+    async fn poll_txpool(&self, chain: &Chain) -> Result<()> {
+        let mut seen_txs = SeenTxs::default();
+        loop {
+            match self.client.txpool().content().await {
+                Ok(content) => {
+                    for tx in content
+                        .pending
+                        .into_values()
+                        .flat_map(|txs| txs.into_values())
+                    {
+                        let tx_hash = tx.hash.hash_string()?;
+                        if !seen_txs.insert(&tx_hash) {
+                            continue;
+                        }
+
+                        if let Err(err) = self.process_tx(chain.clone(), tx, None).await {
+                            warn!(
+                                "failed to execute pending tx {} in chain {} - {}",
+                                tx_hash, chain.id, err
+                            )
+                        }
+                    }
+                }
+                Err(err) => warn!("failed to poll txpool of chain {} - {}", chain.id, err),
+            }
+
+            tokio::time::sleep(MEMPOOL_POLL_INTERVAL).await;
+        }
+    }
 }
 
 #[tonic::async_trait]
 impl<S: EthCrawlerStorage> CrawlChain for EthCrawler<S> {
-    async fn try_crawl_chain(&self, chain: &Chain) -> Result<()> {
+    async fn try_crawl_chain(&self, chain: &Chain, stop: &AtomicBool) -> Result<()> {
         self.initialize_chain(&chain.id).await?;
-        loop {
-            let latest_block_height = self.client.eth().block_number().await?.as_u64();
-
-            let curr_block_height = self.store.get_last_processed_block(&chain.id).await? as u64;
-            if curr_block_height != latest_block_height {
-                for i in (curr_block_height + 1)..=latest_block_height {
-                    let svc = self.clone();
-                    let chain = chain.clone();
-                    tokio::spawn(async move {
-                        if let Err(err) = svc.process_block(chain.clone(), i).await {
-                            warn!(
-                                "failed to execute block {} in chain {} - {}",
-                                i, chain.id, err
-                            );
-                        }
-                    });
+
+        // The subscription only wakes up the crawl loop early, polling continues regardless
+        let subscription = async {
+            if self.chain_data.eth_ws_endpoint.is_empty() {
+                return std::future::pending().await;
+            }
+            self.heads
+                .run(&self.chain_data.eth_ws_endpoint, eth_new_heads_request())
+                .await
+        };
+        let mempool = async {
+            if !self.chain_data.watch_mempool {
+                return std::future::pending().await;
+            }
+            // Pending txs do not hold back the cursor
+            let svc = Self {
+                work: WorkTracker::default(),
+                ..self.clone()
+            };
+            svc.watch_mempool(chain).await
+        };
+
+        tokio::select! {
+            res = self.crawl_blocks(chain, stop) => res,
+            _ = subscription => Ok(()),
+            res = mempool => res,
+        }
+    }
+
+    async fn head_height(&self) -> Result<u64> {
+        self.get_head_block_height().await
+    }
+
+    async fn backfill_chain(
+        &self,
+        chain: &Chain,
+        from_height: u64,
+        to_height: u64,
+    ) -> Vec<(u64, String)> {
+        let mut failures = vec![];
+        for heights in block_batches(from_height, to_height) {
+            failures.extend(
+                self.process_blocks(chain, heights, HashMap::new(), Arc::default())
+                    .await,
+            );
+        }
+        self.work.wait_idle().await;
+
+        failures
+    }
+}
+
+impl<S: EthCrawlerStorage> EthCrawler<S> {
+    /// Processes new blocks as they come in, persisting the crawler state once the notifications of
+    /// a batch are sent
+    async fn crawl_blocks(&self, chain: &Chain, stop: &AtomicBool) -> Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            let (head_height, latest_block_height) = self.get_head_heights().await?;
+            let mut state = self.store.get_crawler_state(&chain.id).await?;
+            self.status
+                .report_progress(&chain.id, "ethereum", head_height, state.processed_blocks);
+
+            let processed_txs: ProcessedTxs = Arc::new(Mutex::new(
+                state
+                    .failed_blocks
+                    .iter()
+                    .map(|b| (b.height, b.processed_txs.iter().cloned().collect()))
+                    .collect(),
+            ));
+
+            let retry_heights = retryable_blocks(&state.failed_blocks);
+            if !retry_heights.is_empty() {
+                let failures = self
+                    .process_blocks(
+                        chain,
+                        retry_heights.clone(),
+                        HashMap::new(),
+                        processed_txs.clone(),
+                    )
+                    .await;
+                update_failed_blocks(&mut state.failed_blocks, &retry_heights, failures);
+                mark_txs_processed(&mut state.failed_blocks, &processed_txs);
+            }
+
+            let curr_block_height = state.processed_blocks;
+            if curr_block_height >= latest_block_height {
+                if !retry_heights.is_empty() {
+                    self.work.wait_idle().await;
+                    self.store.set_crawler_state(&chain.id, state).await?;
                 }
+                self.heads.wait().await;
+                continue;
+            }
 
-                self.store
-                    .set_last_processed_block(&chain.id, latest_block_height as i64)
-                    .await?;
-            } else {
-                tokio::time::sleep(Duration::from_secs(5)).await
+            let to_height = latest_block_height.min(curr_block_height + BLOCK_BATCH_SIZE);
+            let mut block_hashes = HashMap::new();
+            let mut reorged = false;
+            for i in (curr_block_height + 1)..=to_height {
+                let (block, parent_hash) = self.get_block_ref(i).await?;
+
+                if let Some(prev_block) = state.recent_blocks.last() {
+                    if prev_block.number + 1 == i && prev_block.hash != parent_hash.hash_string()? {
+                        reorged = true;
+                        break;
+                    }
+                }
+
+                block_hashes.insert(i, block.hash.parse()?);
+                state.recent_blocks.push(block);
+                if state.recent_blocks.len() > MAX_REORG_DEPTH {
+                    state.recent_blocks.remove(0);
+                }
             }
+
+            let processed_height = if reorged {
+                let fork_height = self.handle_reorg(chain, &mut state.recent_blocks).await?;
+                block_hashes.retain(|height, _| *height <= fork_height);
+                state.failed_blocks.retain(|b| b.height <= fork_height);
+                fork_height
+            } else {
+                to_height
+            };
+
+            let mut heights: Vec<u64> = block_hashes.keys().cloned().collect();
+            heights.sort();
+            let failures = self
+                .process_blocks(chain, heights.clone(), block_hashes, processed_txs.clone())
+                .await;
+            update_failed_blocks(&mut state.failed_blocks, &heights, failures);
+            mark_txs_processed(&mut state.failed_blocks, &processed_txs);
+
+            // The cursor only moves once the notifications of the batch are sent
+            self.work.wait_idle().await;
+            state.processed_blocks = processed_height;
+            self.store.set_crawler_state(&chain.id, state).await?;
         }
+
+        Ok(())
+    }
+
+    /// Processes the given blocks, looking up the canonical hash of any block missing from
+    /// `block_hashes`
+    async fn process_blocks(
+        &self,
+        chain: &Chain,
+        heights: Vec<u64>,
+        block_hashes: HashMap<u64, H256>,
+        processed_txs: ProcessedTxs,
+    ) -> Vec<(u64, String)> {
+        blocks::process_blocks(&chain.id, &self.workers, heights, |height| {
+            let svc = self.clone();
+            let chain = chain.clone();
+            let block_hash = block_hashes.get(&height).cloned();
+            let processed_txs = processed_txs.clone();
+            async move {
+                let block_hash = match block_hash {
+                    Some(block_hash) => block_hash,
+                    None => svc.get_block_ref(height).await?.0.hash.parse()?,
+                };
+                svc.process_block(chain, height, block_hash, processed_txs)
+                    .await
+            }
+        })
+        .await
+    }
+}
+
+/// Remembers which txs of the failed blocks already went out
+fn mark_txs_processed(
+    failed_blocks: &mut [FailedBlock],
+    processed_txs: &Mutex<HashMap<u64, HashSet<String>>>,
+) {
+    let processed_txs = processed_txs.lock().unwrap();
+    for block in failed_blocks {
+        let mut tx_hashes: Vec<String> = processed_txs
+            .get(&block.height)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        tx_hashes.sort();
+        block.processed_txs = tx_hashes;
     }
 }
 
+/// Whether the node does not serve the requested method. Besides the standard error code, nodes
+/// answer with a generic server error such as -32000 "method not supported"
+fn method_unsupported(err: &jsonrpc_core::Error) -> bool {
+    if err.code == jsonrpc_core::ErrorCode::MethodNotFound {
+        return true;
+    }
+
+    let message = err.message.to_lowercase();
+    matches!(
+        err.code,
+        jsonrpc_core::ErrorCode::ServerError(_) | jsonrpc_core::ErrorCode::InvalidRequest
+    ) && [
+        "not supported",
+        "unsupported",
+        "not implemented",
+        "not available",
+        "does not exist",
+        "method not found",
+    ]
+    .iter()
+    .any(|m| message.contains(m))
+}
+
 #[tonic::async_trait]
 pub trait EthCrawlerStorage: Send + Sync + Clone + 'static {
-    async fn get_last_processed_block(&self, chain_id: &str) -> Result<i64>;
-    async fn set_last_processed_block(&self, chain_id: &str, height: i64) -> Result<()>;
+    async fn get_crawler_state(&self, chain_id: &str) -> Result<EthCrawlerData>;
+    async fn set_crawler_state(&self, chain_id: &str, state: EthCrawlerData) -> Result<()>;
 }
 
 #[tonic::async_trait]
-impl<T: CrawlerStorage> EthCrawlerStorage for T {
-    async fn get_last_processed_block(&self, chain_id: &str) -> Result<i64> {
+impl<T: CrawlerStorage> EthCrawlerStorage for LeasedStorage<T> {
+    async fn get_crawler_state(&self, chain_id: &str) -> Result<EthCrawlerData> {
         if let CrawlerData::Ethereum(data) = self.get_crawler_data(chain_id).await? {
-            return Ok(data.processed_blocks as i64);
+            return Ok(data);
         } else {
             return Err("unexpected crawler data".into());
         }
     }
 
-    async fn set_last_processed_block(&self, chain_id: &str, height: i64) -> Result<()> {
-        self.set_crawler_data(
-            chain_id,
-            CrawlerData::Ethereum(EthCrawlerData {
-                processed_blocks: height as u64,
-            }),
-        )
-        .await?;
+    async fn set_crawler_state(&self, chain_id: &str, state: EthCrawlerData) -> Result<()> {
+        self.set_crawler_data(chain_id, CrawlerData::Ethereum(state))
+            .await?;
 
         Ok(())
     }
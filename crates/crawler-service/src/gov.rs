@@ -0,0 +1,172 @@
+use cosmrs::{
+    proto::cosmos::base::abci::v1beta1::{AbciMessageLog, StringEvent},
+    Any,
+};
+use mempools_api::api::alert_cosmos_governance::Event;
+use util::{gov, service_registry::GovernanceUpdate, Result};
+
+/// Proposal steps taken by the msgs of a successful tx sharing `msg_log`, a msg and the msgs it
+/// wraps
+pub fn msg_governance_updates(
+    msgs: &[&Any],
+    msg_log: &AbciMessageLog,
+) -> Result<Vec<(Event, GovernanceUpdate)>> {
+    let mut updates = vec![];
+    let mut submitted = vec![];
+    // Every submitted proposal has its own event, in the order the msgs ran
+    let mut proposal_ids = event_attrs(&msg_log.events, "submit_proposal", "proposal_id");
+    for msg in msgs {
+        if let Some(proposal) = gov::decode_submit_proposal(msg)? {
+            let update = GovernanceUpdate {
+                proposal_id: proposal_ids
+                    .next()
+                    .ok_or("could not find submitted proposal id")?
+                    .parse()?,
+                title: proposal.title,
+                proposer: proposal.proposer,
+                ..Default::default()
+            };
+            updates.push((Event::ProposalSubmitted, update.clone()));
+            submitted.push(update);
+        }
+        if let Some(vote) = gov::decode_vote(msg)? {
+            updates.push((
+                Event::VoteCast,
+                GovernanceUpdate {
+                    proposal_id: vote.proposal_id,
+                    voter: vote.voter,
+                    options: vote
+                        .options
+                        .into_iter()
+                        .map(|(option, weight)| (option.as_str_name().to_string(), weight))
+                        .collect(),
+                    ..Default::default()
+                },
+            ));
+        }
+    }
+
+    // Submitting or depositing on a proposal starts its voting period once the deposit is enough
+    for attr in msg_log
+        .events
+        .iter()
+        .flat_map(|e| &e.attributes)
+        .filter(|a| a.key == "voting_period_start")
+    {
+        let proposal_id = attr.value.parse()?;
+        let update = match submitted.iter().find(|u| u.proposal_id == proposal_id) {
+            Some(update) => update.clone(),
+            None => GovernanceUpdate {
+                proposal_id,
+                ..Default::default()
+            },
+        };
+        updates.push((Event::VotingStarted, update));
+    }
+
+    Ok(updates)
+}
+
+/// Proposal whose deposit or voting period ended in a block, the tally of a finished vote is not
+/// part of the event
+pub fn block_governance_update(event: &StringEvent) -> Result<Option<(Event, GovernanceUpdate)>> {
+    let gov_event = match event.r#type.as_str() {
+        "inactive_proposal" => Event::DepositPeriodEnded,
+        "active_proposal" => Event::VotingEnded,
+        _ => return Ok(None),
+    };
+    let events = std::slice::from_ref(event);
+
+    Ok(Some((
+        gov_event,
+        GovernanceUpdate {
+            proposal_id: event_attr(events, &event.r#type, "proposal_id")
+                .ok_or("could not find proposal id")?
+                .parse()?,
+            result: event_attr(events, &event.r#type, "proposal_result")
+                .unwrap_or_default()
+                .to_string(),
+            ..Default::default()
+        },
+    )))
+}
+
+fn event_attr<'a>(events: &'a [StringEvent], event_type: &'a str, key: &'a str) -> Option<&'a str> {
+    event_attrs(events, event_type, key).next()
+}
+
+fn event_attrs<'a>(
+    events: &'a [StringEvent],
+    event_type: &'a str,
+    key: &'a str,
+) -> impl Iterator<Item = &'a str> {
+    events
+        .iter()
+        .filter(move |e| e.r#type == event_type)
+        .flat_map(|e| &e.attributes)
+        .filter(move |a| a.key == key)
+        .map(|a| a.value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmrs::proto::{
+        cosmos::{
+            base::abci::v1beta1::Attribute,
+            gov::v1beta1::{MsgSubmitProposal, TextProposal},
+        },
+        traits::Message,
+    };
+
+    use super::*;
+
+    fn submit_proposal(title: &str) -> Any {
+        Any {
+            type_url: gov::MSG_SUBMIT_PROPOSAL_V1BETA1.to_string(),
+            value: MsgSubmitProposal {
+                content: Some(Any {
+                    type_url: "/cosmos.gov.v1beta1.TextProposal".to_string(),
+                    value: TextProposal {
+                        title: title.to_string(),
+                        description: "".to_string(),
+                    }
+                    .encode_to_vec(),
+                }),
+                initial_deposit: vec![],
+                proposer: "cosmos1proposer".to_string(),
+            }
+            .encode_to_vec(),
+        }
+    }
+
+    #[test]
+    fn matches_each_submitted_proposal_to_its_event() -> Result<()> {
+        let (first, second) = (submit_proposal("first"), submit_proposal("second"));
+        // Msg logs merge the events of both proposals into one event
+        let msg_log = AbciMessageLog {
+            events: vec![StringEvent {
+                r#type: "submit_proposal".to_string(),
+                attributes: ["7", "8"]
+                    .into_iter()
+                    .map(|id| Attribute {
+                        key: "proposal_id".to_string(),
+                        value: id.to_string(),
+                    })
+                    .collect(),
+            }],
+            ..Default::default()
+        };
+
+        let updates = msg_governance_updates(&[&first, &second], &msg_log)?;
+        let submitted: Vec<_> = updates
+            .into_iter()
+            .map(|(_, update)| (update.proposal_id, update.title))
+            .collect();
+        assert_eq!(
+            submitted,
+            vec![(7, "first".to_string()), (8, "second".to_string())]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use std::{sync::Arc, time::Duration};
+
+use async_tungstenite::{tokio::connect_async, tungstenite::Message};
+use futures::{SinkExt, StreamExt};
+use log::warn;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use util::Result;
+
+// How long crawlers sleep between polls when no subscription is delivering heads
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// A subscription that stays silent for this long is considered dead and reopened
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Wakes up a crawler as soon as its node announces a new block over a websocket
+#[derive(Clone, Default)]
+pub struct HeadSubscription {
+    notify: Arc<Notify>,
+}
+
+impl HeadSubscription {
+    /// Waits for the next head, or for `POLL_INTERVAL` if none arrives. Returns whether
+    /// a head arrived
+    pub async fn wait(&self) -> bool {
+        tokio::time::timeout(POLL_INTERVAL, self.notify.notified())
+            .await
+            .is_ok()
+    }
+
+    /// Keeps a subscription open on `url`. Crawlers fall back to polling while it is down
+    pub async fn run(&self, url: &str, request: Value) {
+        subscribe(url, request, |msg| {
+            if is_new_head(&msg) {
+                self.notify.notify_one();
+            }
+        })
+        .await
+    }
+}
+
+/// Sends `request` over a websocket to `url` and hands every message that comes back to
+/// `on_msg`, reconnecting whenever the connection drops
+pub async fn subscribe(url: &str, request: Value, mut on_msg: impl FnMut(Value) + Send) {
+    loop {
+        if let Err(err) = try_subscribe(url, &request, &mut on_msg).await {
+            warn!(
+                "subscription to {} failed - {} - reconnecting in {} seconds",
+                url,
+                err,
+                RECONNECT_INTERVAL.as_secs()
+            );
+        }
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+async fn try_subscribe(
+    url: &str,
+    request: &Value,
+    on_msg: &mut (impl FnMut(Value) + Send),
+) -> Result<()> {
+    let (mut ws, _) = connect_async(url).await?;
+    ws.send(Message::Text(request.to_string())).await?;
+
+    loop {
+        let msg = match tokio::time::timeout(IDLE_TIMEOUT, ws.next()).await {
+            Ok(Some(msg)) => msg?,
+            Ok(None) => return Err("connection closed".into()),
+            Err(_) => return Err("no messages received".into()),
+        };
+
+        let msg: Value = match msg {
+            Message::Text(text) => serde_json::from_str(&text)?,
+            Message::Close(_) => return Err("connection closed".into()),
+            _ => continue,
+        };
+
+        if let Some(err) = msg.get("error") {
+            return Err(format!("subscription rejected - {}", err).into());
+        }
+
+        on_msg(msg);
+    }
+}
+
+/// `eth_subscribe` request for new block headers
+pub fn eth_new_heads_request() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["newHeads"],
+    })
+}
+
+/// CometBFT rpc `subscribe` request for new blocks
+pub fn cometbft_new_block_request() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "subscribe",
+        "params": { "query": "tm.event='NewBlock'" },
+    })
+}
+
+// Ethereum nodes push `eth_subscription` notifications, CometBFT repeats the subscribe
+// response with the event under `result.data`. The initial subscribe responses carry neither
+fn is_new_head(msg: &Value) -> bool {
+    msg["method"] == "eth_subscription" || !msg["result"]["data"].is_null()
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use cosmrs::proto::cosmos::base::abci::v1beta1::AbciMessageLog;
+use mempools_api::api::alert_cosmos_ibc_packet::Stage;
+use serde_json::Value;
+use util::service_registry::IbcPacket;
+
+/// Stage of an ICS-20 packet, found in the events of the msg that caused it
+pub struct IbcPacketEvent {
+    pub stage: Stage,
+    pub packet: IbcPacket,
+    pub error: Option<String>,
+}
+
+/// Transfer packets sent, received, acknowledged or timed out by a msg. Packets of other IBC
+/// applications are skipped
+pub fn ibc_packet_events(msg_log: &AbciMessageLog) -> Vec<IbcPacketEvent> {
+    // Attributes the transfer module adds about the packet it handled
+    let app_attrs = |event_type: &str| -> HashMap<&str, &str> {
+        msg_log
+            .events
+            .iter()
+            .filter(|e| e.r#type == event_type)
+            .flat_map(|e| &e.attributes)
+            .map(|a| (a.key.as_str(), a.value.as_str()))
+            .collect()
+    };
+    let token_attrs = app_attrs("fungible_token_packet");
+    let timeout_attrs = app_attrs("timeout");
+
+    let mut packets = vec![];
+    for event in &msg_log.events {
+        let attrs: HashMap<&str, &str> = event
+            .attributes
+            .iter()
+            .map(|a| (a.key.as_str(), a.value.as_str()))
+            .collect();
+        let attr = |key: &str| attrs.get(key).unwrap_or(&"").to_string();
+
+        let mut packet = IbcPacket {
+            sequence: attr("packet_sequence").parse().unwrap_or_default(),
+            src_port: attr("packet_src_port"),
+            src_channel: attr("packet_src_channel"),
+            dst_port: attr("packet_dst_port"),
+            dst_channel: attr("packet_dst_channel"),
+            ..Default::default()
+        };
+        // Only sent and received packets carry their data
+        if let Some(data) = packet_data(&attrs) {
+            let field = |key: &str| data[key].as_str().unwrap_or_default().to_string();
+            packet.sender = field("sender");
+            packet.receiver = field("receiver");
+            packet.denom = field("denom");
+            packet.amount = field("amount");
+        }
+
+        let (stage, error) = match event.r#type.as_str() {
+            "send_packet" => (Stage::Send, None),
+            "recv_packet" => {
+                let error = match token_attrs.get("success") {
+                    Some(&"false") => Some(
+                        token_attrs
+                            .get("error")
+                            .unwrap_or(&"packet was not received")
+                            .to_string(),
+                    ),
+                    _ => None,
+                };
+                (Stage::Recv, error)
+            }
+            "acknowledge_packet" => {
+                packet.sender = token_attrs.get("sender").unwrap_or(&"").to_string();
+                packet.receiver = token_attrs.get("receiver").unwrap_or(&"").to_string();
+                packet.denom = token_attrs.get("denom").unwrap_or(&"").to_string();
+                packet.amount = token_attrs.get("amount").unwrap_or(&"").to_string();
+                match token_attrs.get("error") {
+                    Some(error) => (Stage::AckError, Some(error.to_string())),
+                    None => (Stage::AckSuccess, None),
+                }
+            }
+            "timeout_packet" => {
+                // Only the refunded sender is known once a packet times out
+                packet.sender = timeout_attrs
+                    .get("refund_receiver")
+                    .unwrap_or(&"")
+                    .to_string();
+                packet.denom = timeout_attrs.get("refund_denom").unwrap_or(&"").to_string();
+                packet.amount = timeout_attrs
+                    .get("refund_amount")
+                    .unwrap_or(&"")
+                    .to_string();
+                (Stage::Timeout, None)
+            }
+            _ => continue,
+        };
+
+        if packet.denom.is_empty() {
+            continue;
+        }
+        packets.push(IbcPacketEvent {
+            stage,
+            packet,
+            error,
+        });
+    }
+
+    packets
+}
+
+// Json packet data of ICS-20 transfers, newer ibc-go versions only emit it hex encoded
+fn packet_data(attrs: &HashMap<&str, &str>) -> Option<Value> {
+    let data = match attrs.get("packet_data") {
+        Some(data) => data.as_bytes().to_vec(),
+        None => hex::decode(attrs.get("packet_data_hex")?).ok()?,
+    };
+
+    serde_json::from_slice(&data).ok()
+}
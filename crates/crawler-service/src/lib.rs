@@ -1,37 +1,191 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use eth_crawler::EthCrawler;
-use log::warn;
+use log::{info, warn};
 
-use mempools_api::api::{chain::Status, Chain};
+use mempools_api::api::{
+    chain::Status, crawler_cursor::Cursor, BackendUserAlert, Chain, ChainData, CrawlerCursor,
+    CrawlerStatus, FailedBlock,
+};
 
 use tokio::task::JoinHandle;
 
-use util::{service_registry::ServiceRegistry, Result};
+use util::{
+    pipeline::PipelineConfig,
+    service_registry::{CrawlerServiceInterface, ServiceRegistry},
+    Result,
+};
 
 use self::{
-    cosmos_crawler::CosmosCrawler, cosmos_evm_crawler::CosmosEvmCrawler, storage::CrawlerStorage,
+    cosmos_crawler::CosmosCrawler,
+    cosmos_evm_crawler::CosmosEvmCrawler,
+    status::CrawlerStatusTracker,
+    storage::{CrawlerStorage, LeasedStorage},
 };
 
+mod blocks;
 mod cosmos_crawler;
 mod cosmos_evm_crawler;
 mod eth_crawler;
+mod gov;
+pub mod heads;
+mod ibc;
+mod mempool;
+mod staking;
+mod status;
 mod storage;
+mod traces;
+
+// A replica that stops renewing its lease for this long has its chains taken over
+const LEASE_TTL: Duration = Duration::from_secs(60);
+const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(15);
+// How long a crawler gets to finish its current batch before it is aborted
+const GRACEFUL_STOP_TIMEOUT: Duration = Duration::from_secs(20);
+// Backfills run within the request, larger ranges have to be split up
+const MAX_BACKFILL_BLOCKS: u64 = 1_000;
 
 #[derive(Clone)]
 pub struct CrawlerService<S: CrawlerStorage> {
     store: S,
     registry: ServiceRegistry,
+    config: PipelineConfig,
+    // Identifies this replica in the crawler leases
+    lease_holder: String,
+    status: CrawlerStatusTracker,
+}
+
+/// Crawler task of a chain this replica holds the lease on
+struct RunningCrawler {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+    // Configuration the crawler was started with
+    chain_data: Option<ChainData>,
+}
+
+impl RunningCrawler {
+    /// Lets the crawler finish the batch it is processing, aborting it if that takes too long
+    async fn stop(mut self, timeout: Duration) {
+        self.stop.store(true, Ordering::Relaxed);
+        if tokio::time::timeout(timeout, &mut self.handle)
+            .await
+            .is_err()
+        {
+            self.handle.abort();
+        }
+    }
+}
+
+/// Crawlers of the chains this replica holds the lease on. Crawlers are stopped in the
+/// background so the leases of the other chains keep being renewed meanwhile
+struct Crawlers {
+    running: HashMap<String, RunningCrawler>,
+    stopping: HashMap<String, JoinHandle<()>>,
+    stop_timeout: Duration,
+}
+
+impl Default for Crawlers {
+    fn default() -> Self {
+        Self {
+            running: HashMap::new(),
+            stopping: HashMap::new(),
+            stop_timeout: GRACEFUL_STOP_TIMEOUT,
+        }
+    }
+}
+
+impl Crawlers {
+    fn is_running(&self, chain_id: &str) -> bool {
+        self.running.contains_key(chain_id)
+    }
+
+    /// Whether a stopped crawler of the chain is still finishing its batch
+    fn is_stopping(&mut self, chain_id: &str) -> bool {
+        self.stopping.retain(|_, handle| !handle.is_finished());
+        self.stopping.contains_key(chain_id)
+    }
+
+    fn start(&mut self, chain_id: &str, crawler: RunningCrawler) {
+        self.running.insert(chain_id.to_string(), crawler);
+    }
+
+    /// Stops the crawler of the chain without waiting for it, `then` runs once it has stopped.
+    /// Returns whether the chain had a running crawler
+    fn stop(&mut self, chain_id: &str, then: impl Future<Output = ()> + Send + 'static) -> bool {
+        let Some(crawler) = self.running.remove(chain_id) else {
+            return false;
+        };
+        let timeout = self.stop_timeout;
+        let handle = tokio::spawn(async move {
+            crawler.stop(timeout).await;
+            then.await;
+        });
+        self.stopping.insert(chain_id.to_string(), handle);
+
+        true
+    }
+
+    /// Aborts the crawler of the chain right away, returns whether the chain had one
+    fn abort(&mut self, chain_id: &str) -> bool {
+        if let Some(handle) = self.stopping.remove(chain_id) {
+            handle.abort();
+        }
+        match self.running.remove(chain_id) {
+            Some(crawler) => {
+                crawler.handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Aborts every crawler, returning the chains that had a running one
+    fn abort_all(&mut self) -> Vec<String> {
+        for (_, handle) in self.stopping.drain() {
+            handle.abort();
+        }
+        self.running
+            .drain()
+            .map(|(chain_id, crawler)| {
+                crawler.handle.abort();
+                chain_id
+            })
+            .collect()
+    }
 }
 
 #[tonic::async_trait]
 pub trait CrawlChain: Send + Sync {
-    async fn try_crawl_chain(&self, chain: &Chain) -> Result<()>;
+    /// Crawls until `stop` is set, the batch in progress is finished first
+    async fn try_crawl_chain(&self, chain: &Chain, stop: &AtomicBool) -> Result<()>;
+    /// Height of the newest block the crawler is allowed to process
+    async fn head_height(&self) -> Result<u64>;
+    /// Processes `[from_height, to_height]` without touching the crawler state and returns
+    /// the blocks that failed
+    async fn backfill_chain(
+        &self,
+        chain: &Chain,
+        from_height: u64,
+        to_height: u64,
+    ) -> Vec<(u64, String)>;
 }
 
 impl<S: CrawlerStorage> CrawlerService<S> {
-    pub fn new(store: S, registry: ServiceRegistry) -> Self {
-        Self { store, registry }
+    pub fn new(store: S, registry: ServiceRegistry, config: PipelineConfig) -> Self {
+        Self {
+            store,
+            registry,
+            config,
+            lease_holder: format!("{:016x}", rand::random::<u64>()),
+            status: CrawlerStatusTracker::default(),
+        }
     }
 
     pub fn spawn_daemons(&self) {
@@ -40,42 +194,118 @@ impl<S: CrawlerStorage> CrawlerService<S> {
     }
 
     pub async fn crawl(&self) {
-        let mut chain_crawler: HashMap<String, JoinHandle<()>> = HashMap::new();
+        let mut crawlers = Crawlers::default();
         loop {
-            if let Err(err) = self.try_crawl(&mut chain_crawler).await {
+            if let Err(err) = self.try_crawl(&mut crawlers).await {
                 warn!("crawler failed - {} - restarting in 10 seconds...", err);
+                // The leases cannot be renewed either, another replica may take over the chains
+                for chain_id in crawlers.abort_all() {
+                    self.status.remove(&chain_id);
+                }
                 tokio::time::sleep(Duration::from_secs(10)).await;
             }
         }
     }
 
-    async fn try_crawl(&self, chain_crawler: &mut HashMap<String, JoinHandle<()>>) -> Result<()> {
+    async fn try_crawl(&self, crawlers: &mut Crawlers) -> Result<()> {
         let registry = self.registry.get_services().await?;
         let chain_service = registry.chain_service;
 
         loop {
             let chains = chain_service.get_chains().await?.chains;
+            let mut controls = self.store.get_crawler_controls().await?;
             for chain in chains {
-                if chain.status == Status::Enabled as i32 {
-                    if !chain_crawler.contains_key(&chain.id) {
-                        let crawler = self.clone();
-                        let chain_id = chain.id.clone();
-                        let chain = chain.clone();
-                        let handle = tokio::spawn(async move { crawler.crawl_chain(chain).await });
-                        chain_crawler.insert(chain_id, handle);
+                let controls = controls.remove(&chain.id).unwrap_or_default();
+                if chain.status == Status::Enabled as i32 && !controls.paused {
+                    // Also renews the lease of chains this replica is already crawling
+                    let leased = self
+                        .store
+                        .acquire_lease(&chain.id, &self.lease_holder, LEASE_TTL)
+                        .await?;
+                    if !leased {
+                        if crawlers.abort(&chain.id) {
+                            warn!(
+                                "lost crawler lease on chain {} to another replica",
+                                chain.id
+                            );
+                            self.status.remove(&chain.id);
+                        }
+                        continue;
+                    }
+
+                    let reconfigured = crawlers
+                        .running
+                        .get(&chain.id)
+                        .map(|c| c.chain_data != chain.chain_data)
+                        .unwrap_or_default();
+                    if reconfigured {
+                        info!(
+                            "configuration of chain {} changed, restarting its crawler",
+                            chain.id
+                        );
+                        crawlers.stop(&chain.id, async {});
+                    }
+
+                    if let Some(cursor) = controls.requested_cursor {
+                        // The crawler has to be stopped so it does not overwrite the new cursor,
+                        // the cursor is moved once it is
+                        crawlers.stop(&chain.id, async {});
+                        if !crawlers.is_stopping(&chain.id) {
+                            self.apply_cursor(&chain, cursor).await?;
+                        }
+                    }
+
+                    // A restarted crawler waits for the previous one to finish its batch
+                    if !crawlers.is_running(&chain.id) && !crawlers.is_stopping(&chain.id) {
+                        crawlers.start(&chain.id, self.spawn_crawler(chain.clone()));
+                    }
+
+                    self.store
+                        .set_crawler_status(
+                            &chain.id,
+                            &self.lease_holder,
+                            self.status.status(&chain.id),
+                        )
+                        .await?;
+                } else {
+                    let store = self.store.clone();
+                    let holder = self.lease_holder.clone();
+                    let chain_id = chain.id.clone();
+                    let release = async move {
+                        if let Err(err) = store.release_lease(&chain_id, &holder).await {
+                            warn!(
+                                "failed to release crawler lease on chain {} - {}",
+                                chain_id, err
+                            );
+                        }
+                    };
+                    if crawlers.stop(&chain.id, release) {
+                        self.status.remove(&chain.id);
                     }
-                } else if let Some(handle) = chain_crawler.get(&chain.id) {
-                    handle.abort();
-                    chain_crawler.remove(&chain.id);
                 }
             }
-            tokio::time::sleep(Duration::from_secs(30)).await
+            tokio::time::sleep(LEASE_RENEW_INTERVAL).await
         }
     }
 
-    pub async fn crawl_chain(&self, chain: Chain) {
-        loop {
-            if let Err(err) = self.try_crawl_chain(&chain).await {
+    fn spawn_crawler(&self, chain: Chain) -> RunningCrawler {
+        let svc = self.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let chain_data = chain.chain_data.clone();
+        let crawler_stop = stop.clone();
+        let handle = tokio::spawn(async move { svc.crawl_chain(chain, &crawler_stop).await });
+
+        RunningCrawler {
+            handle,
+            stop,
+            chain_data,
+        }
+    }
+
+    pub async fn crawl_chain(&self, chain: Chain, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            if let Err(err) = self.try_crawl_chain(&chain, stop).await {
+                self.status.report_error(&chain.id, err.to_string());
                 warn!(
                     "crawler for chain {:?} failed - {} - restarting in 10 seconds...",
                     chain, err
@@ -85,9 +315,64 @@ impl<S: CrawlerStorage> CrawlerService<S> {
         }
     }
 
-    async fn try_crawl_chain(&self, chain: &Chain) -> Result<()> {
+    async fn apply_cursor(&self, chain: &Chain, cursor: CrawlerCursor) -> Result<()> {
+        match cursor.cursor.ok_or("could not find cursor")? {
+            // Crawlers without state start from the head
+            Cursor::Head(_) => {
+                info!("moving cursor of chain {} to head", chain.id);
+                self.store.reset_crawler_data(&chain.id).await?;
+            }
+            Cursor::Height(height) => {
+                info!("moving cursor of chain {} to block {}", chain.id, height);
+                let chain_data = chain
+                    .chain_data
+                    .as_ref()
+                    .ok_or("could not find chain data")?
+                    .chain_data
+                    .as_ref()
+                    .ok_or("could not find chain data")?;
+                let prev = self.store.get_crawler_data(&chain.id).await.ok();
+                self.store
+                    .set_crawler_data(
+                        &chain.id,
+                        &self.lease_holder,
+                        blocks::crawler_data_at(chain_data, prev, height),
+                    )
+                    .await?;
+            }
+        }
+
+        self.store.set_requested_cursor(&chain.id, None).await
+    }
+
+    async fn get_chain(&self, chain_id: &str) -> Result<Chain> {
+        Ok(self
+            .registry
+            .get_services()
+            .await?
+            .chain_service
+            .get_chains()
+            .await?
+            .chains
+            .into_iter()
+            .find(|c| c.id == chain_id)
+            .ok_or("could not find chain")?)
+    }
+
+    async fn try_crawl_chain(&self, chain: &Chain, stop: &AtomicBool) -> Result<()> {
+        let crawler = self.new_crawler(chain, None).await?;
+        crawler.try_crawl_chain(chain, stop).await?;
+
+        Ok(())
+    }
+
+    async fn new_crawler(
+        &self,
+        chain: &Chain,
+        alert: Option<BackendUserAlert>,
+    ) -> Result<Box<dyn CrawlChain>> {
         let registry = self.registry.clone();
-        let store = self.store.clone();
+        let store = LeasedStorage::new(self.store.clone(), &self.lease_holder);
         let crawler = match chain
             .chain_data
             .as_ref()
@@ -98,18 +383,236 @@ impl<S: CrawlerStorage> CrawlerService<S> {
             .clone()
         {
             mempools_api::api::chain_data::ChainData::CosmosChainData(c) => {
-                Box::new(CosmosCrawler::new(c, registry, store).await?) as Box<dyn CrawlChain>
+                let mut crawler = CosmosCrawler::new(c, registry, store, &self.config).await?;
+                crawler.alert = alert;
+                crawler.status = self.status.clone();
+                Box::new(crawler) as Box<dyn CrawlChain>
             }
             mempools_api::api::chain_data::ChainData::CosmosEvmChainData(c) => {
-                Box::new(CosmosEvmCrawler::new(c, registry, store).await?) as Box<dyn CrawlChain>
+                let mut crawler = CosmosEvmCrawler::new(c, registry, store, &self.config).await?;
+                crawler.cosmos_crawler.alert = alert.clone();
+                crawler.cosmos_crawler.status = self.status.clone();
+                crawler.eth_crawler.alert = alert;
+                crawler.eth_crawler.status = self.status.clone();
+                Box::new(crawler) as Box<dyn CrawlChain>
             }
             mempools_api::api::chain_data::ChainData::EthChainData(c) => {
-                Box::new(EthCrawler::new(c, registry, store).await?) as Box<dyn CrawlChain>
+                let mut crawler = EthCrawler::new(c, registry, store, &self.config).await?;
+                crawler.alert = alert;
+                crawler.status = self.status.clone();
+                Box::new(crawler) as Box<dyn CrawlChain>
             }
         };
 
-        crawler.try_crawl_chain(chain).await?;
+        Ok(crawler)
+    }
+}
 
-        Ok(())
+#[tonic::async_trait]
+impl<S: CrawlerStorage> CrawlerServiceInterface for CrawlerService<S> {
+    async fn backfill_chain(
+        &self,
+        chain_id: String,
+        from_height: u64,
+        to_height: u64,
+        alert_id: Option<String>,
+    ) -> Result<Vec<FailedBlock>> {
+        let chain = self.get_chain(&chain_id).await?;
+
+        let alert = match alert_id {
+            Some(alert_id) => {
+                let alert = self
+                    .registry
+                    .get_services()
+                    .await?
+                    .alert_service
+                    .get_alert_by_id(alert_id)
+                    .await?;
+                if alert
+                    .user_alert
+                    .as_ref()
+                    .ok_or("Missing user alert")?
+                    .chain_id
+                    != chain.id
+                {
+                    return Err("alert does not belong to chain".into());
+                }
+                Some(alert)
+            }
+            None => None,
+        };
+
+        if to_height - from_height >= MAX_BACKFILL_BLOCKS {
+            return Err(format!(
+                "cannot backfill more than {} blocks at once",
+                MAX_BACKFILL_BLOCKS
+            )
+            .into());
+        }
+
+        let crawler = self.new_crawler(&chain, alert).await?;
+        if to_height > crawler.head_height().await? {
+            return Err("cannot backfill blocks past the chain head".into());
+        }
+
+        info!(
+            "backfilling blocks {} to {} in chain {}",
+            from_height, to_height, chain.id
+        );
+        let failures = crawler.backfill_chain(&chain, from_height, to_height).await;
+        info!(
+            "backfill of blocks {} to {} in chain {} finished with {} failed blocks",
+            from_height,
+            to_height,
+            chain.id,
+            failures.len()
+        );
+
+        Ok(failures
+            .into_iter()
+            .map(|(height, error)| FailedBlock {
+                height,
+                attempts: 1,
+                error,
+                ..Default::default()
+            })
+            .collect())
+    }
+    async fn get_crawler_statuses(&self) -> Result<Vec<CrawlerStatus>> {
+        let chains = self
+            .registry
+            .get_services()
+            .await?
+            .chain_service
+            .get_chains()
+            .await?
+            .chains;
+        let mut statuses: HashMap<String, CrawlerStatus> = self
+            .store
+            .get_crawler_statuses()
+            .await?
+            .into_iter()
+            .map(|s| (s.chain_id.clone(), s))
+            .collect();
+        let controls = self.store.get_crawler_controls().await?;
+
+        // Chains that were never leased have not been crawled yet
+        Ok(chains
+            .into_iter()
+            .map(|chain| {
+                let mut status = statuses.remove(&chain.id).unwrap_or(CrawlerStatus {
+                    chain_id: chain.id,
+                    ..Default::default()
+                });
+                status.paused = controls
+                    .get(&status.chain_id)
+                    .map(|c| c.paused)
+                    .unwrap_or_default();
+                status
+            })
+            .collect())
+    }
+
+    async fn set_crawler_paused(&self, chain_id: String, paused: bool) -> Result<()> {
+        self.get_chain(&chain_id).await?;
+        self.store.set_crawler_paused(&chain_id, paused).await
+    }
+
+    async fn set_crawler_cursor(&self, chain_id: String, cursor: CrawlerCursor) -> Result<()> {
+        self.get_chain(&chain_id).await?;
+        self.store
+            .set_requested_cursor(&chain_id, Some(cursor))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Crawler that keeps going until it is stopped, `batch` is how long its last batch takes
+    fn crawler(batch: Duration, finished: Arc<AtomicBool>) -> RunningCrawler {
+        let stop = Arc::new(AtomicBool::new(false));
+        let crawler_stop = stop.clone();
+        let handle = tokio::spawn(async move {
+            while !crawler_stop.load(Ordering::Relaxed) {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            tokio::time::sleep(batch).await;
+            finished.store(true, Ordering::Relaxed);
+        });
+
+        RunningCrawler {
+            handle,
+            stop,
+            chain_data: None,
+        }
+    }
+
+    async fn wait_stopped(crawlers: &mut Crawlers, chain_id: &str) {
+        while crawlers.is_stopping(chain_id) {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_does_not_wait_for_the_batch() {
+        let mut crawlers = Crawlers::default();
+        crawlers.start("1", crawler(Duration::from_secs(60), Arc::default()));
+
+        let started = std::time::Instant::now();
+        assert!(crawlers.stop("1", async {}));
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!crawlers.is_running("1"));
+        assert!(crawlers.is_stopping("1"));
+    }
+
+    #[tokio::test]
+    async fn stopped_crawler_finishes_its_batch() {
+        let mut crawlers = Crawlers::default();
+        let finished = Arc::new(AtomicBool::new(false));
+        let released = Arc::new(AtomicBool::new(false));
+        crawlers.start("1", crawler(Duration::from_millis(20), finished.clone()));
+
+        let then = released.clone();
+        crawlers.stop("1", async move { then.store(true, Ordering::Relaxed) });
+        wait_stopped(&mut crawlers, "1").await;
+
+        assert!(finished.load(Ordering::Relaxed));
+        assert!(released.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn stopped_crawler_is_aborted_after_timeout() {
+        let mut crawlers = Crawlers {
+            stop_timeout: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let finished = Arc::new(AtomicBool::new(false));
+        let released = Arc::new(AtomicBool::new(false));
+        crawlers.start("1", crawler(Duration::from_secs(60), finished.clone()));
+
+        let then = released.clone();
+        crawlers.stop("1", async move { then.store(true, Ordering::Relaxed) });
+        wait_stopped(&mut crawlers, "1").await;
+
+        assert!(!finished.load(Ordering::Relaxed));
+        assert!(released.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn stopping_only_affects_its_chain() {
+        let mut crawlers = Crawlers::default();
+        crawlers.start("1", crawler(Duration::from_secs(60), Arc::default()));
+        crawlers.start("2", crawler(Duration::from_secs(60), Arc::default()));
+
+        assert!(crawlers.stop("1", async {}));
+        assert!(!crawlers.stop("3", async {}));
+
+        assert!(crawlers.is_running("2"));
+        assert!(!crawlers.is_stopping("2"));
+        assert_eq!(crawlers.abort_all(), vec!["2".to_string()]);
+        assert!(!crawlers.is_stopping("1"));
     }
 }
@@ -0,0 +1,51 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+// How often mempools without a push subscription are polled for pending txs
+pub const MEMPOOL_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Pending txs requested per unconfirmed_txs page, the most CometBFT returns at once
+pub const MEMPOOL_PAGE_SIZE: u64 = 100;
+// Pending txs read per unconfirmed_txs poll, however many pages that takes
+pub const MAX_MEMPOOL_TXS: u64 = 10_000;
+// Pending tx hashes remembered so a tx sitting in the mempool is only processed once
+const SEEN_TXS_CAPACITY: usize = 50_000;
+
+/// Bounded set of recently seen pending tx hashes, the oldest hashes are evicted first
+#[derive(Default)]
+pub struct SeenTxs {
+    hashes: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenTxs {
+    /// Returns whether the hash was not seen before
+    pub fn insert(&mut self, tx_hash: &str) -> bool {
+        if self.hashes.contains(tx_hash) {
+            return false;
+        }
+
+        if self.order.len() >= SEEN_TXS_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        self.hashes.insert(tx_hash.to_string());
+        self.order.push_back(tx_hash.to_string());
+
+        true
+    }
+}
+
+/// `eth_subscribe` request for the hashes of new pending txs
+pub fn eth_pending_txs_request() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["newPendingTransactions"],
+    })
+}
@@ -0,0 +1,166 @@
+use cosmrs::{
+    bip32::secp256k1::sha2::{Digest, Sha256},
+    proto::{
+        cosmos::{
+            base::abci::v1beta1::StringEvent,
+            crypto::ed25519,
+            slashing::v1beta1::MsgUnjail,
+            staking::v1beta1::{MsgBeginRedelegate, MsgDelegate, MsgEditValidator, MsgUndelegate},
+        },
+        traits::Message,
+    },
+    Any,
+};
+use mempools_api::api::{
+    alert_cosmos_staking::Action, alert_cosmos_validator::Event as ValidatorEvent,
+};
+use util::{legacy_dec, service_registry::ValidatorUpdate, Result};
+
+// Moniker of a MsgEditValidator that leaves the description as it is
+const DO_NOT_MODIFY: &str = "[do-not-modify]";
+const ED25519_PUB_KEY: &str = "/cosmos.crypto.ed25519.PubKey";
+
+/// Delegation moved by a staking msg
+pub struct Delegation {
+    pub action: Action,
+    pub delegator: String,
+    pub validator: String,
+    pub validator_dst: Option<String>,
+    pub amount: Option<cosmrs::proto::cosmos::base::v1beta1::Coin>,
+}
+
+/// Delegation moved by `msg`, None when it is not a staking msg
+pub fn decode_delegation(msg: &Any) -> Result<Option<Delegation>> {
+    let delegation = match msg.type_url.as_str() {
+        "/cosmos.staking.v1beta1.MsgDelegate" => {
+            let msg = MsgDelegate::decode(msg.value.as_slice())?;
+            Delegation {
+                action: Action::Delegate,
+                delegator: msg.delegator_address,
+                validator: msg.validator_address,
+                validator_dst: None,
+                amount: msg.amount,
+            }
+        }
+        "/cosmos.staking.v1beta1.MsgUndelegate" => {
+            let msg = MsgUndelegate::decode(msg.value.as_slice())?;
+            Delegation {
+                action: Action::Undelegate,
+                delegator: msg.delegator_address,
+                validator: msg.validator_address,
+                validator_dst: None,
+                amount: msg.amount,
+            }
+        }
+        "/cosmos.staking.v1beta1.MsgBeginRedelegate" => {
+            let msg = MsgBeginRedelegate::decode(msg.value.as_slice())?;
+            Delegation {
+                action: Action::Redelegate,
+                delegator: msg.delegator_address,
+                validator: msg.validator_src_address,
+                validator_dst: Some(msg.validator_dst_address),
+                amount: msg.amount,
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(delegation))
+}
+
+/// Changes an operator made to its validator with `msg`
+pub fn msg_validator_updates(msg: &Any) -> Result<Vec<(ValidatorEvent, ValidatorUpdate)>> {
+    let mut updates = vec![];
+    match msg.type_url.as_str() {
+        "/cosmos.slashing.v1beta1.MsgUnjail" => {
+            let msg = MsgUnjail::decode(msg.value.as_slice())?;
+            updates.push((
+                ValidatorEvent::Unjailed,
+                ValidatorUpdate {
+                    validator: msg.validator_addr,
+                    ..Default::default()
+                },
+            ));
+        }
+        "/cosmos.staking.v1beta1.MsgEditValidator" => {
+            let msg = MsgEditValidator::decode(msg.value.as_slice())?;
+            let moniker = msg.description.map(|d| d.moniker).unwrap_or_default();
+            if !msg.commission_rate.is_empty() {
+                updates.push((
+                    ValidatorEvent::CommissionChanged,
+                    ValidatorUpdate {
+                        validator: msg.validator_address.clone(),
+                        commission_rate: legacy_dec(&msg.commission_rate),
+                        ..Default::default()
+                    },
+                ));
+            }
+            if !moniker.is_empty() && moniker != DO_NOT_MODIFY {
+                updates.push((
+                    ValidatorEvent::MonikerChanged,
+                    ValidatorUpdate {
+                        validator: msg.validator_address,
+                        moniker,
+                        ..Default::default()
+                    },
+                ));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(updates)
+}
+
+/// Changes the chain made to validators in a block, along with the consensus address of each
+/// validator, which still needs to be mapped to its operator
+pub fn block_validator_updates(
+    event: &StringEvent,
+) -> Result<Vec<(ValidatorEvent, String, ValidatorUpdate)>> {
+    let attr = |key: &str| {
+        event
+            .attributes
+            .iter()
+            .find(|a| a.key == key)
+            .map(|a| a.value.clone())
+    };
+    let address = || attr("address").ok_or("could not find validator consensus address");
+
+    let mut updates = vec![];
+    match event.r#type.as_str() {
+        "slash" => {
+            let update = ValidatorUpdate {
+                slash_reason: attr("reason").unwrap_or_default(),
+                power: attr("power").unwrap_or_default(),
+                burned_coins: attr("burned_coins").unwrap_or_default(),
+                ..Default::default()
+            };
+            if attr("jailed").map(|j| !j.is_empty()).unwrap_or_default() {
+                updates.push((ValidatorEvent::Jailed, address()?, update.clone()));
+            }
+            updates.push((ValidatorEvent::Slashed, address()?, update));
+        }
+        // Emitted for every block the validator misses, with its signing info's counter
+        "liveness" => updates.push((
+            ValidatorEvent::MissedBlocks,
+            address()?,
+            ValidatorUpdate {
+                missed_blocks: attr("missed_blocks").unwrap_or_default().parse()?,
+                ..Default::default()
+            },
+        )),
+        _ => {}
+    }
+
+    Ok(updates)
+}
+
+/// Raw consensus address of a validator's consensus key
+pub fn consensus_address(pub_key: &Any) -> Result<Vec<u8>> {
+    if pub_key.type_url != ED25519_PUB_KEY {
+        return Err(format!("unsupported consensus key type {}", pub_key.type_url).into());
+    }
+    let key = ed25519::PubKey::decode(pub_key.value.as_slice())?.key;
+
+    Ok(Sha256::digest(key)[..20].to_vec())
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use mempools_api::api::CrawlerStatus;
+
+// Weight of the newest sample in the blocks per second moving average
+const EWMA_WEIGHT: f64 = 0.3;
+
+/// Progress and failures of the crawlers run by this replica
+#[derive(Clone, Default)]
+pub struct CrawlerStatusTracker {
+    chains: Arc<Mutex<HashMap<String, ChainStatus>>>,
+}
+
+#[derive(Default)]
+struct ChainStatus {
+    // Cosmos evm chains run a crawler per side
+    crawlers: HashMap<&'static str, CrawlerProgress>,
+    last_error: String,
+    restart_count: u64,
+}
+
+struct CrawlerProgress {
+    head_height: u64,
+    processed_height: u64,
+    blocks_per_second: f64,
+    reported_at: Instant,
+}
+
+impl CrawlerStatusTracker {
+    pub fn report_progress(
+        &self,
+        chain_id: &str,
+        crawler: &'static str,
+        head_height: u64,
+        processed_height: u64,
+    ) {
+        let mut chains = self.chains.lock().unwrap();
+        let crawlers = &mut chains.entry(chain_id.to_string()).or_default().crawlers;
+
+        let now = Instant::now();
+        let blocks_per_second = match crawlers.get(crawler) {
+            Some(prev) => {
+                let elapsed = now.duration_since(prev.reported_at).as_secs_f64();
+                if elapsed > 0.0 {
+                    let rate =
+                        processed_height.saturating_sub(prev.processed_height) as f64 / elapsed;
+                    prev.blocks_per_second * (1.0 - EWMA_WEIGHT) + rate * EWMA_WEIGHT
+                } else {
+                    prev.blocks_per_second
+                }
+            }
+            None => 0.0,
+        };
+
+        crawlers.insert(
+            crawler,
+            CrawlerProgress {
+                head_height,
+                processed_height,
+                blocks_per_second,
+                reported_at: now,
+            },
+        );
+    }
+
+    pub fn report_error(&self, chain_id: &str, err: String) {
+        let mut chains = self.chains.lock().unwrap();
+        let status = chains.entry(chain_id.to_string()).or_default();
+        status.last_error = err;
+        status.restart_count += 1;
+    }
+
+    pub fn remove(&self, chain_id: &str) {
+        self.chains.lock().unwrap().remove(chain_id);
+    }
+
+    /// Status of the chain, reporting the progress of its slowest crawler
+    pub fn status(&self, chain_id: &str) -> CrawlerStatus {
+        let chains = self.chains.lock().unwrap();
+        let mut status = CrawlerStatus {
+            chain_id: chain_id.to_string(),
+            running: true,
+            updated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if let Some(chain) = chains.get(chain_id) {
+            status.last_error = chain.last_error.clone();
+            status.restart_count = chain.restart_count;
+
+            let slowest = chain
+                .crawlers
+                .values()
+                .max_by_key(|c| c.head_height.saturating_sub(c.processed_height));
+            if let Some(crawler) = slowest {
+                status.head_height = crawler.head_height;
+                status.processed_height = crawler.processed_height;
+                status.lag = crawler.head_height.saturating_sub(crawler.processed_height);
+                status.blocks_per_second = crawler.blocks_per_second;
+            }
+        }
+
+        status
+    }
+}
@@ -1,16 +1,79 @@
+use std::{collections::HashMap, time::Duration};
+
 use cosmrs::proto::traits::Message;
-use mempools_api::api::crawler_data::CrawlerData;
+use mempools_api::api::{crawler_data::CrawlerData, CrawlerCursor, CrawlerStatus};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
-    Set,
+    sea_query::{Expr, OnConflict, Query},
+    ColumnTrait, ConnectionTrait, DatabaseBackend, DatabaseConnection, EntityTrait,
+    FromQueryResult, QueryFilter, Set, Statement,
 };
 
 use util::Result;
 
+/// Admin overrides for the crawler of a chain
+#[derive(Default)]
+pub struct CrawlerControls {
+    pub paused: bool,
+    // Applied by the replica holding the lease before it (re)starts the crawler
+    pub requested_cursor: Option<CrawlerCursor>,
+}
+
 #[tonic::async_trait]
 pub trait CrawlerStorage: Clone + Send + Sync + 'static {
     async fn get_crawler_data(&self, chain_id: &str) -> Result<CrawlerData>;
-    async fn set_crawler_data(&self, chain_id: &str, data: CrawlerData) -> Result<()>;
+    /// Only stores the data while `holder` has an unexpired lease on the chain, so a replica
+    /// that lost its lease cannot move the cursor of the one that took over
+    async fn set_crawler_data(&self, chain_id: &str, holder: &str, data: CrawlerData)
+        -> Result<()>;
+    /// Drops the crawler state so the crawler starts over from the head
+    async fn reset_crawler_data(&self, chain_id: &str) -> Result<()>;
+    async fn get_crawler_controls(&self) -> Result<HashMap<String, CrawlerControls>>;
+    async fn set_crawler_paused(&self, chain_id: &str, paused: bool) -> Result<()>;
+    async fn set_requested_cursor(
+        &self,
+        chain_id: &str,
+        cursor: Option<CrawlerCursor>,
+    ) -> Result<()>;
+    /// Takes or renews the lease on crawling the chain for `ttl`. Returns false while another
+    /// holder has an unexpired lease
+    async fn acquire_lease(&self, chain_id: &str, holder: &str, ttl: Duration) -> Result<bool>;
+    async fn release_lease(&self, chain_id: &str, holder: &str) -> Result<()>;
+    /// Stores the status of the chain's crawler next to the lease, only while `holder` has it
+    async fn set_crawler_status(
+        &self,
+        chain_id: &str,
+        holder: &str,
+        status: CrawlerStatus,
+    ) -> Result<()>;
+    /// Statuses of every chain that has been leased to a crawler
+    async fn get_crawler_statuses(&self) -> Result<Vec<CrawlerStatus>>;
+}
+
+/// Crawler state of the chains this replica holds the lease on, writes fail once the lease is
+/// lost
+#[derive(Clone)]
+pub struct LeasedStorage<S: CrawlerStorage> {
+    store: S,
+    holder: String,
+}
+
+impl<S: CrawlerStorage> LeasedStorage<S> {
+    pub fn new(store: S, holder: &str) -> Self {
+        Self {
+            store,
+            holder: holder.to_string(),
+        }
+    }
+
+    pub async fn get_crawler_data(&self, chain_id: &str) -> Result<CrawlerData> {
+        self.store.get_crawler_data(chain_id).await
+    }
+
+    pub async fn set_crawler_data(&self, chain_id: &str, data: CrawlerData) -> Result<()> {
+        self.store
+            .set_crawler_data(chain_id, &self.holder, data)
+            .await
+    }
 }
 
 #[tonic::async_trait]
@@ -27,32 +90,238 @@ impl CrawlerStorage for DatabaseConnection {
         Ok(data.crawler_data.ok_or("could not find crawler data")?)
     }
 
-    async fn set_crawler_data(&self, chain_id: &str, data: CrawlerData) -> Result<()> {
-        let row = db_entities::crawler::Entity::find()
-            .filter(db_entities::crawler::Column::ChainId.eq(chain_id.parse::<i32>()?))
-            .one(self)
-            .await?;
+    async fn set_crawler_data(
+        &self,
+        chain_id: &str,
+        holder: &str,
+        data: CrawlerData,
+    ) -> Result<()> {
+        insert_crawler_row(self, chain_id).await?;
 
+        let chain_id = chain_id.parse::<i32>()?;
         let data = hex::encode(
             mempools_api::api::CrawlerData {
                 crawler_data: Some(data),
             }
             .encode_to_vec(),
         );
+        let updated = db_entities::crawler::Entity::update_many()
+            .col_expr(db_entities::crawler::Column::Data, Expr::value(data))
+            .filter(db_entities::crawler::Column::ChainId.eq(chain_id))
+            .filter(
+                db_entities::crawler::Column::ChainId.in_subquery(
+                    Query::select()
+                        .column(db_entities::crawler_lease::Column::ChainId)
+                        .from(db_entities::crawler_lease::Entity)
+                        .and_where(db_entities::crawler_lease::Column::ChainId.eq(chain_id))
+                        .and_where(db_entities::crawler_lease::Column::Holder.eq(holder))
+                        .and_where(
+                            Expr::col(db_entities::crawler_lease::Column::ExpiresAt)
+                                .gte(Expr::cust(&db_now(self.get_database_backend()))),
+                        )
+                        .to_owned(),
+                ),
+            )
+            .exec(self)
+            .await?
+            .rows_affected;
+        if updated == 0 {
+            return Err("crawler lease on chain was lost".into());
+        }
 
-        if let Some(row) = row {
-            let mut row = row.into_active_model();
-            row.data = Set(data);
-            row.update(self).await?;
-        } else {
-            let row = db_entities::crawler::ActiveModel {
-                chain_id: Set(chain_id.parse::<i32>()?),
-                data: Set(data),
-                ..Default::default()
+        Ok(())
+    }
+
+    async fn reset_crawler_data(&self, chain_id: &str) -> Result<()> {
+        db_entities::crawler::Entity::update_many()
+            .col_expr(db_entities::crawler::Column::Data, Expr::value(""))
+            .filter(db_entities::crawler::Column::ChainId.eq(chain_id.parse::<i32>()?))
+            .exec(self)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_crawler_controls(&self) -> Result<HashMap<String, CrawlerControls>> {
+        let mut controls = HashMap::new();
+        for row in db_entities::crawler::Entity::find().all(self).await? {
+            let requested_cursor = if row.requested_cursor.is_empty() {
+                None
+            } else {
+                Some(CrawlerCursor::decode(
+                    hex::decode(row.requested_cursor)?.as_slice(),
+                )?)
             };
-            row.insert(self).await?;
+
+            controls.insert(
+                row.chain_id.to_string(),
+                CrawlerControls {
+                    paused: row.paused,
+                    requested_cursor,
+                },
+            );
         }
 
+        Ok(controls)
+    }
+
+    async fn set_crawler_paused(&self, chain_id: &str, paused: bool) -> Result<()> {
+        insert_crawler_row(self, chain_id).await?;
+        db_entities::crawler::Entity::update_many()
+            .col_expr(db_entities::crawler::Column::Paused, Expr::value(paused))
+            .filter(db_entities::crawler::Column::ChainId.eq(chain_id.parse::<i32>()?))
+            .exec(self)
+            .await?;
+
         Ok(())
     }
+
+    async fn set_requested_cursor(
+        &self,
+        chain_id: &str,
+        cursor: Option<CrawlerCursor>,
+    ) -> Result<()> {
+        insert_crawler_row(self, chain_id).await?;
+        let cursor = cursor
+            .map(|c| hex::encode(c.encode_to_vec()))
+            .unwrap_or_default();
+        db_entities::crawler::Entity::update_many()
+            .col_expr(
+                db_entities::crawler::Column::RequestedCursor,
+                Expr::value(cursor),
+            )
+            .filter(db_entities::crawler::Column::ChainId.eq(chain_id.parse::<i32>()?))
+            .exec(self)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn acquire_lease(&self, chain_id: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let chain_id = chain_id.parse::<i32>()?;
+
+        // Starts out expired so it is taken by the update below
+        db_entities::crawler_lease::Entity::insert(db_entities::crawler_lease::ActiveModel {
+            chain_id: Set(chain_id),
+            holder: Set("".to_string()),
+            expires_at: Set(0),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(db_entities::crawler_lease::Column::ChainId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(self)
+        .await?;
+
+        // Conditional update so two replicas racing for an expired lease cannot both win. Expiry
+        // is measured on the database clock, replica clocks may drift apart
+        let now = db_now(self.get_database_backend());
+        let renewed = db_entities::crawler_lease::Entity::update_many()
+            .col_expr(
+                db_entities::crawler_lease::Column::Holder,
+                Expr::value(holder.to_string()),
+            )
+            .col_expr(
+                db_entities::crawler_lease::Column::ExpiresAt,
+                Expr::cust(&format!("{} + {}", now, ttl.as_millis())),
+            )
+            .filter(db_entities::crawler_lease::Column::ChainId.eq(chain_id))
+            .filter(
+                Expr::col(db_entities::crawler_lease::Column::Holder)
+                    .eq(holder)
+                    .or(Expr::col(db_entities::crawler_lease::Column::ExpiresAt)
+                        .lt(Expr::cust(&now))),
+            )
+            .exec(self)
+            .await?
+            .rows_affected;
+
+        Ok(renewed > 0)
+    }
+
+    async fn release_lease(&self, chain_id: &str, holder: &str) -> Result<()> {
+        db_entities::crawler_lease::Entity::delete_many()
+            .filter(db_entities::crawler_lease::Column::ChainId.eq(chain_id.parse::<i32>()?))
+            .filter(db_entities::crawler_lease::Column::Holder.eq(holder))
+            .exec(self)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_crawler_status(
+        &self,
+        chain_id: &str,
+        holder: &str,
+        status: CrawlerStatus,
+    ) -> Result<()> {
+        db_entities::crawler_lease::Entity::update_many()
+            .col_expr(
+                db_entities::crawler_lease::Column::Status,
+                Expr::value(hex::encode(status.encode_to_vec())),
+            )
+            .filter(db_entities::crawler_lease::Column::ChainId.eq(chain_id.parse::<i32>()?))
+            .filter(db_entities::crawler_lease::Column::Holder.eq(holder))
+            .exec(self)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_crawler_statuses(&self) -> Result<Vec<CrawlerStatus>> {
+        let backend = self.get_database_backend();
+        let now = DbNow::find_by_statement(Statement::from_string(
+            backend,
+            format!("SELECT {} AS now", db_now(backend)),
+        ))
+        .one(self)
+        .await?
+        .ok_or("could not read database time")?
+        .now;
+
+        let mut statuses = vec![];
+        for row in db_entities::crawler_lease::Entity::find().all(self).await? {
+            let mut status = CrawlerStatus::decode(hex::decode(row.status)?.as_slice())?;
+            status.chain_id = row.chain_id.to_string();
+            status.running = row.expires_at >= now;
+            statuses.push(status);
+        }
+
+        Ok(statuses)
+    }
+}
+
+#[derive(FromQueryResult)]
+struct DbNow {
+    now: i64,
+}
+
+/// Current time of the database in unix milliseconds, as an sql expression
+fn db_now(backend: DatabaseBackend) -> String {
+    match backend {
+        DatabaseBackend::Postgres => "CAST(EXTRACT(EPOCH FROM now()) * 1000 AS BIGINT)",
+        DatabaseBackend::MySql => "CAST(UNIX_TIMESTAMP(NOW(3)) * 1000 AS SIGNED)",
+        DatabaseBackend::Sqlite => "CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)",
+    }
+    .to_string()
+}
+
+// Controls can be set before the crawler ever ran, in which case the row has no state yet
+async fn insert_crawler_row(db: &DatabaseConnection, chain_id: &str) -> Result<()> {
+    db_entities::crawler::Entity::insert(db_entities::crawler::ActiveModel {
+        chain_id: Set(chain_id.parse::<i32>()?),
+        data: Set("".to_string()),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(db_entities::crawler::Column::ChainId)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+
+    Ok(())
 }
@@ -0,0 +1,286 @@
+use serde_json::Value;
+use web3::types::{H160, H256, U256};
+
+use util::Result;
+
+/// Native value sent by a contract while a tx was executing
+pub struct InternalTransfer {
+    pub tx_hash: H256,
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+}
+
+/// Transfers in the result of `debug_traceBlockByHash` with the callTracer, whose traces come
+/// in the same order as the txs of the block
+pub fn call_tracer_transfers(tx_hashes: &[H256], traces: &Value) -> Result<Vec<InternalTransfer>> {
+    let traces = traces.as_array().ok_or("could not find block traces")?;
+    if traces.len() != tx_hashes.len() {
+        return Err(format!(
+            "got {} traces for a block with {} txs",
+            traces.len(),
+            tx_hashes.len()
+        )
+        .into());
+    }
+
+    let mut transfers = vec![];
+    for (tx_hash, trace) in tx_hashes.iter().zip(traces) {
+        // Older geth versions return the bare call frame without the tx hash
+        let frame = trace.get("result").unwrap_or(trace);
+        collect_call_transfers(*tx_hash, frame, 0, &mut transfers)?;
+    }
+
+    Ok(transfers)
+}
+
+fn collect_call_transfers(
+    tx_hash: H256,
+    frame: &Value,
+    depth: usize,
+    transfers: &mut Vec<InternalTransfer>,
+) -> Result<()> {
+    // Nothing a reverted call did sticks, including the calls it made
+    if !frame["error"].is_null() {
+        return Ok(());
+    }
+
+    // The top level call is the tx itself, which is already its own alert source
+    let call_type = frame["type"].as_str().unwrap_or_default();
+    if depth > 0 && moves_value(call_type) {
+        let value = parse_u256(&frame["value"])?;
+        if !value.is_zero() {
+            transfers.push(InternalTransfer {
+                tx_hash,
+                from: parse_address(&frame["from"])?,
+                to: parse_address(&frame["to"])?,
+                value,
+            });
+        }
+    }
+
+    for call in frame["calls"].as_array().unwrap_or(&vec![]) {
+        collect_call_transfers(tx_hash, call, depth + 1, transfers)?;
+    }
+
+    Ok(())
+}
+
+// Delegate, static and callcode calls run in the caller's context and never move its value
+fn moves_value(call_type: &str) -> bool {
+    matches!(
+        call_type.to_ascii_uppercase().as_str(),
+        "CALL" | "CREATE" | "CREATE2" | "SELFDESTRUCT"
+    )
+}
+
+/// Transfers in the result of the parity style `trace_block`, whose traces are flattened in
+/// call order with `traceAddress` locating each call inside its tx
+pub fn parity_transfers(block_hash: H256, traces: &Value) -> Result<Vec<InternalTransfer>> {
+    let traces = traces.as_array().ok_or("could not find block traces")?;
+
+    let mut transfers = vec![];
+    // Trace addresses of the reverted calls of the current tx
+    let mut reverted: Vec<Vec<Value>> = vec![];
+    let mut curr_tx = Value::Null;
+    for trace in traces {
+        // Block and uncle rewards are not part of any tx
+        if trace["transactionHash"].is_null() {
+            continue;
+        }
+        if parse_h256(&trace["blockHash"])? != block_hash {
+            return Err("traces belong to a different block".into());
+        }
+
+        if trace["transactionHash"] != curr_tx {
+            curr_tx = trace["transactionHash"].clone();
+            reverted.clear();
+        }
+        let trace_address = trace["traceAddress"]
+            .as_array()
+            .ok_or("could not find trace address")?;
+        if reverted.iter().any(|r| trace_address.starts_with(r)) {
+            continue;
+        }
+        if !trace["error"].is_null() {
+            reverted.push(trace_address.clone());
+            continue;
+        }
+        if trace_address.is_empty() {
+            continue;
+        }
+
+        let action = &trace["action"];
+        let (from, to, value) = match trace["type"].as_str().unwrap_or_default() {
+            "call" if moves_value(action["callType"].as_str().unwrap_or_default()) => {
+                (&action["from"], &action["to"], &action["value"])
+            }
+            "create" => (
+                &action["from"],
+                &trace["result"]["address"],
+                &action["value"],
+            ),
+            "suicide" => (
+                &action["address"],
+                &action["refundAddress"],
+                &action["balance"],
+            ),
+            _ => continue,
+        };
+
+        let value = parse_u256(value)?;
+        if !value.is_zero() {
+            transfers.push(InternalTransfer {
+                tx_hash: parse_h256(&curr_tx)?,
+                from: parse_address(from)?,
+                to: parse_address(to)?,
+                value,
+            });
+        }
+    }
+
+    Ok(transfers)
+}
+
+fn parse_u256(val: &Value) -> Result<U256> {
+    match val.as_str().map(|v| v.trim_start_matches("0x")) {
+        Some(val) if !val.is_empty() => Ok(U256::from_str_radix(val, 16)?),
+        _ => Ok(U256::zero()),
+    }
+}
+
+fn parse_address(val: &Value) -> Result<H160> {
+    Ok(val.as_str().ok_or("could not find address")?.parse()?)
+}
+
+fn parse_h256(val: &Value) -> Result<H256> {
+    Ok(val.as_str().ok_or("could not find hash")?.parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const BLOCK_HASH: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+    const TX_HASH: &str = "0x2222222222222222222222222222222222222222222222222222222222222222";
+    const OTHER_TX_HASH: &str =
+        "0x3333333333333333333333333333333333333333333333333333333333333333";
+
+    fn address(index: u8) -> String {
+        format!("{:#x}", H160::repeat_byte(index))
+    }
+
+    fn call(to: u8, value: u64, error: Option<&str>, calls: Vec<Value>) -> Value {
+        json!({
+            "type": "CALL",
+            "from": address(1),
+            "to": address(to),
+            "value": format!("{:#x}", value),
+            "error": error,
+            "calls": calls,
+        })
+    }
+
+    fn parity_trace(
+        tx_hash: &str,
+        trace_address: Vec<usize>,
+        to: u8,
+        error: Option<&str>,
+    ) -> Value {
+        json!({
+            "type": "call",
+            "action": {
+                "callType": "call",
+                "from": address(1),
+                "to": address(to),
+                "value": "0x64",
+            },
+            "blockHash": BLOCK_HASH,
+            "transactionHash": tx_hash,
+            "traceAddress": trace_address,
+            "error": error,
+        })
+    }
+
+    #[test]
+    fn call_tracer_skips_reverted_subcalls() {
+        let traces = json!([{
+            "txHash": TX_HASH,
+            "result": call(2, 0, None, vec![
+                call(3, 100, Some("execution reverted"), vec![call(4, 50, None, vec![])]),
+                call(5, 200, None, vec![]),
+            ]),
+        }]);
+
+        let transfers = call_tracer_transfers(&[TX_HASH.parse().unwrap()], &traces).unwrap();
+
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].to, H160::repeat_byte(5));
+        assert_eq!(transfers[0].value, 200.into());
+    }
+
+    #[test]
+    fn call_tracer_skips_reverted_txs() {
+        // Bare call frames, as older geth versions return them
+        let traces = json!([call(
+            2,
+            100,
+            Some("out of gas"),
+            vec![call(3, 100, None, vec![])]
+        )]);
+
+        let transfers = call_tracer_transfers(&[TX_HASH.parse().unwrap()], &traces).unwrap();
+
+        assert!(transfers.is_empty());
+    }
+
+    #[test]
+    fn call_tracer_requires_a_trace_per_tx() {
+        let traces = json!([call(2, 0, None, vec![])]);
+
+        assert!(call_tracer_transfers(&[], &traces).is_err());
+    }
+
+    #[test]
+    fn parity_skips_reverted_subcalls() {
+        let traces = json!([
+            parity_trace(TX_HASH, vec![], 2, None),
+            parity_trace(TX_HASH, vec![0], 3, Some("Reverted")),
+            parity_trace(TX_HASH, vec![0, 0], 4, None),
+            parity_trace(TX_HASH, vec![1], 5, None),
+            // Block rewards have no tx
+            {"type": "reward", "action": {}, "blockHash": BLOCK_HASH, "transactionHash": null},
+        ]);
+
+        let transfers = parity_transfers(BLOCK_HASH.parse().unwrap(), &traces).unwrap();
+
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].tx_hash, TX_HASH.parse().unwrap());
+        assert_eq!(transfers[0].to, H160::repeat_byte(5));
+        assert_eq!(transfers[0].value, 100.into());
+    }
+
+    #[test]
+    fn parity_reverted_calls_only_apply_to_their_tx() {
+        let traces = json!([
+            parity_trace(TX_HASH, vec![], 2, None),
+            parity_trace(TX_HASH, vec![0], 3, Some("Reverted")),
+            parity_trace(OTHER_TX_HASH, vec![], 2, None),
+            parity_trace(OTHER_TX_HASH, vec![0], 3, None),
+        ]);
+
+        let transfers = parity_transfers(BLOCK_HASH.parse().unwrap(), &traces).unwrap();
+
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].tx_hash, OTHER_TX_HASH.parse().unwrap());
+    }
+
+    #[test]
+    fn parity_rejects_traces_of_another_block() {
+        let traces = json!([parity_trace(TX_HASH, vec![0], 3, None)]);
+
+        assert!(parity_transfers(H256::zero(), &traces).is_err());
+    }
+}
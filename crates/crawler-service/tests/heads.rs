@@ -0,0 +1,79 @@
+use async_tungstenite::{tokio::accept_async, tungstenite::Message};
+use crawler_service::heads::{cometbft_new_block_request, eth_new_heads_request, HeadSubscription};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+// Serves a single websocket connection that answers the subscribe request with `responses`
+async fn mock_ws_server(responses: Vec<Value>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(stream).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+        for response in responses {
+            ws.send(Message::Text(response.to_string())).await.unwrap();
+        }
+        // Keep the connection open so the subscription does not reconnect
+        while ws.next().await.is_some() {}
+    });
+
+    format!("ws://{}", addr)
+}
+
+async fn assert_wakes_up(url: String, request: Value) {
+    let heads = HeadSubscription::default();
+    let subscription = heads.clone();
+    tokio::spawn(async move { subscription.run(&url, request).await });
+
+    assert!(heads.wait().await);
+}
+
+#[tokio::test]
+async fn eth_new_heads_wake_up_crawler() {
+    let url = mock_ws_server(vec![
+        json!({"jsonrpc": "2.0", "id": 1, "result": "0x9cef478923ff08bf67fde6c64013158d"}),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": {
+                "subscription": "0x9cef478923ff08bf67fde6c64013158d",
+                "result": {"number": "0x1b4"},
+            },
+        }),
+    ])
+    .await;
+
+    assert_wakes_up(url, eth_new_heads_request()).await;
+}
+
+#[tokio::test]
+async fn cometbft_new_block_wakes_up_crawler() {
+    let url = mock_ws_server(vec![
+        json!({"jsonrpc": "2.0", "id": 1, "result": {}}),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "query": "tm.event='NewBlock'",
+                "data": {"type": "tendermint/event/NewBlock", "value": {}},
+            },
+        }),
+    ])
+    .await;
+
+    assert_wakes_up(url, cometbft_new_block_request()).await;
+}
+
+#[tokio::test]
+async fn subscribe_response_does_not_wake_up_crawler() {
+    let url = mock_ws_server(vec![json!({"jsonrpc": "2.0", "id": 1, "result": {}})]).await;
+
+    let heads = HeadSubscription::default();
+    let subscription = heads.clone();
+    tokio::spawn(async move { subscription.run(&url, cometbft_new_block_request()).await });
+
+    assert!(!heads.wait().await);
+}
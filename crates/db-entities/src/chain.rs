@@ -20,6 +20,8 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_one = "super::crawler::Entity")]
     Crawler,
+    #[sea_orm(has_one = "super::crawler_lease::Entity")]
+    CrawlerLease,
     #[sea_orm(has_many = "super::user_alert::Entity")]
     UserAlert,
 }
@@ -30,6 +32,12 @@ impl Related<super::crawler::Entity> for Entity {
     }
 }
 
+impl Related<super::crawler_lease::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CrawlerLease.def()
+    }
+}
+
 impl Related<super::user_alert::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserAlert.def()
@@ -10,6 +10,8 @@ pub struct Model {
     #[sea_orm(unique)]
     pub chain_id: i32,
     pub data: String,
+    pub paused: bool,
+    pub requested_cursor: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -5,6 +5,7 @@ pub mod prelude;
 pub mod alert_notification;
 pub mod chain;
 pub mod crawler;
+pub mod crawler_lease;
 pub mod jwt;
 pub mod telegram_chat_id;
 pub mod user_alert;
@@ -14,6 +14,9 @@ mod m20230725_204915_jwt_management;
 mod m20230731_204843_client_webhook_endpoint;
 mod m20230731_210652_client_id;
 mod m20230731_220915_alert_client_id;
+mod m20261018_093000_crawler_lease;
+mod m20261018_110000_crawler_status;
+mod m20261018_130000_crawler_controls;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -32,6 +35,9 @@ impl MigratorTrait for Migrator {
             Box::new(m20230731_204843_client_webhook_endpoint::Migration),
             Box::new(m20230731_210652_client_id::Migration),
             Box::new(m20230731_220915_alert_client_id::Migration),
+            Box::new(m20261018_093000_crawler_lease::Migration),
+            Box::new(m20261018_110000_crawler_status::Migration),
+            Box::new(m20261018_130000_crawler_controls::Migration),
         ]
     }
 }
@@ -27,6 +27,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(Iden)]
+#[allow(clippy::enum_variant_names)]
 pub enum Jwt {
     Table,
     Jwt,
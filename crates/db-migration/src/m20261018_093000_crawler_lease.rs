@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CrawlerLease::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CrawlerLease::ChainId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CrawlerLease::Holder).string().not_null())
+                    .col(
+                        ColumnDef::new(CrawlerLease::ExpiresAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_crawler_lease_chain")
+                            .from(CrawlerLease::Table, CrawlerLease::ChainId)
+                            .to(Alias::new("chain"), Alias::new("id"))
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CrawlerLease::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CrawlerLease {
+    Table,
+
+    ChainId,
+    Holder,
+    ExpiresAt,
+    Status,
+}
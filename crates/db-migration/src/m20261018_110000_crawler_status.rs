@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20261018_093000_crawler_lease::CrawlerLease;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CrawlerLease::Table)
+                    .add_column(
+                        ColumnDef::new(CrawlerLease::Status)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CrawlerLease::Table)
+                    .drop_column(CrawlerLease::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -2,6 +2,7 @@ use log::warn;
 use mempools_api::api::{user_alert::Status, BackendUserAlert};
 
 use util::{
+    pipeline::{PipelineConfig, WorkQueue, WorkToken},
     service_registry::{
         AlertFilter, AlertNotification, FilterServiceInterface, Notification,
         ProcessAlertSourceRequeust, ServiceRegistry,
@@ -14,61 +15,83 @@ use alerts::AlertSourceFilter;
 #[derive(Clone)]
 pub struct FilterService {
     registry: ServiceRegistry,
+    queue: WorkQueue<(ProcessAlertSourceRequeust, BackendUserAlert, WorkToken)>,
+    workers: usize,
 }
 
 impl FilterService {
-    pub fn new(registry: ServiceRegistry) -> Result<Self> {
-        Ok(Self { registry })
+    pub fn new(registry: ServiceRegistry, config: &PipelineConfig) -> Result<Self> {
+        Ok(Self {
+            registry,
+            queue: WorkQueue::new(config.filter_queue_size),
+            workers: config.filter_workers,
+        })
     }
-}
-
-#[tonic::async_trait]
-impl FilterServiceInterface for FilterService {
-    async fn process_alert_source(&self, alert_source: ProcessAlertSourceRequeust) -> Result<()> {
-        let registry = self.registry.get_services().await?;
-        let alert_service = registry.alert_service;
-
-        let mut page = 0;
-        loop {
-            let ctx = alert_source.ctx();
-            let filter = AlertFilter {
-                alert_source: Some(ctx.source_type),
-                chain_id: Some(ctx.chain_id.parse::<i32>()?),
-                ..Default::default()
-            };
-            let alerts = alert_service.get_alerts(filter, Some(page)).await?;
-
-            if alerts.is_empty() {
-                break;
-            }
 
-            for alert in alerts {
-                let user_alert_id = alert
-                    .user_alert
-                    .as_ref()
-                    .ok_or("Missing user alert")?
-                    .id
-                    .clone();
-
-                let ctx = ctx.clone();
-                let svc = registry.filter_service.clone();
-                let alert_source = alert_source.clone();
-                let alert_source_id = ctx.id;
-                let alert_source_type_name = ctx.source_type.as_str_name();
-                tokio::spawn(async move {
+    pub fn spawn_daemons(&self) {
+        let svc = self.clone();
+        self.queue
+            .spawn_workers(self.workers, move |(alert_source, alert, work)| {
+                let svc = svc.clone();
+                async move {
+                    let ctx = alert_source.ctx();
+                    let user_alert_id = alert
+                        .user_alert
+                        .as_ref()
+                        .map(|a| a.id.clone())
+                        .unwrap_or_default();
                     if let Err(err) = svc
-                        .process_alert_for_alert_source(alert_source, alert.clone())
+                        .process_alert_for_alert_source(alert_source, alert, work)
                         .await
                     {
                         warn!(
                             "failed to filter alert source {} {}, for alert id {},reason - {}",
-                            alert_source_type_name, alert_source_id, user_alert_id, err
+                            ctx.source_type.as_str_name(),
+                            ctx.id,
+                            user_alert_id,
+                            err
                         )
                     };
-                });
-            }
+                }
+            });
+    }
+}
 
-            page += 1;
+#[tonic::async_trait]
+impl FilterServiceInterface for FilterService {
+    async fn process_alert_source(
+        &self,
+        alert_source: ProcessAlertSourceRequeust,
+        work: WorkToken,
+    ) -> Result<()> {
+        let registry = self.registry.get_services().await?;
+        let alert_service = registry.alert_service;
+
+        for chain_id in alert_source.alert_chain_ids() {
+            for source_type in alert_source.alert_sources() {
+                let mut page = 0;
+                loop {
+                    let filter = AlertFilter {
+                        alert_source: Some(source_type),
+                        chain_id: Some(chain_id.parse::<i32>()?),
+                        ..Default::default()
+                    };
+                    let alerts = alert_service.get_alerts(filter, Some(page)).await?;
+
+                    if alerts.is_empty() {
+                        break;
+                    }
+
+                    for alert in alerts {
+                        // waits while the filter queue is full so crawlers slow down instead of piling up
+                        self.queue
+                            .push((alert_source.clone(), alert, work.clone()))
+                            .await?;
+                    }
+
+                    page += 1;
+                }
+            }
         }
 
         Ok(())
@@ -78,6 +101,7 @@ impl FilterServiceInterface for FilterService {
         &self,
         alert_source: ProcessAlertSourceRequeust,
         alert: BackendUserAlert,
+        work: WorkToken,
     ) -> Result<()> {
         let user_alert = alert.user_alert.ok_or("Missing user alert")?;
 
@@ -88,6 +112,15 @@ impl FilterServiceInterface for FilterService {
             return Ok(());
         }
 
+        let include_pending = user_alert
+            .alert
+            .as_ref()
+            .map(|a| a.include_pending)
+            .unwrap_or_default();
+        if ctx.pending && !include_pending {
+            return Ok(());
+        }
+
         let tx_alert: Box<dyn AlertSourceFilter> = user_alert.clone().try_into()?;
         let notification = if let Ok(notification) = tx_alert.filter(&alert_source) {
             notification
@@ -102,8 +135,11 @@ impl FilterServiceInterface for FilterService {
                     notification,
                     alert_id: user_alert.id.clone(),
                     alert_source_id: ctx.id,
+                    pending: ctx.pending,
+                    msg_path: alert_source.msg_path(),
                 }),
                 user_alert.user_id,
+                work,
             )
             .await?;
 
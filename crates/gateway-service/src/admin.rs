@@ -1,9 +1,11 @@
 use crate::GatewayService;
 
 use mempools_api::api::{
-    gateway_admin_server::GatewayAdmin, CreateChainRequest, CreateChainResponse, GrantJwtRequest,
-    GrantJwtResponse, UpdateChainRequest, UpdateChainResponse, UpdateJwtValidityRequest,
-    UpdateJwtValidityResponse,
+    gateway_admin_server::GatewayAdmin, BackfillChainRequest, BackfillChainResponse,
+    CreateChainRequest, CreateChainResponse, GetCrawlerStatusRequest, GetCrawlerStatusResponse,
+    GrantJwtRequest, GrantJwtResponse, PauseCrawlerRequest, PauseCrawlerResponse,
+    ResumeCrawlerRequest, ResumeCrawlerResponse, SetCrawlerCursorRequest, SetCrawlerCursorResponse,
+    UpdateChainRequest, UpdateChainResponse, UpdateJwtValidityRequest, UpdateJwtValidityResponse,
 };
 
 use request_validation::Validateable;
@@ -22,13 +24,28 @@ impl GatewayAdmin for GatewayService {
             .to_grpc_result()?;
 
         let registry = self.registry.get_services().await.to_grpc_result()?;
-        let chain_svc = registry.chain_service;
-        Ok(Response::new(
-            chain_svc
-                .create_chain(request.get_ref())
+        let res = registry
+            .chain_service
+            .create_chain(request.get_ref())
+            .await
+            .to_grpc_result()?;
+
+        if let Some(cursor) = request.into_inner().start_cursor {
+            let chain_id = res
+                .chain
+                .as_ref()
+                .ok_or("could not find chain")
+                .to_grpc_result()?
+                .id
+                .clone();
+            registry
+                .crawler_service
+                .set_crawler_cursor(chain_id, cursor)
                 .await
-                .to_grpc_result()?,
-        ))
+                .to_grpc_result()?;
+        }
+
+        Ok(Response::new(res))
     }
     async fn update_chain(
         &self,
@@ -48,6 +65,122 @@ impl GatewayAdmin for GatewayService {
                 .to_grpc_result()?,
         ))
     }
+    async fn backfill_chain(
+        &self,
+        request: Request<BackfillChainRequest>,
+    ) -> Result<Response<BackfillChainResponse>, Status> {
+        request
+            .validate(self.registry.clone())
+            .await
+            .to_grpc_result()?;
+
+        let request = request.into_inner();
+        let failed_blocks = self
+            .registry
+            .get_services()
+            .await
+            .to_grpc_result()?
+            .crawler_service
+            .backfill_chain(
+                request.chain_id,
+                request.from_height,
+                request.to_height,
+                request.alert_id,
+            )
+            .await
+            .to_grpc_result()?;
+
+        Ok(Response::new(BackfillChainResponse { failed_blocks }))
+    }
+    async fn get_crawler_status(
+        &self,
+        request: Request<GetCrawlerStatusRequest>,
+    ) -> Result<Response<GetCrawlerStatusResponse>, Status> {
+        request
+            .validate(self.registry.clone())
+            .await
+            .to_grpc_result()?;
+
+        let request = request.into_inner();
+        let statuses = self
+            .registry
+            .get_services()
+            .await
+            .to_grpc_result()?
+            .crawler_service
+            .get_crawler_statuses()
+            .await
+            .to_grpc_result()?
+            .into_iter()
+            .filter(|s| request.chain_id.is_empty() || s.chain_id == request.chain_id)
+            .collect();
+
+        Ok(Response::new(GetCrawlerStatusResponse { statuses }))
+    }
+    async fn pause_crawler(
+        &self,
+        request: Request<PauseCrawlerRequest>,
+    ) -> Result<Response<PauseCrawlerResponse>, Status> {
+        request
+            .validate(self.registry.clone())
+            .await
+            .to_grpc_result()?;
+
+        self.registry
+            .get_services()
+            .await
+            .to_grpc_result()?
+            .crawler_service
+            .set_crawler_paused(request.into_inner().chain_id, true)
+            .await
+            .to_grpc_result()?;
+
+        Ok(Response::new(PauseCrawlerResponse {}))
+    }
+    async fn resume_crawler(
+        &self,
+        request: Request<ResumeCrawlerRequest>,
+    ) -> Result<Response<ResumeCrawlerResponse>, Status> {
+        request
+            .validate(self.registry.clone())
+            .await
+            .to_grpc_result()?;
+
+        self.registry
+            .get_services()
+            .await
+            .to_grpc_result()?
+            .crawler_service
+            .set_crawler_paused(request.into_inner().chain_id, false)
+            .await
+            .to_grpc_result()?;
+
+        Ok(Response::new(ResumeCrawlerResponse {}))
+    }
+    async fn set_crawler_cursor(
+        &self,
+        request: Request<SetCrawlerCursorRequest>,
+    ) -> Result<Response<SetCrawlerCursorResponse>, Status> {
+        request
+            .validate(self.registry.clone())
+            .await
+            .to_grpc_result()?;
+
+        let request = request.into_inner();
+        self.registry
+            .get_services()
+            .await
+            .to_grpc_result()?
+            .crawler_service
+            .set_crawler_cursor(
+                request.chain_id,
+                request.cursor.ok_or("missing cursor").to_grpc_result()?,
+            )
+            .await
+            .to_grpc_result()?;
+
+        Ok(Response::new(SetCrawlerCursorResponse {}))
+    }
     async fn grant_jwt(
         &self,
         request: tonic::Request<GrantJwtRequest>,
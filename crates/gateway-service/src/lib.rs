@@ -1,14 +1,18 @@
+use std::collections::HashMap;
+
+use log::warn;
 use mempools_api::api::{
-    gateway_server::Gateway, AlertSource, BackendUserAlert, CreateAlertRequest,
-    CreateAlertResponse, DeleteAlertRequest, DeleteAlertResponse, GetAlertsRequest,
-    GetAlertsResponse, GetChainsRequest, GetChainsResponse, GetNotificationsRequest,
-    GetNotificationsResponse, GetStatisticsRequest, GetStatisticsResponse, SendBroadcastRequest,
-    SendBroadcastResponse, UpdateAlertRequest, UpdateAlertResponse,
+    gateway_server::Gateway, AlertSource, BackendUserAlert, ChainCrawlerStatus, CrawlerStatus,
+    CreateAlertRequest, CreateAlertResponse, DeleteAlertRequest, DeleteAlertResponse,
+    GetAlertsRequest, GetAlertsResponse, GetChainsRequest, GetChainsResponse,
+    GetNotificationsRequest, GetNotificationsResponse, GetStatisticsRequest, GetStatisticsResponse,
+    SendBroadcastRequest, SendBroadcastResponse, UpdateAlertRequest, UpdateAlertResponse,
 };
 
 use request_validation::Validateable;
 use tonic::{Request, Response, Status};
 use util::{
+    pipeline::WorkToken,
     service_registry::{
         AlertFilter, NotificationFilter, NotificationServiceInterface, ServiceRegistry, TimeRange,
     },
@@ -82,7 +86,7 @@ impl Gateway for GatewayService {
         let alerts = alert_svc
             .get_alerts(
                 AlertFilter {
-                    id: req.alert_id,
+                    id: req.alert_id.and_then(|id| id.parse().ok()),
                     user_id: req.user_id,
                     chain_id: req.chain_id,
                     ..Default::default()
@@ -266,10 +270,30 @@ impl Gateway for GatewayService {
             .to_grpc_result()?;
 
         let registry = self.registry.get_services().await.to_grpc_result()?;
-        let chain_svc = registry.chain_service;
-        Ok(Response::new(
-            chain_svc.get_chains().await.to_grpc_result()?,
-        ))
+        let mut chains = registry.chain_service.get_chains().await.to_grpc_result()?;
+        // Chains are still listed when the crawler statuses cannot be read, just without them
+        let mut statuses: HashMap<String, CrawlerStatus> =
+            match registry.crawler_service.get_crawler_statuses().await {
+                Ok(statuses) => statuses
+                    .into_iter()
+                    .map(|s| (s.chain_id.clone(), s))
+                    .collect(),
+                Err(err) => {
+                    warn!("failed to get crawler statuses - {}", err);
+                    HashMap::new()
+                }
+            };
+
+        for chain in chains.chains.iter_mut() {
+            chain.crawler_status = statuses.remove(&chain.id).map(|s| ChainCrawlerStatus {
+                running: s.running,
+                head_height: s.head_height,
+                processed_height: s.processed_height,
+                lag: s.lag,
+            });
+        }
+
+        Ok(Response::new(chains))
     }
     async fn send_broadcast(
         &self,
@@ -297,6 +321,7 @@ impl Gateway for GatewayService {
                     message: request.message,
                     client_id,
                 },
+                WorkToken::default(),
             )
             .await
             .to_grpc_result()?;
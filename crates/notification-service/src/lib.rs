@@ -1,6 +1,11 @@
 use std::time::Instant;
 
+use log::warn;
+use mempools_api::api::{
+    alert_notification_data, AlertNotificationData, RetractedNotificationData,
+};
 use reqwest::Client;
+use util::pipeline::{PipelineConfig, WorkQueue, WorkToken};
 use util::service_registry::{NotificationStatistics, ResponseTime, ServiceRegistry};
 use util::{
     service_registry::{
@@ -24,6 +29,8 @@ pub struct NotificationService {
     registry: ServiceRegistry,
     storage: Box<dyn NotificationStorage>,
     http_client: reqwest::Client,
+    queue: WorkQueue<(Notification, String, WorkToken)>,
+    workers: usize,
 }
 
 #[tonic::async_trait]
@@ -32,12 +39,12 @@ impl NotificationServiceInterface for NotificationService {
         &self,
         notification: Notification,
         alert_owner_id: String,
+        work: WorkToken,
     ) -> Result<()> {
-        match notification {
-            Notification::AlertNotification(n) => {
-                self.send_alert_notification(n, alert_owner_id).await?
-            }
-        }
+        // waits while the notification queue is full so filtering slows down instead of piling up
+        self.queue
+            .push((notification, alert_owner_id, work))
+            .await?;
 
         Ok(())
     }
@@ -55,16 +62,108 @@ impl NotificationServiceInterface for NotificationService {
     ) -> Result<Vec<mempools_api::api::AlertNotification>> {
         self.storage.get_notifications(filter, Some(page)).await
     }
+    async fn retract_notifications(
+        &self,
+        chain_id: String,
+        tx_hashes: Vec<String>,
+        block_hash: String,
+    ) -> Result<()> {
+        if tx_hashes.is_empty() {
+            return Ok(());
+        }
+
+        let alert_svc = self.registry.get_services().await?.alert_service;
+        let notifications = self
+            .storage
+            .get_notifications_by_alert_sources(chain_id.parse()?, tx_hashes)
+            .await?;
+
+        for notification in notifications {
+            // Pending notifications never depended on the orphaned block
+            if let Some(AlertNotificationData {
+                alert_notification_data,
+                pending,
+            }) = &notification.notification_data
+            {
+                if *pending
+                    || matches!(
+                        alert_notification_data,
+                        Some(alert_notification_data::AlertNotificationData::Retracted(_))
+                    )
+                {
+                    continue;
+                }
+            }
+
+            let user_id = alert_svc
+                .get_alert_by_id(notification.alert_id.clone())
+                .await?
+                .user_alert
+                .ok_or("Missing user alert")?
+                .user_id;
+
+            self.send_alert_notification(
+                AlertNotification {
+                    notification: alert_notification_data::AlertNotificationData::Retracted(
+                        RetractedNotificationData {
+                            notification_id: notification.id,
+                            tx_hash: notification.alert_source_id.clone(),
+                            block_hash: block_hash.clone(),
+                        },
+                    ),
+                    alert_id: notification.alert_id,
+                    alert_source_id: notification.alert_source_id,
+                    pending: false,
+                    msg_path: vec![],
+                },
+                user_id,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
 }
 
 impl NotificationService {
-    pub fn new<S: NotificationStorage>(registry: ServiceRegistry, storage: S) -> Result<Self> {
+    pub fn new<S: NotificationStorage>(
+        registry: ServiceRegistry,
+        storage: S,
+        config: &PipelineConfig,
+    ) -> Result<Self> {
         Ok(Self {
             registry,
             storage: Box::new(storage),
             http_client: Client::new(),
+            queue: WorkQueue::new(config.notification_queue_size),
+            workers: config.notification_workers,
         })
     }
+
+    pub fn spawn_daemons(&self) {
+        let svc = self.clone();
+        self.queue
+            .spawn_workers(self.workers, move |(notification, alert_owner_id, work)| {
+                let svc = svc.clone();
+                async move {
+                    // the notification counts as done for the crawler once it is sent
+                    let _work = work;
+                    match notification {
+                        Notification::AlertNotification(n) => {
+                            let alert_id = n.alert_id.clone();
+                            let alert_source_id = n.alert_source_id.clone();
+                            if let Err(err) = svc.send_alert_notification(n, alert_owner_id).await
+                            {
+                                warn!(
+                                    "failed to send notification for alert {} from alert source {} - {}",
+                                    alert_id, alert_source_id, err
+                                )
+                            }
+                        }
+                    }
+                }
+            });
+    }
     async fn send_alert_notification(
         &self,
         alert_notification: AlertNotification,
@@ -30,6 +30,11 @@ pub trait NotificationStorage: DynClone + Send + Sync + 'static {
         alert_id: Option<i32>,
         user_id: Option<String>,
     ) -> Result<NotificationStatistics>;
+    async fn get_notifications_by_alert_sources(
+        &self,
+        chain_id: i32,
+        alert_source_ids: Vec<String>,
+    ) -> Result<Vec<AlertNotification>>;
     async fn get_telegram_chat_id(&self, username: String) -> Result<String>;
     async fn set_telegram_chat_id(&self, username: String, chat_id: String) -> Result<()>;
     async fn get_endpoint(&self, client_id: &str) -> Result<String>;
@@ -51,6 +56,7 @@ impl NotificationStorage for DatabaseConnection {
             notification_data: Set(hex::encode(
                 AlertNotificationData {
                     alert_notification_data: Some(req.notification.clone()),
+                    pending: req.pending,
                 }
                 .encode_to_vec(),
             )),
@@ -208,6 +214,22 @@ impl NotificationStorage for DatabaseConnection {
         })
     }
 
+    async fn get_notifications_by_alert_sources(
+        &self,
+        chain_id: i32,
+        alert_source_ids: Vec<String>,
+    ) -> Result<Vec<AlertNotification>> {
+        let models = db_entities::alert_notification::Entity::find()
+            .filter(db_entities::alert_notification::Column::DeletedAt.is_null())
+            .filter(db_entities::alert_notification::Column::AlertSourceId.is_in(alert_source_ids))
+            .left_join(db_entities::user_alert::Entity)
+            .filter(db_entities::user_alert::Column::ChainId.eq(chain_id))
+            .all(self)
+            .await?;
+
+        Ok(models.try_convert()?)
+    }
+
     async fn get_telegram_chat_id(&self, username: String) -> Result<String> {
         let row = db_entities::telegram_chat_id::Entity::find()
             .filter(db_entities::telegram_chat_id::Column::Username.eq(username))
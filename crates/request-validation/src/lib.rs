@@ -1,7 +1,10 @@
 use mempools_api::api::{
-    alert::ChainAlert, cosmos_evm_alert, Alert, CosmosAlert, CosmosEvmAlert, CreateAlertRequest,
+    alert::ChainAlert, alert_cosmos_governance::Event as GovernanceEvent,
+    alert_cosmos_validator::Event as ValidatorEvent, cosmos_evm_alert, crawler_cursor, Alert,
+    BackfillChainRequest, CosmosAlert, CosmosEvmAlert, CrawlerCursor, CreateAlertRequest,
     CreateChainRequest, DeleteAlertRequest, EthAlert, GetAlertsRequest, GetChainsRequest,
-    GetNotificationsRequest, GetStatisticsRequest, GrantJwtRequest, SendBroadcastRequest,
+    GetCrawlerStatusRequest, GetNotificationsRequest, GetStatisticsRequest, GrantJwtRequest,
+    PauseCrawlerRequest, ResumeCrawlerRequest, SendBroadcastRequest, SetCrawlerCursorRequest,
     UpdateAlertRequest, UpdateChainRequest, UpdateJwtValidityRequest,
 };
 use tonic::Request;
@@ -48,6 +51,39 @@ impl Validateable for Request<CreateAlertRequest> {
                                 return Err("Signer must be specified".into())
                             }
                         },
+                        mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosBlockEvents(a) => {
+                            if a.event_type.is_empty(){
+                                return Err("Event type must be specified".into())
+                            }
+                        },
+                        mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosIbcPacket(a) => {
+                            if a.sender.is_empty() && a.receiver.is_empty() && a.channel.is_empty() && a.denom.is_empty(){
+                                return Err("Sender, receiver, channel or denom must be specified".into())
+                            }
+                        },
+                        mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosGovernance(a) => {
+                            if !a.voter.is_empty() && !a.events.is_empty() && !a.events.contains(&(GovernanceEvent::VoteCast as i32)){
+                                return Err("Voter can only be matched on votes".into())
+                            }
+                        },
+                        mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosStaking(a) => {
+                            if a.delegator.is_empty() && a.validator.is_empty(){
+                                return Err("Delegator or validator must be specified".into())
+                            }
+                        },
+                        mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosValidator(a) => {
+                            if a.validator.is_empty(){
+                                return Err("Validator must be specified".into())
+                            }
+                            if a.missed_blocks_threshold == 0 && a.events.contains(&(ValidatorEvent::MissedBlocks as i32)){
+                                return Err("Missed blocks threshold must be specified".into())
+                            }
+                        },
+                        mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosCw721Transfers(a) => {
+                            if a.contract_addr.is_empty() && a.token_id.is_empty(){
+                                return Err("Contract address or token id must be specified".into())
+                            }
+                        },
                     }
                 },
                 ChainAlert::CosmosEvmAlert(CosmosEvmAlert{
@@ -79,6 +115,39 @@ impl Validateable for Request<CreateAlertRequest> {
                                 return Err("Signer must be specified".into())
                             }
                         },
+                        cosmos_evm_alert::CosmosEvmAlert::AlertCosmosBlockEvents(a) => {
+                            if a.event_type.is_empty(){
+                                return Err("Event type must be specified".into())
+                            }
+                        },
+                        cosmos_evm_alert::CosmosEvmAlert::AlertCosmosIbcPacket(a) => {
+                            if a.sender.is_empty() && a.receiver.is_empty() && a.channel.is_empty() && a.denom.is_empty(){
+                                return Err("Sender, receiver, channel or denom must be specified".into())
+                            }
+                        },
+                        cosmos_evm_alert::CosmosEvmAlert::AlertCosmosGovernance(a) => {
+                            if !a.voter.is_empty() && !a.events.is_empty() && !a.events.contains(&(GovernanceEvent::VoteCast as i32)){
+                                return Err("Voter can only be matched on votes".into())
+                            }
+                        },
+                        cosmos_evm_alert::CosmosEvmAlert::AlertCosmosStaking(a) => {
+                            if a.delegator.is_empty() && a.validator.is_empty(){
+                                return Err("Delegator or validator must be specified".into())
+                            }
+                        },
+                        cosmos_evm_alert::CosmosEvmAlert::AlertCosmosValidator(a) => {
+                            if a.validator.is_empty(){
+                                return Err("Validator must be specified".into())
+                            }
+                            if a.missed_blocks_threshold == 0 && a.events.contains(&(ValidatorEvent::MissedBlocks as i32)){
+                                return Err("Missed blocks threshold must be specified".into())
+                            }
+                        },
+                        cosmos_evm_alert::CosmosEvmAlert::AlertEthNftTransfers(a) => {
+                            if a.contract_addr.is_empty() && a.token_id.is_empty() && a.address.is_empty(){
+                                return Err("Contract address, token id or address must be specified".into())
+                            }
+                        },
                     }
                 },
                 ChainAlert::EthAlert(EthAlert{ eth_alert: Some(eth_alert)}) => {
@@ -98,6 +167,11 @@ impl Validateable for Request<CreateAlertRequest> {
                                 return Err("Address must be specified".into())
                             }
                         },
+                        mempools_api::api::eth_alert::EthAlert::AlertEthNftTransfers(a) => {
+                            if a.contract_addr.is_empty() && a.token_id.is_empty() && a.address.is_empty(){
+                                return Err("Contract address, token id or address must be specified".into())
+                            }
+                        },
                     }
                 },
                 ChainAlert::ArchwayBroadcastAlert(_) => {},
@@ -167,6 +241,10 @@ impl Validateable for Request<UpdateJwtValidityRequest> {
 #[tonic::async_trait]
 impl Validateable for Request<CreateChainRequest> {
     async fn validate(&self, _registry: ServiceRegistry) -> Result<()> {
+        if let Some(cursor) = &self.get_ref().start_cursor {
+            validate_cursor(cursor)?;
+        }
+
         Ok(())
     }
 }
@@ -177,6 +255,71 @@ impl Validateable for Request<UpdateChainRequest> {
         Ok(())
     }
 }
+
+#[tonic::async_trait]
+impl Validateable for Request<BackfillChainRequest> {
+    async fn validate(&self, _registry: ServiceRegistry) -> Result<()> {
+        let req = self.get_ref();
+        if req.chain_id.is_empty() {
+            return Err("backfill needs to specify chain".into());
+        }
+        if req.from_height == 0 || req.from_height > req.to_height {
+            return Err("invalid block range".into());
+        }
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Validateable for Request<GetCrawlerStatusRequest> {
+    async fn validate(&self, _registry: ServiceRegistry) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Validateable for Request<PauseCrawlerRequest> {
+    async fn validate(&self, _registry: ServiceRegistry) -> Result<()> {
+        if self.get_ref().chain_id.is_empty() {
+            return Err("missing chain id".into());
+        }
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Validateable for Request<ResumeCrawlerRequest> {
+    async fn validate(&self, _registry: ServiceRegistry) -> Result<()> {
+        if self.get_ref().chain_id.is_empty() {
+            return Err("missing chain id".into());
+        }
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Validateable for Request<SetCrawlerCursorRequest> {
+    async fn validate(&self, _registry: ServiceRegistry) -> Result<()> {
+        let req = self.get_ref();
+        if req.chain_id.is_empty() {
+            return Err("missing chain id".into());
+        }
+
+        validate_cursor(req.cursor.as_ref().ok_or("missing cursor")?)
+    }
+}
+
+fn validate_cursor(cursor: &CrawlerCursor) -> Result<()> {
+    match cursor.cursor.as_ref().ok_or("missing cursor")? {
+        crawler_cursor::Cursor::Height(0) => Err("cursor height has to be positive".into()),
+        crawler_cursor::Cursor::Head(false) => Err("invalid cursor".into()),
+        _ => Ok(()),
+    }
+}
+
 #[tonic::async_trait]
 impl Validateable for Request<SendBroadcastRequest> {
     async fn validate(&self, _registry: ServiceRegistry) -> Result<()> {
@@ -3,7 +3,7 @@ use std::env;
 use base64::Engine;
 use serde::Deserialize;
 use serde::Serialize;
-use util::Result;
+use util::{pipeline::PipelineConfig, Result};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ApplicationConfig {
@@ -16,7 +16,8 @@ impl Default for ApplicationConfig {
     fn default() -> Self {
         Self {
             jwt_secret: Box::new(b"default_key_insecure".to_owned()),
-            db_url: "postgresql://0.0.0.0:5432?dbname=postgres&user=postgres&password=mempools".to_string(),
+            db_url: "postgresql://0.0.0.0:5432?dbname=postgres&user=postgres&password=mempools"
+                .to_string(),
             admins: vec!["admin@admin.com".to_string()],
         }
     }
@@ -25,6 +26,8 @@ impl Default for ApplicationConfig {
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Config {
     pub application_config: ApplicationConfig,
+    #[serde(default)]
+    pub pipeline_config: PipelineConfig,
 }
 
 impl Config {
@@ -66,12 +66,14 @@ async fn main() -> Result<()> {
     server::test_data::add_test_data(&db).await?;
 
     let gateway_service = GatewayService::new(registry.clone());
-    let crawler_service = CrawlerService::new(db.clone(), registry.clone());
-    let filter_service = FilterService::new(registry.clone())?;
+    let crawler_service =
+        CrawlerService::new(db.clone(), registry.clone(), config.pipeline_config.clone());
+    let filter_service = FilterService::new(registry.clone(), &config.pipeline_config)?;
     let alert_service = AlertService::new(db.clone());
     let auth_service = AuthService::new(db.clone(), &config.application_config.jwt_secret);
     let chain_service = ChainService::new(db.clone());
-    let notification_service = NotificationService::new(registry.clone(), db.clone())?;
+    let notification_service =
+        NotificationService::new(registry.clone(), db.clone(), &config.pipeline_config)?;
 
     let svcs = RegistryServices {
         filter_service: Box::new(filter_service.clone()),
@@ -79,11 +81,14 @@ async fn main() -> Result<()> {
         auth_service: Box::new(auth_service.clone()),
         notification_service: Box::new(notification_service.clone()),
         chain_service: Box::new(chain_service.clone()),
+        crawler_service: Box::new(crawler_service.clone()),
     };
     registry.register_services(svcs).await;
 
     // Daemons
     crawler_service.spawn_daemons();
+    filter_service.spawn_daemons();
+    notification_service.spawn_daemons();
 
     // Create server
     let server = Server::builder()
@@ -1,11 +1,76 @@
+use std::time::Duration;
+
 use chain_service::storage::ChainStorage;
+use chain_simulator::{SimulatedCosmosChain, SimulatedCosmosTx, SimulatedEthChain, SimulatedEthTx};
 use db_migration::ToDbResult;
-use mempools_api::api::{CosmosChainData, CosmosEvmChainData, CreateChainRequest, EthChainData};
+use mempools_api::api::{chain_data, ChainData, CosmosChainData, CreateChainRequest};
 use sea_orm::DatabaseConnection;
 use util::Result;
 
+// How often the simulated chains produce a block with a demo transfer
+const SIMULATED_BLOCK_INTERVAL: Duration = Duration::from_secs(5);
+
 pub async fn add_test_data(db: &DatabaseConnection) -> Result<()> {
+    // Lets the dev server run without network access
+    let chains = if std::env::var("SIMULATED_CHAINS").is_ok() {
+        simulated_chains().await?
+    } else {
+        public_chains()
+    };
+
+    for chain in chains {
+        db.create_chain(&chain).await.to_db_result()?;
+    }
+
+    Ok(())
+}
+
+/// Starts in-process cosmos and ethereum nodes that keep producing blocks with transfers
+async fn simulated_chains() -> Result<Vec<CreateChainRequest>> {
+    let cosmos = SimulatedCosmosChain::start("cosmos").await?;
+    let eth = SimulatedEthChain::start().await?;
+
     let chains = vec![
+        CreateChainRequest {
+            name: "Simulated Cosmos".to_string(),
+            icon: "".to_string(),
+            chain_data: Some(ChainData {
+                chain_data: Some(chain_data::ChainData::CosmosChainData(cosmos.chain_data())),
+            }),
+            start_cursor: None,
+        },
+        CreateChainRequest {
+            name: "Simulated Ethereum".to_string(),
+            icon: "".to_string(),
+            chain_data: Some(ChainData {
+                chain_data: Some(chain_data::ChainData::EthChainData(eth.chain_data())),
+            }),
+            start_cursor: None,
+        },
+    ];
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(SIMULATED_BLOCK_INTERVAL).await;
+            cosmos.push_block(vec![SimulatedCosmosTx::bank_send(
+                &cosmos.account(1),
+                &cosmos.account(2),
+                1_000,
+                "stake",
+            )]);
+            eth.push_block(vec![SimulatedEthTx::transfer(
+                SimulatedEthChain::account(1),
+                SimulatedEthChain::account(2),
+                1_000_000_000u64.into(),
+            )]);
+        }
+    });
+
+    Ok(chains)
+}
+
+fn public_chains() -> Vec<CreateChainRequest> {
+    vec![
         CreateChainRequest {
             name: "Archway Testnet".to_string(),
             icon: "".to_string(),
@@ -14,9 +79,16 @@ pub async fn add_test_data(db: &DatabaseConnection) -> Result<()> {
                     CosmosChainData {
                         grpc_endpoint: "grpc.constantine.archway.tech:443".to_string(),
                         bech32_prefix: "arch".to_string(),
+                        confirmations: 0,
+                        fallback_grpc_endpoints: vec![],
+                        websocket_endpoint: "".to_string(),
+                        rpc_endpoint: "".to_string(),
+                        watch_mempool: false,
+                        network_id: "".to_string(),
                     },
                 )),
             }),
+            start_cursor: None,
         },
         // CreateChainRequest {
         //     name: "Canto mainnet".to_string(),
@@ -49,11 +121,5 @@ pub async fn add_test_data(db: &DatabaseConnection) -> Result<()> {
         //         )),
         //     }),
         // },
-    ];
-
-    for chain in chains {
-        db.create_chain(&chain).await.to_db_result()?;
-    }
-
-    Ok(())
+    ]
 }
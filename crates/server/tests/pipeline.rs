@@ -0,0 +1,1276 @@
+use std::{convert::Infallible, time::Duration};
+
+use alert_service::AlertService;
+use auth_service::AuthService;
+use chain_service::{storage::ChainStorage, ChainService};
+use chain_simulator::{
+    erc1155_transfer_batch_log, erc20_transfer_log, SimulatedCosmosChain, SimulatedCosmosTx,
+    SimulatedEthChain, SimulatedEthTx, SimulatedInternalTransfer,
+};
+use cosmrs::proto::cosmos::gov::v1beta1::{TallyResult, VoteOption};
+use crawler_service::CrawlerService;
+use db_migration::{Migrator, MigratorTrait};
+use filter_service::FilterService;
+use hyper::{
+    body::to_bytes,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use mempools_api::api::{
+    alert::ChainAlert, alert_cosmos_governance::Event as GovernanceEvent,
+    alert_cosmos_ibc_packet::Stage, alert_cosmos_validator::Event as ValidatorEvent, chain_data,
+    cosmos_alert, crawler_cursor::Cursor, eth_alert, eth_chain_data::TraceMode, Alert,
+    AlertCosmosCw721Transfers, AlertCosmosGovernance, AlertCosmosIbcPacket,
+    AlertCosmosMonitorFunds, AlertCosmosStaking, AlertCosmosValidator, AlertEthMonitorFunds,
+    AlertEthNftTransfers, ChainData, CosmosAlert, CosmosChainData, CrawlerCursor,
+    CreateAlertRequest, CreateChainRequest, EthAlert, EthChainData, TokenMetadata,
+    UpdateChainRequest,
+};
+use notification_service::NotificationService;
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use util::{
+    pipeline::PipelineConfig,
+    service_registry::{IbcPacket, RegistryServices, ServiceRegistry},
+    Result,
+};
+
+const USER_ID: &str = "1";
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Services wired the same way as the server, on a fresh sqlite database
+struct Pipeline {
+    svcs: RegistryServices,
+    db: DatabaseConnection,
+    crawler_service: CrawlerService<DatabaseConnection>,
+    filter_service: FilterService,
+    notification_service: NotificationService,
+}
+
+impl Pipeline {
+    async fn new() -> Result<Self> {
+        // Every connection of the pool shares the same in-memory database
+        let db = Database::connect(
+            ConnectOptions::new("sqlite::memory:".to_string())
+                .sqlx_logging(false)
+                .clone(),
+        )
+        .await?;
+        Migrator::up(&db, None).await?;
+
+        let config = PipelineConfig::default();
+        let registry = ServiceRegistry::new();
+        let crawler_service = CrawlerService::new(db.clone(), registry.clone(), config.clone());
+        let filter_service = FilterService::new(registry.clone(), &config)?;
+        let notification_service = NotificationService::new(registry.clone(), db.clone(), &config)?;
+
+        let svcs = RegistryServices {
+            filter_service: Box::new(filter_service.clone()),
+            alert_service: Box::new(AlertService::new(db.clone())),
+            auth_service: Box::new(AuthService::new(db.clone(), b"test")),
+            notification_service: Box::new(notification_service.clone()),
+            chain_service: Box::new(ChainService::new(db.clone())),
+            crawler_service: Box::new(crawler_service.clone()),
+        };
+        registry.register_services(svcs.clone()).await;
+
+        Ok(Self {
+            svcs,
+            db,
+            crawler_service,
+            filter_service,
+            notification_service,
+        })
+    }
+
+    // Started once the chains exist, otherwise the crawler only sees them a lease renewal later
+    fn spawn_daemons(&self) {
+        self.crawler_service.spawn_daemons();
+        self.filter_service.spawn_daemons();
+        self.notification_service.spawn_daemons();
+    }
+}
+
+/// Webhook endpoint that forwards the body of every notification it receives
+fn start_webhook() -> Result<(String, mpsc::UnboundedReceiver<Value>)> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let make_svc = make_service_fn(move |_| {
+        let sender = sender.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let sender = sender.clone();
+                async move {
+                    let body = to_bytes(req.into_body()).await.unwrap_or_default();
+                    if let Ok(body) = serde_json::from_slice(&body) {
+                        let _ = sender.send(body);
+                    }
+                    Ok::<_, Infallible>(Response::new(Body::empty()))
+                }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&"127.0.0.1:0".parse()?)?.serve(make_svc);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+
+    Ok((url, receiver))
+}
+
+/// Creates the chain and an alert on it, the crawler starts at `start_height`
+async fn watch_chain(
+    svcs: &RegistryServices,
+    chain_data: chain_data::ChainData,
+    alert: ChainAlert,
+    start_height: u64,
+) -> Result<mpsc::UnboundedReceiver<Value>> {
+    let (webhook_endpoint, notifications) = start_webhook()?;
+    svcs.auth_service
+        .generate_jwt(
+            USER_ID.to_string(),
+            TokenMetadata {
+                name: "test".to_string(),
+                webhook_endpoint,
+            },
+        )
+        .await?;
+
+    let chain_id = create_chain(svcs, chain_data, start_height).await?;
+
+    svcs.alert_service
+        .create_alert(
+            &CreateAlertRequest {
+                user_id: USER_ID.to_string(),
+                alert: Some(Alert {
+                    chain_alert: Some(alert),
+                    ..Default::default()
+                }),
+                message: "".to_string(),
+                chain_id,
+                name: "test".to_string(),
+            },
+            USER_ID.to_string(),
+        )
+        .await?;
+
+    Ok(notifications)
+}
+
+/// Forgets the network of a chain, as stored for chains created before it was looked up. Goes
+/// straight to the database since the chain service fills it in
+async fn clear_network_id(pipeline: &Pipeline, chain_id: &str) -> Result<()> {
+    let mut chain = pipeline
+        .svcs
+        .chain_service
+        .get_chains()
+        .await?
+        .chains
+        .into_iter()
+        .find(|c| c.id == chain_id)
+        .ok_or("could not find chain")?;
+    if let Some(ChainData {
+        chain_data: Some(chain_data::ChainData::CosmosChainData(data)),
+    }) = chain.chain_data.as_mut()
+    {
+        data.network_id = "".to_string();
+    }
+    pipeline
+        .db
+        .update_chain(&UpdateChainRequest { chain: Some(chain) })
+        .await?;
+
+    Ok(())
+}
+
+/// Creates a chain the crawler starts at `start_height` and returns its id
+async fn create_chain(
+    svcs: &RegistryServices,
+    chain_data: chain_data::ChainData,
+    start_height: u64,
+) -> Result<String> {
+    let chain = svcs
+        .chain_service
+        .create_chain(&CreateChainRequest {
+            name: "Simulated".to_string(),
+            icon: "".to_string(),
+            chain_data: Some(ChainData {
+                chain_data: Some(chain_data),
+            }),
+            start_cursor: None,
+        })
+        .await?
+        .chain
+        .ok_or("could not find chain")?;
+    svcs.crawler_service
+        .set_crawler_cursor(
+            chain.id.clone(),
+            CrawlerCursor {
+                cursor: Some(Cursor::Height(start_height)),
+            },
+        )
+        .await?;
+
+    Ok(chain.id)
+}
+
+async fn next_notification(notifications: &mut mpsc::UnboundedReceiver<Value>) -> Value {
+    tokio::time::timeout(NOTIFICATION_TIMEOUT, notifications.recv())
+        .await
+        .expect("no notification received")
+        .expect("webhook stopped")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cosmos_transfer_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    let (from, to) = (chain.account(1), chain.account(2));
+    chain.push_block(vec![SimulatedCosmosTx::bank_send(
+        &from, &to, 1_000, "stake",
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosMonitorFunds(
+                AlertCosmosMonitorFunds {
+                    address: to.clone(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(body["user_id"], USER_ID);
+    assert_eq!(notification["from"], from);
+    assert_eq!(notification["to"], to);
+    assert_eq!(notification["amount"][0]["amount"], "1000");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn authz_wrapped_transfer_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    let (grantee, from, to) = (chain.account(1), chain.account(2), chain.account(3));
+    chain.push_block(vec![SimulatedCosmosTx::authz_exec(
+        &grantee,
+        SimulatedCosmosTx::bank_send(&from, &to, 1_000, "stake"),
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosMonitorFunds(
+                AlertCosmosMonitorFunds {
+                    address: to.clone(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(notification["from"], from);
+    assert_eq!(notification["to"], to);
+    assert_eq!(notification["amount"][0]["amount"], "1000");
+    assert_eq!(body["alert"]["msg_path"], json!([0]));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ica_wrapped_transfer_is_not_matched_on_controller_chain() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    let (owner, from, to) = (chain.account(1), chain.account(2), chain.account(3));
+    chain.push_block(vec![
+        SimulatedCosmosTx::ica_send_tx(
+            &owner,
+            "connection-0",
+            SimulatedCosmosTx::bank_send(&from, &to, 1_000, "stake"),
+        ),
+        SimulatedCosmosTx::bank_send(&from, &to, 2_000, "stake"),
+    ]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosMonitorFunds(
+                AlertCosmosMonitorFunds {
+                    address: to.clone(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(notification["amount"][0]["amount"], "2000");
+    assert!(body["alert"].get("msg_path").is_none());
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+            .await
+            .is_err(),
+        "msg run on the host chain was notified"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cosmos_transfer_reaches_webhook_without_rpc_endpoint() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    let (from, to) = (chain.account(1), chain.account(2));
+    chain.push_block(vec![
+        SimulatedCosmosTx::bank_send(&from, &chain.account(3), 500, "stake"),
+        SimulatedCosmosTx::bank_send(&from, &to, 1_000, "stake"),
+    ]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.grpc_chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosMonitorFunds(
+                AlertCosmosMonitorFunds {
+                    address: to.clone(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(notification["to"], to);
+    assert_eq!(notification["amount"][0]["amount"], "1000");
+    // The txs of the block come from the tx index at once rather than one by one
+    assert_eq!(chain.get_tx_requests(), 0);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chain_with_unreachable_grpc_fallback_is_rejected() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    let mut chain_data = chain.chain_data();
+    // Nothing listens on port 1
+    chain_data.fallback_grpc_endpoints = vec!["http://127.0.0.1:1".to_string()];
+
+    let pipeline = Pipeline::new().await?;
+    let res = create_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain_data),
+        1,
+    )
+    .await;
+    assert!(
+        res.is_err(),
+        "chain with an unreachable grpc endpoint was created"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watching_mempool_without_rpc_endpoint_is_rejected_on_update() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    let pipeline = Pipeline::new().await?;
+    let chain_id = create_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.grpc_chain_data()),
+        1,
+    )
+    .await?;
+
+    let mut stored = pipeline
+        .svcs
+        .chain_service
+        .get_chains()
+        .await?
+        .chains
+        .into_iter()
+        .find(|c| c.id == chain_id)
+        .ok_or("could not find chain")?;
+    stored.chain_data = Some(ChainData {
+        chain_data: Some(chain_data::ChainData::CosmosChainData(CosmosChainData {
+            watch_mempool: true,
+            ..chain.grpc_chain_data()
+        })),
+    });
+    let res = pipeline
+        .svcs
+        .chain_service
+        .update_chain(&UpdateChainRequest {
+            chain: Some(stored),
+        })
+        .await;
+    assert!(
+        res.is_err(),
+        "mempool of a chain without rpc endpoint is watched"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cw721_transfer_through_marketplace_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("juno").await?;
+    assert_cw721_transfer_notified(&chain, chain.chain_data()).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn msg_logs_are_read_from_events_since_sdk_050() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("juno").await?;
+    chain.use_events_only_logs();
+    assert_cw721_transfer_notified(&chain, chain.chain_data()).await?;
+
+    let chain = SimulatedCosmosChain::start("juno").await?;
+    chain.use_events_only_logs();
+    assert_cw721_transfer_notified(&chain, chain.grpc_chain_data()).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_cw20_movement_of_a_msg_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("juno").await?;
+    let (sender, router, token, other) = (
+        chain.account(1),
+        chain.account(2),
+        chain.account(3),
+        chain.account(4),
+    );
+    // The router pays the sender twice and someone else in between
+    let mut wasm_attributes = vec![("_contract_address", router.as_str()), ("action", "swap")];
+    for (to, amount) in [(&sender, "750"), (&other, "100"), (&sender, "250")] {
+        wasm_attributes.extend([
+            ("_contract_address", token.as_str()),
+            ("action", "transfer"),
+            ("from", router.as_str()),
+            ("to", to.as_str()),
+            ("amount", amount),
+        ]);
+    }
+    chain.push_block(vec![SimulatedCosmosTx::wasm_execute(
+        &sender,
+        &router,
+        json!({ "swap": {} }),
+        wasm_attributes,
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosMonitorFunds(
+                AlertCosmosMonitorFunds {
+                    address: sender.clone(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let mut amounts = vec![];
+    for _ in 0..2 {
+        let body = next_notification(&mut notifications).await;
+        let notification = &body["alert"]["notification"]["MonitorFundsToken"];
+        assert_eq!(notification["contract_addr"], token);
+        assert_eq!(notification["to"], sender);
+        amounts.push(notification["amount"].clone());
+    }
+    amounts.sort_by_key(|amount| amount.to_string());
+    assert_eq!(amounts, vec![json!("250"), json!("750")]);
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+            .await
+            .is_err(),
+        "movement to another address was notified"
+    );
+
+    Ok(())
+}
+
+// Only the wasm events in the msg log show the collection moving the token
+async fn assert_cw721_transfer_notified(
+    chain: &SimulatedCosmosChain,
+    chain_data: CosmosChainData,
+) -> Result<()> {
+    let buyer = chain.account(1);
+    let (marketplace, collection) = (chain.account(2), chain.account(3));
+    // The buyer only calls the marketplace, which has the collection transfer the token
+    chain.push_block(vec![SimulatedCosmosTx::wasm_execute(
+        &buyer,
+        &marketplace,
+        json!({ "buy": { "token_id": "42" } }),
+        vec![
+            ("_contract_address", &marketplace),
+            ("action", "buy"),
+            ("_contract_address", &collection),
+            ("action", "transfer_nft"),
+            ("sender", &marketplace),
+            ("recipient", &buyer),
+            ("token_id", "42"),
+        ],
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain_data),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosCw721Transfers(
+                AlertCosmosCw721Transfers {
+                    contract_addr: collection.clone(),
+                    token_id: "42".to_string(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["Cw721Transfer"];
+    assert_eq!(notification["action"], "transfer_nft");
+    assert_eq!(notification["contract_addr"], collection);
+    assert_eq!(notification["from"], marketplace);
+    assert_eq!(notification["to"], buyer);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ethermint_transfer_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("evmos").await?;
+    let (from, to) = (
+        SimulatedCosmosChain::evm_account(1),
+        SimulatedCosmosChain::evm_account(2),
+    );
+    let tx = SimulatedCosmosTx::ethereum_transfer(1, 0, to, 1_000u64.into());
+    let eth_tx = util::ethermint::decode_ethereum_tx(&tx.tx.body.as_ref().unwrap().messages[0])?;
+    chain.push_block(vec![tx]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosMonitorFunds(
+                AlertCosmosMonitorFunds {
+                    // The bech32 form of the evm recipient
+                    address: cosmrs::AccountId::new("evmos", to.as_bytes())?.to_string(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(notification["from"], format!("{:#x}", from));
+    assert_eq!(notification["to"], format!("{:#x}", to));
+    assert_eq!(notification["tx_hash"], format!("{:#x}", eth_tx.hash));
+    assert_eq!(notification["amount"][0]["amount"], "1000");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_erc20_transfer_log_of_an_ethermint_tx_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("evmos").await?;
+    let (router, token, to, other) = (
+        SimulatedCosmosChain::evm_account(2),
+        SimulatedCosmosChain::evm_account(3),
+        SimulatedCosmosChain::evm_account(4),
+        SimulatedCosmosChain::evm_account(5),
+    );
+    // The router pays the address twice and someone else in between
+    let tx = SimulatedCosmosTx::ethereum_transfer(1, 0, router, 0u64.into()).with_evm_logs(vec![
+        erc20_transfer_log(token, router, to, 750u64.into()),
+        erc20_transfer_log(token, router, other, 100u64.into()),
+        erc20_transfer_log(token, router, to, 250u64.into()),
+    ]);
+    let eth_tx = util::ethermint::decode_ethereum_tx(&tx.tx.body.as_ref().unwrap().messages[0])?;
+    chain.push_block(vec![tx]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosMonitorFunds(
+                AlertCosmosMonitorFunds {
+                    address: cosmrs::AccountId::new("evmos", to.as_bytes())?.to_string(),
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let mut transfers = vec![];
+    for _ in 0..2 {
+        let body = next_notification(&mut notifications).await;
+        let notification = &body["alert"]["notification"]["MonitorFundsToken"];
+        assert_eq!(notification["tx_hash"], format!("{:#x}", eth_tx.hash));
+        transfers.push((
+            notification["log_index"].as_u64(),
+            notification["amount"].clone(),
+        ));
+    }
+    transfers.sort_by_key(|(log_index, _)| *log_index);
+    assert_eq!(
+        transfers,
+        vec![(Some(0), json!("750")), (Some(2), json!("250"))]
+    );
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+            .await
+            .is_err(),
+        "transfer to another address was notified"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ibc_packet_reaches_webhook_of_sending_chain() -> Result<()> {
+    assert_recv_packet_notified(false).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ibc_packet_of_sdk_050_chain_reaches_webhook() -> Result<()> {
+    assert_recv_packet_notified(true).await
+}
+
+// Chains on Cosmos SDK 0.50 only report their msg logs as events, the counterparty then also
+// stands for chains registered before their network id was stored
+async fn assert_recv_packet_notified(sdk_050: bool) -> Result<()> {
+    let (chain, counterparty) = (
+        SimulatedCosmosChain::start("cosmos").await?,
+        SimulatedCosmosChain::start("osmo").await?,
+    );
+    if sdk_050 {
+        chain.use_events_only_logs();
+        counterparty.use_events_only_logs();
+    }
+    chain.open_channel("transfer", "channel-0", &counterparty);
+    counterparty.open_channel("transfer", "channel-1", &chain);
+    let packet = IbcPacket {
+        sequence: 7,
+        src_port: "transfer".to_string(),
+        src_channel: "channel-0".to_string(),
+        dst_port: "transfer".to_string(),
+        dst_channel: "channel-1".to_string(),
+        sender: chain.account(1),
+        receiver: counterparty.account(2),
+        denom: "stake".to_string(),
+        amount: "1000".to_string(),
+    };
+    chain.push_block(vec![SimulatedCosmosTx::ibc_transfer(&packet, false)]);
+    counterparty.push_block(vec![SimulatedCosmosTx::ibc_transfer(&packet, true)]);
+
+    let pipeline = Pipeline::new().await?;
+    // Only the sending chain has the alert, the receiving one is merely registered
+    let counterparty_id = create_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(counterparty.chain_data()),
+        2,
+    )
+    .await?;
+    let chain_data = match sdk_050 {
+        false => chain.chain_data(),
+        true => {
+            clear_network_id(&pipeline, &counterparty_id).await?;
+            chain.grpc_chain_data()
+        }
+    };
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain_data),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosIbcPacket(
+                AlertCosmosIbcPacket {
+                    receiver: packet.receiver.clone(),
+                    stages: vec![Stage::Recv as i32],
+                    ..Default::default()
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["IbcPacket"];
+    assert_eq!(notification["stage"], "RECV");
+    assert_eq!(notification["chain_id"], counterparty_id);
+    assert_eq!(notification["sequence"], 7);
+    assert_eq!(notification["sender"], packet.sender);
+    assert_eq!(notification["amount"], "1000");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn proposal_tally_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    chain.push_block(vec![SimulatedCosmosTx::vote(
+        &chain.account(1),
+        4,
+        VoteOption::Yes,
+    )]);
+    chain.end_voting(
+        4,
+        "proposal_passed",
+        TallyResult {
+            yes: "100".to_string(),
+            abstain: "0".to_string(),
+            no: "20".to_string(),
+            no_with_veto: "0".to_string(),
+        },
+    );
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosGovernance(
+                AlertCosmosGovernance {
+                    events: vec![GovernanceEvent::VotingEnded as i32],
+                    proposal_id: 4,
+                    ..Default::default()
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["Governance"];
+    assert_eq!(notification["event"], "VOTING_ENDED");
+    assert_eq!(notification["proposal_id"], 4);
+    assert_eq!(notification["result"], "proposal_passed");
+    assert_eq!(notification["tally"]["yes"], "100");
+    assert_eq!(notification["tally"]["no"], "20");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn vote_on_sdk_050_chain_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    chain.use_events_only_logs();
+    let voter = chain.account(1);
+    chain.push_block(vec![SimulatedCosmosTx::vote(&voter, 4, VoteOption::No)]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosGovernance(
+                AlertCosmosGovernance {
+                    events: vec![GovernanceEvent::VoteCast as i32],
+                    voter: voter.clone(),
+                    ..Default::default()
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["Governance"];
+    assert_eq!(notification["event"], "VOTE_CAST");
+    assert_eq!(notification["proposal_id"], 4);
+    assert_eq!(notification["voter"], voter);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delegation_reaches_webhook() -> Result<()> {
+    assert_delegation_notified(false).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delegation_on_sdk_050_chain_reaches_webhook() -> Result<()> {
+    assert_delegation_notified(true).await
+}
+
+// Chains on Cosmos SDK 0.50 only report their msg logs as events
+async fn assert_delegation_notified(sdk_050: bool) -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    if sdk_050 {
+        chain.use_events_only_logs();
+    }
+    let (delegator, validator) = (chain.account(1), chain.add_validator(1));
+    chain.push_block(vec![SimulatedCosmosTx::delegate(
+        &delegator, &validator, 500, "stake",
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosStaking(
+                AlertCosmosStaking {
+                    validator: validator.clone(),
+                    ..Default::default()
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["Staking"];
+    assert_eq!(notification["action"], "DELEGATE");
+    assert_eq!(notification["delegator"], delegator);
+    assert_eq!(notification["amount"], "500");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn validator_jailing_reaches_webhook() -> Result<()> {
+    let chain = SimulatedCosmosChain::start("cosmos").await?;
+    let validator = chain.add_validator(1);
+    // Another validator jailed first must not match
+    chain.add_validator(2);
+    chain.jail_validator(2);
+    chain.jail_validator(1);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::CosmosChainData(chain.chain_data()),
+        ChainAlert::CosmosAlert(CosmosAlert {
+            cosmos_alert: Some(cosmos_alert::CosmosAlert::AlertCosmosValidator(
+                AlertCosmosValidator {
+                    validator: validator.clone(),
+                    events: vec![ValidatorEvent::Jailed as i32],
+                    ..Default::default()
+                },
+            )),
+        }),
+        2,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["Validator"];
+    assert_eq!(notification["event"], "JAILED");
+    assert_eq!(notification["validator"], validator);
+    assert_eq!(notification["height"], 3);
+    assert_eq!(notification["reason"], "missing_signature");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn eth_transfer_reaches_webhook() -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    let (from, to) = (SimulatedEthChain::account(1), SimulatedEthChain::account(2));
+    let (_, tx_hashes) = chain.push_block(vec![SimulatedEthTx::transfer(
+        from,
+        to,
+        1_000_000u64.into(),
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(chain.chain_data()),
+        ChainAlert::EthAlert(EthAlert {
+            eth_alert: Some(eth_alert::EthAlert::AlertEthMonitorFunds(
+                AlertEthMonitorFunds {
+                    address: format!("{:#x}", to),
+                },
+            )),
+        }),
+        1,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(notification["from"], format!("{:#x}", from));
+    assert_eq!(notification["tx_hash"], format!("{:#x}", tx_hashes[0]));
+    assert_eq!(notification["amount"][0]["amount"], "1000000");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn eth_reorg_retracts_notification() -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    let (from, to) = (SimulatedEthChain::account(1), SimulatedEthChain::account(2));
+    let (orphaned_hash, tx_hashes) = chain.push_block(vec![SimulatedEthTx::transfer(
+        from,
+        to,
+        1_000_000u64.into(),
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(chain.chain_data()),
+        ChainAlert::EthAlert(EthAlert {
+            eth_alert: Some(eth_alert::EthAlert::AlertEthMonitorFunds(
+                AlertEthMonitorFunds {
+                    address: format!("{:#x}", to),
+                },
+            )),
+        }),
+        1,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    assert!(body["alert"]["notification"]["MonitorFundsCoin"].is_object());
+
+    // The next block builds on a replacement of the one holding the transfer
+    chain.reorg(1);
+    chain.push_block(vec![]);
+    chain.push_block(vec![]);
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["Retracted"];
+    assert_eq!(notification["tx_hash"], format!("{:#x}", tx_hashes[0]));
+    assert_eq!(notification["block_hash"], format!("{:#x}", orphaned_hash));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn backfill_is_limited_to_chain_head() -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    chain.push_block(vec![]);
+    chain.push_block(vec![]);
+
+    let pipeline = Pipeline::new().await?;
+    let chain_id = create_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(chain.chain_data()),
+        1,
+    )
+    .await?;
+
+    let crawler_service = &pipeline.svcs.crawler_service;
+    assert!(crawler_service
+        .backfill_chain(chain_id.clone(), 1, 3, None)
+        .await
+        .is_err());
+    assert!(crawler_service
+        .backfill_chain(chain_id.clone(), 1, 1_000_000, None)
+        .await
+        .is_err());
+    let failed_blocks = crawler_service.backfill_chain(chain_id, 1, 2, None).await?;
+    assert!(failed_blocks.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn eth_internal_transfer_reaches_webhook() -> Result<()> {
+    assert_internal_transfer_notified(TraceMode::DebugTraceBlock).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn eth_internal_transfer_reaches_webhook_through_trace_block() -> Result<()> {
+    assert_internal_transfer_notified(TraceMode::TraceBlock).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn eth_transfer_reaches_webhook_when_node_cannot_trace() -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    chain.disable_traces();
+    let (from, to) = (SimulatedEthChain::account(1), SimulatedEthChain::account(2));
+    let (_, tx_hashes) = chain.push_block(vec![SimulatedEthTx::transfer(
+        from,
+        to,
+        1_000_000u64.into(),
+    )]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(EthChainData {
+            trace_mode: TraceMode::DebugTraceBlock.into(),
+            ..chain.chain_data()
+        }),
+        ChainAlert::EthAlert(EthAlert {
+            eth_alert: Some(eth_alert::EthAlert::AlertEthMonitorFunds(
+                AlertEthMonitorFunds {
+                    address: format!("{:#x}", to),
+                },
+            )),
+        }),
+        1,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(notification["tx_hash"], format!("{:#x}", tx_hashes[0]));
+    assert_eq!(notification["amount"][0]["amount"], "1000000");
+
+    Ok(())
+}
+
+// The contract first makes a call that reverts, only the value of the second one moves
+async fn assert_internal_transfer_notified(trace_mode: TraceMode) -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    let (sender, contract, to) = (
+        SimulatedEthChain::account(1),
+        SimulatedEthChain::account(2),
+        SimulatedEthChain::account(3),
+    );
+    let (_, tx_hashes) = chain.push_block(vec![SimulatedEthTx {
+        internal_transfers: vec![
+            SimulatedInternalTransfer {
+                from: contract,
+                to,
+                value: 7_000u64.into(),
+                reverted: true,
+            },
+            SimulatedInternalTransfer {
+                from: contract,
+                to,
+                value: 5_000u64.into(),
+                reverted: false,
+            },
+        ],
+        ..SimulatedEthTx::transfer(sender, contract, 0u64.into())
+    }]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(EthChainData {
+            trace_mode: trace_mode.into(),
+            ..chain.chain_data()
+        }),
+        ChainAlert::EthAlert(EthAlert {
+            eth_alert: Some(eth_alert::EthAlert::AlertEthMonitorFunds(
+                AlertEthMonitorFunds {
+                    address: format!("{:#x}", to),
+                },
+            )),
+        }),
+        1,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsCoin"];
+    assert_eq!(notification["from"], format!("{:#x}", contract));
+    assert_eq!(notification["tx_hash"], format!("{:#x}", tx_hashes[0]));
+    assert_eq!(notification["amount"][0]["amount"], "5000");
+    assert_eq!(notification["internal"], true);
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+            .await
+            .is_err(),
+        "reverted call was notified"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn erc20_transfer_through_router_reaches_webhook() -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    let (sender, router, token, to) = (
+        SimulatedEthChain::account(1),
+        SimulatedEthChain::account(2),
+        SimulatedEthChain::account(3),
+        SimulatedEthChain::account(4),
+    );
+    // The sender only calls the router, which moves the tokens on its behalf
+    let (_, tx_hashes) = chain.push_block(vec![SimulatedEthTx {
+        input: vec![0x5a, 0xe4, 0x01, 0xdc].into(),
+        logs: vec![erc20_transfer_log(token, sender, to, 750u64.into())],
+        ..SimulatedEthTx::transfer(sender, router, 0u64.into())
+    }]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(chain.chain_data()),
+        ChainAlert::EthAlert(EthAlert {
+            eth_alert: Some(eth_alert::EthAlert::AlertEthMonitorFunds(
+                AlertEthMonitorFunds {
+                    address: format!("{:#x}", to),
+                },
+            )),
+        }),
+        1,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["MonitorFundsToken"];
+    assert_eq!(notification["from"], format!("{:#x}", sender));
+    assert_eq!(notification["contract_addr"], format!("{:#x}", token));
+    assert_eq!(notification["tx_hash"], format!("{:#x}", tx_hashes[0]));
+    assert_eq!(notification["amount"], "750");
+    assert_eq!(notification["log_index"], 0);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_erc20_transfer_log_of_a_tx_reaches_webhook() -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    let (sender, router, token, to, other) = (
+        SimulatedEthChain::account(1),
+        SimulatedEthChain::account(2),
+        SimulatedEthChain::account(3),
+        SimulatedEthChain::account(4),
+        SimulatedEthChain::account(5),
+    );
+    // The router pays the address twice and someone else in between
+    chain.push_block(vec![SimulatedEthTx {
+        input: vec![0x5a, 0xe4, 0x01, 0xdc].into(),
+        logs: vec![
+            erc20_transfer_log(token, router, to, 750u64.into()),
+            erc20_transfer_log(token, router, other, 100u64.into()),
+            erc20_transfer_log(token, router, to, 250u64.into()),
+        ],
+        ..SimulatedEthTx::transfer(sender, router, 0u64.into())
+    }]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(chain.chain_data()),
+        ChainAlert::EthAlert(EthAlert {
+            eth_alert: Some(eth_alert::EthAlert::AlertEthMonitorFunds(
+                AlertEthMonitorFunds {
+                    address: format!("{:#x}", to),
+                },
+            )),
+        }),
+        1,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let mut transfers = vec![];
+    for _ in 0..2 {
+        let body = next_notification(&mut notifications).await;
+        let notification = &body["alert"]["notification"]["MonitorFundsToken"];
+        transfers.push((
+            notification["log_index"].as_u64(),
+            notification["amount"].clone(),
+        ));
+    }
+    transfers.sort_by_key(|(log_index, _)| *log_index);
+    assert_eq!(
+        transfers,
+        vec![(Some(0), json!("750")), (Some(2), json!("250"))]
+    );
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), notifications.recv())
+            .await
+            .is_err(),
+        "transfer to another address was notified"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn erc1155_batch_transfer_reaches_webhook() -> Result<()> {
+    let chain = SimulatedEthChain::start().await?;
+    let (marketplace, collection, from, to) = (
+        SimulatedEthChain::account(1),
+        SimulatedEthChain::account(2),
+        SimulatedEthChain::account(3),
+        SimulatedEthChain::account(4),
+    );
+    let (_, tx_hashes) = chain.push_block(vec![SimulatedEthTx {
+        input: vec![0x2e, 0xb2, 0xc2, 0xd6].into(),
+        logs: vec![erc1155_transfer_batch_log(
+            collection,
+            marketplace,
+            from,
+            to,
+            vec![(7u64.into(), 2u64.into()), (9u64.into(), 1u64.into())],
+        )],
+        ..SimulatedEthTx::transfer(marketplace, collection, 0u64.into())
+    }]);
+
+    let pipeline = Pipeline::new().await?;
+    let mut notifications = watch_chain(
+        &pipeline.svcs,
+        chain_data::ChainData::EthChainData(chain.chain_data()),
+        ChainAlert::EthAlert(EthAlert {
+            eth_alert: Some(eth_alert::EthAlert::AlertEthNftTransfers(
+                AlertEthNftTransfers {
+                    contract_addr: format!("{:#x}", collection),
+                    address: format!("{:#x}", to),
+                    ..Default::default()
+                },
+            )),
+        }),
+        1,
+    )
+    .await?;
+    pipeline.spawn_daemons();
+
+    let body = next_notification(&mut notifications).await;
+    let notification = &body["alert"]["notification"]["NftTransfer"];
+    assert_eq!(notification["standard"], "ERC1155");
+    assert_eq!(notification["from"], format!("{:#x}", from));
+    assert_eq!(notification["operator"], format!("{:#x}", marketplace));
+    assert_eq!(notification["tx_hash"], format!("{:#x}", tx_hashes[0]));
+    assert_eq!(notification["tokens"][0]["token_id"], "7");
+    assert_eq!(notification["tokens"][0]["amount"], "2");
+    assert_eq!(notification["tokens"][1]["token_id"], "9");
+
+    Ok(())
+}
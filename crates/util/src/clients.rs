@@ -1,38 +1,320 @@
-use std::time::Duration;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use cosmrs::proto::cosmos::base::tendermint::v1beta1::{
-    service_client::ServiceClient, GetLatestBlockRequest,
+use base64::Engine;
+use cosmrs::proto::cosmos::base::{
+    abci::v1beta1::{AbciMessageLog, Attribute, StringEvent, TxResponse},
+    tendermint::v1beta1::{service_client::ServiceClient, GetLatestBlockRequest},
+};
+use cosmrs::proto::cosmos::{
+    base::query::v1beta1::PageRequest,
+    gov::v1beta1::QueryProposalRequest,
+    staking::v1beta1::{
+        query_client::QueryClient as StakingClient, QueryValidatorsRequest, Validator,
+    },
 };
 use cosmrs::proto::cosmos::{
     base::tendermint::v1beta1::service_client::ServiceClient as TendermintClient,
-    tx::v1beta1::service_client::ServiceClient as TxClient,
+    tx::v1beta1::{service_client::ServiceClient as TxClient, GetTxsEventResponse, Tx},
+};
+use cosmrs::proto::ibc::{
+    core::channel::v1::{
+        query_client::QueryClient as ChannelClient, QueryChannelClientStateRequest,
+    },
+    lightclients::tendermint::v1::ClientState,
+};
+use cosmrs::proto::tendermint::abci::{Event, EventAttribute};
+use cosmrs::proto::traits::Message;
+use cosmrs::Any;
+
+use futures::{future::BoxFuture, Future};
+use jsonrpc_core as rpc;
+use log::warn;
+use mempools_api::api::{CosmosChainData, EthChainData};
+use serde_json::Value;
+use tonic::{
+    body::BoxBody,
+    codec::ProstCodec,
+    codegen::{http, http::uri::PathAndQuery, Body, Service},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Status,
+};
+use web3::{
+    error::TransportError, helpers::build_request, transports::Http, BatchTransport, RequestId,
+    Transport, Web3,
 };
 
-use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
-use web3::{transports::Http, Web3};
+use super::{
+    endpoints::{endpoint_urls, EndpointPool, REQUEST_TIMEOUT},
+    gov::{self, TallyResult},
+    nested::{self, QueryGroupProposalRequest, QueryGroupProposalResponse},
+    service_registry::BlockEventPhase,
+    Result,
+};
 
-use super::Result;
+pub type EthClient = Web3<EthTransport>;
+
+// Per grpc endpoint, busy chains need a request per tx when blocks cannot be fetched whole
+const GRPC_REQUESTS_PER_SECOND: u64 = 50;
+const GET_TXS_EVENT: &str = "/cosmos.tx.v1beta1.Service/GetTxsEvent";
+
+/// GetTxsEventRequest as understood by every Cosmos SDK version. SDK 0.46 replaced the
+/// pagination with page and limit and SDK 0.50 replaced the events with a query
+#[derive(Clone, PartialEq, prost::Message)]
+struct GetTxsEventRequest {
+    #[prost(string, repeated, tag = "1")]
+    events: Vec<String>,
+    #[prost(message, optional, tag = "2")]
+    pagination: Option<PageRequest>,
+    #[prost(int32, tag = "3")]
+    order_by: i32,
+    #[prost(uint64, tag = "4")]
+    page: u64,
+    #[prost(uint64, tag = "5")]
+    limit: u64,
+    #[prost(string, tag = "6")]
+    query: String,
+}
 
 #[derive(Clone)]
 pub struct CosmosClient {
-    pub tendermint_client: TendermintClient<Channel>,
-    pub tx_client: TxClient<Channel>,
+    pub tendermint_client: TendermintClient<CosmosChannel>,
+    pub tx_client: TxClient<CosmosChannel>,
+    pub channel_client: ChannelClient<CosmosChannel>,
+    pub staking_client: StakingClient<CosmosChannel>,
+    channel: CosmosChannel,
 }
 
 impl CosmosClient {
-    pub async fn new(mut url: String) -> Result<Self> {
+    /// Connects to every grpc endpoint of the chain. Endpoints that are down stay in the pool and
+    /// connect once they are back up
+    pub async fn new(chain_data: &CosmosChainData) -> Result<Self> {
+        let mut channels = vec![];
+        for url in endpoint_urls(
+            &chain_data.grpc_endpoint,
+            &chain_data.fallback_grpc_endpoints,
+        ) {
+            let channel = match Self::connect(url.clone()).await {
+                Ok(channel) => channel,
+                Err(err) => {
+                    warn!("could not connect to grpc endpoint {} - {}", url, err);
+                    Self::connect_lazy(&url)?
+                }
+            };
+            channels.push((url, channel));
+        }
+
+        let channel = CosmosChannel {
+            pool: EndpointPool::new(channels)?,
+        };
+
+        Ok(CosmosClient {
+            tendermint_client: TendermintClient::new(channel.clone()),
+            tx_client: TxClient::new(channel.clone()),
+            channel_client: ChannelClient::new(channel.clone()),
+            staking_client: StakingClient::new(channel.clone()),
+            channel,
+        })
+    }
+
+    /// Chain id of the network the node belongs to
+    pub async fn network_id(&self) -> Result<String> {
+        Ok(self
+            .tendermint_client
+            .clone()
+            .get_latest_block(GetLatestBlockRequest {})
+            .await?
+            .into_inner()
+            .block
+            .ok_or("could not get block")?
+            .header
+            .ok_or("could not find block header")?
+            .chain_id)
+    }
+
+    /// Chain id of the network on the other end of an IBC channel, read from the channel's
+    /// light client
+    pub async fn channel_network_id(&self, port: &str, channel: &str) -> Result<String> {
+        let client_state = self
+            .channel_client
+            .clone()
+            .channel_client_state(QueryChannelClientStateRequest {
+                port_id: port.to_string(),
+                channel_id: channel.to_string(),
+            })
+            .await?
+            .into_inner()
+            .identified_client_state
+            .ok_or("could not find channel client state")?
+            .client_state
+            .ok_or("could not find channel client state")?;
+
+        Ok(ClientState::decode(client_state.value.as_slice())?.chain_id)
+    }
+
+    /// Final tally of a proposal as of `height`, through the v1 gov query or the v1beta1 one on
+    /// chains that predate it
+    pub async fn proposal_tally(&self, proposal_id: u64, height: u64) -> Result<TallyResult> {
+        for path in [gov::QUERY_PROPOSAL_V1, gov::QUERY_PROPOSAL_V1BETA1] {
+            let mut req = tonic::Request::new(QueryProposalRequest { proposal_id });
+            req.metadata_mut()
+                .insert("x-cosmos-block-height", height.to_string().parse()?);
+
+            let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+            grpc.ready().await?;
+            let res: std::result::Result<tonic::Response<gov::QueryProposalResponse>, Status> =
+                grpc.unary(req, PathAndQuery::from_static(path), ProstCodec::default())
+                    .await;
+            match res {
+                Ok(res) => {
+                    return res
+                        .into_inner()
+                        .proposal
+                        .and_then(|p| p.final_tally_result)
+                        .ok_or_else(|| "could not find proposal tally".into())
+                }
+                Err(status) if status.code() == tonic::Code::Unimplemented => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Err("node does not serve gov proposals".into())
+    }
+
+    /// Msgs of a group proposal as of `height`, executed proposals are pruned right away so the
+    /// height has to precede their execution
+    pub async fn group_proposal_msgs(&self, proposal_id: u64, height: u64) -> Result<Vec<Any>> {
+        let mut req = tonic::Request::new(QueryGroupProposalRequest { proposal_id });
+        req.metadata_mut()
+            .insert("x-cosmos-block-height", height.to_string().parse()?);
+
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        grpc.ready().await?;
+        let res: tonic::Response<QueryGroupProposalResponse> = grpc
+            .unary(
+                req,
+                PathAndQuery::from_static(nested::QUERY_GROUP_PROPOSAL),
+                ProstCodec::default(),
+            )
+            .await?;
+
+        Ok(res
+            .into_inner()
+            .proposal
+            .ok_or("could not find group proposal")?
+            .messages)
+    }
+
+    /// Txs included in the block at `height` along with their results, fetched `per_page` at a
+    /// time through the tx index until `tx_count` txs are found. Returns None when the node does
+    /// not serve GetTxsEvent
+    pub async fn block_txs(
+        &self,
+        height: i64,
+        tx_count: usize,
+        per_page: u64,
+    ) -> Result<Option<Vec<(Tx, TxResponse)>>> {
+        let query = format!("tx.height={}", height);
+        let mut txs = vec![];
+        for page in 1.. {
+            let req = GetTxsEventRequest {
+                events: vec![query.clone()],
+                pagination: Some(PageRequest {
+                    offset: (page - 1) * per_page,
+                    limit: per_page,
+                    ..Default::default()
+                }),
+                order_by: 0,
+                page,
+                limit: per_page,
+                query: query.clone(),
+            };
+
+            let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+            grpc.ready().await?;
+            let res: std::result::Result<tonic::Response<GetTxsEventResponse>, Status> = grpc
+                .unary(
+                    tonic::Request::new(req),
+                    PathAndQuery::from_static(GET_TXS_EVENT),
+                    ProstCodec::default(),
+                )
+                .await;
+            let res = match res {
+                Ok(res) => res.into_inner(),
+                Err(status) if status.code() == tonic::Code::Unimplemented => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            let page_len = res.txs.len();
+            txs.extend(res.txs.into_iter().zip(res.tx_responses));
+            if page_len < per_page as usize || txs.len() >= tx_count {
+                return Ok(Some(txs));
+            }
+        }
+
+        Ok(Some(txs))
+    }
+
+    /// Every validator of the chain, whatever its bond status
+    pub async fn validators(&self) -> Result<Vec<Validator>> {
+        let mut validators = vec![];
+        let mut next_key = vec![];
+        loop {
+            let res = self
+                .staking_client
+                .clone()
+                .validators(QueryValidatorsRequest {
+                    status: "".to_string(),
+                    pagination: Some(PageRequest {
+                        key: next_key,
+                        ..Default::default()
+                    }),
+                })
+                .await?
+                .into_inner();
+            validators.extend(res.validators);
+
+            next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+            if next_key.is_empty() {
+                return Ok(validators);
+            }
+        }
+    }
+
+    async fn connect(mut url: String) -> Result<Channel> {
         if let Some((_, urn)) = url.split_once("://") {
             url = urn.to_string();
         }
 
-        if let Ok(client) = Self::new_with_security(url.clone(), true).await {
-            Ok(client)
+        if let Ok(channel) = Self::connect_with_security(url.clone(), true).await {
+            Ok(channel)
         } else {
-            Self::new_with_security(url, false).await
+            Self::connect_with_security(url, false).await
         }
     }
 
-    async fn new_with_security(mut url: String, secure: bool) -> Result<Self> {
+    /// Channel to an endpoint that could not be reached, it connects on the first request after
+    /// the endpoint is back. Urls without a scheme use tls on port 443 or when no port is given
+    fn connect_lazy(url: &str) -> Result<Channel> {
+        let (url, secure) = match url.split_once("://") {
+            Some((scheme, urn)) => (urn, scheme == "https"),
+            None => (
+                url,
+                url.rsplit_once(':').is_none_or(|(_, port)| port == "443"),
+            ),
+        };
+
+        Ok(Self::endpoint(url.to_string(), secure)?.connect_lazy())
+    }
+
+    fn endpoint(mut url: String, secure: bool) -> Result<Endpoint> {
         if secure {
             url = format!("https://{}", url);
         } else {
@@ -40,7 +322,7 @@ impl CosmosClient {
         }
 
         let mut endpoint = Endpoint::new(url)?
-            .rate_limit(5, Duration::from_secs(1))
+            .rate_limit(GRPC_REQUESTS_PER_SECOND, Duration::from_secs(1))
             .timeout(Duration::from_secs(7))
             .connect_timeout(Duration::from_secs(7));
 
@@ -48,10 +330,12 @@ impl CosmosClient {
             endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
         }
 
-        let channel = endpoint.connect().await?;
-        let tendermint_client = ServiceClient::new(channel.clone());
-        tendermint_client
-            .clone()
+        Ok(endpoint)
+    }
+
+    async fn connect_with_security(url: String, secure: bool) -> Result<Channel> {
+        let channel = Self::endpoint(url, secure)?.connect().await?;
+        ServiceClient::new(channel.clone())
             .get_latest_block(GetLatestBlockRequest {})
             .await?
             .get_ref()
@@ -61,19 +345,434 @@ impl CosmosClient {
             .header
             .ok_or("could not find block header")?;
 
-        let tx_client = TxClient::new(channel.clone());
+        Ok(channel)
+    }
+}
 
-        Ok(CosmosClient {
-            tendermint_client,
-            tx_client,
+/// Grpc channel that fails over between the endpoints of a chain on transport errors
+#[derive(Clone)]
+pub struct CosmosChannel {
+    pool: EndpointPool<Channel>,
+}
+
+impl Service<http::Request<BoxBody>> for CosmosChannel {
+    type Response = http::Response<tonic::transport::Body>;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let pool = self.pool.clone();
+        Box::pin(async move {
+            // The body is buffered so the request can be replayed against another endpoint
+            let (parts, mut body) = req.into_parts();
+            let mut data = vec![];
+            while let Some(chunk) = body.data().await {
+                data.extend_from_slice(&chunk?);
+            }
+            let data = tonic::codegen::Bytes::from(data);
+
+            pool.execute(|mut channel| {
+                let mut req = http::Request::new(
+                    tonic::transport::Body::from(data.clone())
+                        .map_err(|err| Status::from_error(Box::new(err)))
+                        .boxed_unsync(),
+                );
+                *req.method_mut() = parts.method.clone();
+                *req.uri_mut() = parts.uri.clone();
+                *req.version_mut() = parts.version;
+                *req.headers_mut() = parts.headers.clone();
+
+                async move {
+                    futures::future::poll_fn(|cx| channel.poll_ready(cx)).await?;
+                    Ok(channel.call(req).await?)
+                }
+            })
+            .await
+        })
+    }
+}
+
+/// Outcome of executing a block as reported by CometBFT
+pub struct BlockResults {
+    /// Events emitted outside of txs, in execution order
+    pub events: Vec<(BlockEventPhase, StringEvent)>,
+    /// Result of every tx in block order, without the fields that depend on the tx itself
+    /// (hash, tx and timestamp)
+    pub tx_results: Vec<TxResponse>,
+}
+
+/// CometBFT rpc client, for data the cosmos grpc api does not expose
+#[derive(Clone)]
+pub struct CometRpcClient {
+    url: String,
+    http_client: reqwest::Client,
+    // CometBFT 0.34 and older base64 encode event attributes
+    base64_attributes: bool,
+}
+
+impl CometRpcClient {
+    pub async fn new(url: &str) -> Result<Self> {
+        let mut client = Self {
+            url: url.trim_end_matches('/').to_string(),
+            http_client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()?,
+            base64_attributes: false,
+        };
+
+        let status = client.get("status", &[]).await?;
+        let version = status["node_info"]["version"]
+            .as_str()
+            .ok_or("could not find node version")?;
+        client.base64_attributes = has_base64_attributes(version);
+
+        Ok(client)
+    }
+
+    async fn get(&self, method: &str, query: &[(&str, String)]) -> Result<Value> {
+        let mut res: Value = self
+            .http_client
+            .get(format!("{}/{}", self.url, method))
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(err) = res.get("error") {
+            return Err(format!("{} failed - {}", method, err).into());
+        }
+
+        Ok(res["result"].take())
+    }
+
+    pub async fn block_results(&self, height: u64) -> Result<BlockResults> {
+        let res = self
+            .get("block_results", &[("height", height.to_string())])
+            .await?;
+
+        let mut tx_results = vec![];
+        // Null instead of an empty list for blocks without txs
+        for tx_result in res["txs_results"].as_array().unwrap_or(&vec![]) {
+            tx_results.push(self.parse_tx_result(height, tx_result)?);
+        }
+
+        let mut events = vec![];
+        for (phase, key) in [
+            (BlockEventPhase::BeginBlock, "begin_block_events"),
+            (BlockEventPhase::EndBlock, "end_block_events"),
+            (BlockEventPhase::FinalizeBlock, "finalize_block_events"),
+        ] {
+            // Missing or null depending on the CometBFT version
+            if let Some(raw_events) = res[key].as_array() {
+                for event in raw_events {
+                    events.push((phase, self.parse_event(event)?));
+                }
+            }
+        }
+
+        Ok(BlockResults { events, tx_results })
+    }
+
+    fn parse_tx_result(&self, height: u64, res: &Value) -> Result<TxResponse> {
+        let raw_log = res["log"].as_str().unwrap_or_default().to_string();
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(res["data"].as_str().unwrap_or_default())?;
+
+        let mut events = vec![];
+        for event in res["events"].as_array().unwrap_or(&vec![]) {
+            let event = self.parse_event(event)?;
+            events.push(Event {
+                r#type: event.r#type,
+                attributes: event
+                    .attributes
+                    .into_iter()
+                    .map(|a| EventAttribute {
+                        key: a.key.into(),
+                        value: a.value.into(),
+                        index: true,
+                    })
+                    .collect(),
+            });
+        }
+
+        let code = res["code"].as_u64().unwrap_or_default() as u32;
+        let mut logs = parse_message_logs(&raw_log);
+        if logs.is_empty() && code == 0 {
+            logs = msg_logs_from_events(&events);
+        }
+
+        Ok(TxResponse {
+            height: height as i64,
+            codespace: res["codespace"].as_str().unwrap_or_default().to_string(),
+            code,
+            data: hex::encode_upper(data),
+            logs,
+            raw_log,
+            info: res["info"].as_str().unwrap_or_default().to_string(),
+            gas_wanted: res["gas_wanted"]
+                .as_str()
+                .unwrap_or_default()
+                .parse()
+                .unwrap_or_default(),
+            gas_used: res["gas_used"]
+                .as_str()
+                .unwrap_or_default()
+                .parse()
+                .unwrap_or_default(),
+            events,
+            ..Default::default()
+        })
+    }
+
+    /// Txs in the node's mempool, fetched `per_page` at a time until the reported total or
+    /// `max_txs` is reached. Nodes that ignore the page keep returning the first one, which
+    /// ends the listing
+    pub async fn unconfirmed_txs(&self, per_page: u64, max_txs: u64) -> Result<Vec<Vec<u8>>> {
+        let mut txs: Vec<Vec<u8>> = vec![];
+        for page in 1.. {
+            let res = self
+                .get(
+                    "unconfirmed_txs",
+                    &[("limit", per_page.to_string()), ("page", page.to_string())],
+                )
+                .await?;
+            let total: u64 = match &res["total"] {
+                Value::String(total) => total.parse()?,
+                total => total.as_u64().unwrap_or_default(),
+            };
+
+            let mut page_txs = vec![];
+            // Null instead of an empty list when the mempool is empty
+            for tx in res["txs"].as_array().unwrap_or(&vec![]) {
+                page_txs.push(
+                    base64::engine::general_purpose::STANDARD
+                        .decode(tx.as_str().ok_or("could not find tx bytes")?)?,
+                );
+            }
+            if page_txs.is_empty() || (page > 1 && page_txs.first() == txs.first()) {
+                break;
+            }
+
+            txs.extend(page_txs);
+            if txs.len() as u64 >= total.min(max_txs) {
+                break;
+            }
+        }
+
+        Ok(txs)
+    }
+
+    fn parse_event(&self, event: &Value) -> Result<StringEvent> {
+        let mut attributes = vec![];
+        for attr in event["attributes"].as_array().unwrap_or(&vec![]) {
+            attributes.push(Attribute {
+                key: self.decode_attribute(&attr["key"])?,
+                value: self.decode_attribute(&attr["value"])?,
+            });
+        }
+
+        Ok(StringEvent {
+            r#type: event["type"]
+                .as_str()
+                .ok_or("could not find event type")?
+                .to_string(),
+            attributes,
         })
     }
+
+    fn decode_attribute(&self, val: &Value) -> Result<String> {
+        let val = val.as_str().unwrap_or_default();
+        if !self.base64_attributes {
+            return Ok(val.to_string());
+        }
+
+        Ok(String::from_utf8(
+            base64::engine::general_purpose::STANDARD.decode(val)?,
+        )?)
+    }
+}
+
+// Cosmos SDK versions before 0.50 put the per msg events into the log as json, the same way
+// GetTx returns them
+fn parse_message_logs(raw_log: &str) -> Vec<AbciMessageLog> {
+    let logs: Vec<Value> = serde_json::from_str(raw_log).unwrap_or_default();
+    logs.iter()
+        .enumerate()
+        .map(|(i, log)| AbciMessageLog {
+            msg_index: log["msg_index"].as_u64().unwrap_or(i as u64) as u32,
+            log: log["log"].as_str().unwrap_or_default().to_string(),
+            events: log["events"]
+                .as_array()
+                .unwrap_or(&vec![])
+                .iter()
+                .map(|event| StringEvent {
+                    r#type: event["type"].as_str().unwrap_or_default().to_string(),
+                    attributes: event["attributes"]
+                        .as_array()
+                        .unwrap_or(&vec![])
+                        .iter()
+                        .map(|attr| Attribute {
+                            key: attr["key"].as_str().unwrap_or_default().to_string(),
+                            value: attr["value"].as_str().unwrap_or_default().to_string(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Per msg logs rebuilt from the events of a tx, for Cosmos SDK 0.50 and later which only tag
+/// the events of each msg with its `msg_index`. Events of the same type are merged into one
+/// like the logs of older versions did
+pub fn msg_logs_from_events(events: &[Event]) -> Vec<AbciMessageLog> {
+    let mut logs: Vec<AbciMessageLog> = vec![];
+    for event in events {
+        let attributes: Vec<Attribute> = event
+            .attributes
+            .iter()
+            .map(|a| Attribute {
+                key: String::from_utf8_lossy(&a.key).to_string(),
+                value: String::from_utf8_lossy(&a.value).to_string(),
+            })
+            .collect();
+        // Events without a msg index are emitted by the ante handler rather than a msg
+        let msg_index = match attributes
+            .iter()
+            .find(|a| a.key == "msg_index")
+            .and_then(|a| a.value.parse::<usize>().ok())
+        {
+            Some(msg_index) => msg_index,
+            None => continue,
+        };
+
+        while logs.len() <= msg_index {
+            logs.push(AbciMessageLog {
+                msg_index: logs.len() as u32,
+                ..Default::default()
+            });
+        }
+        let attributes = attributes.into_iter().filter(|a| a.key != "msg_index");
+        let log = &mut logs[msg_index];
+        match log.events.iter_mut().find(|e| e.r#type == event.r#type) {
+            Some(merged) => merged.attributes.extend(attributes),
+            None => log.events.push(StringEvent {
+                r#type: event.r#type.clone(),
+                attributes: attributes.collect(),
+            }),
+        }
+    }
+
+    logs
 }
 
-pub async fn new_eth_client(url: &str) -> Result<Web3<Http>> {
-    let transport = web3::transports::Http::new(url)?;
-    let client = web3::Web3::new(transport);
+fn has_base64_attributes(version: &str) -> bool {
+    let mut parts = version.trim_start_matches('v').split('.');
+    match (
+        parts.next(),
+        parts.next().and_then(|p| p.parse::<u32>().ok()),
+    ) {
+        (Some("0"), Some(minor)) => minor <= 34,
+        _ => false,
+    }
+}
+
+/// Connects to every rpc endpoint of the chain, at least one of them has to be reachable
+pub async fn new_eth_client(chain_data: &EthChainData) -> Result<EthClient> {
+    let mut transports = vec![];
+    for url in endpoint_urls(
+        &chain_data.eth_rpc_endpoint,
+        &chain_data.fallback_eth_rpc_endpoints,
+    ) {
+        transports.push((url.clone(), Http::new(&url)?));
+    }
+
+    let client = Web3::new(EthTransport {
+        pool: EndpointPool::new(transports)?,
+        id: Arc::new(AtomicUsize::new(1)),
+    });
     client.eth().block_number().await?;
 
     Ok(client)
 }
+
+/// Json rpc transport that fails over between the endpoints of a chain. Rpc errors are
+/// returned as is since the node itself answered
+#[derive(Clone, Debug)]
+pub struct EthTransport {
+    pool: EndpointPool<Http>,
+    id: Arc<AtomicUsize>,
+}
+
+impl Transport for EthTransport {
+    type Out = BoxFuture<'static, web3::error::Result<rpc::Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, Ordering::AcqRel);
+        (id, build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        Box::pin(self.clone().send_with_timeout(id, request, REQUEST_TIMEOUT))
+    }
+}
+
+impl EthTransport {
+    /// Executes a request giving each endpoint `timeout` to answer, for methods that take
+    /// longer than the usual request timeout such as block traces
+    pub async fn execute_with_timeout(
+        &self,
+        method: &str,
+        params: Vec<rpc::Value>,
+        timeout: Duration,
+    ) -> web3::error::Result<rpc::Value> {
+        let (id, request) = self.prepare(method, params);
+        self.clone().send_with_timeout(id, request, timeout).await
+    }
+
+    async fn send_with_timeout(
+        self,
+        id: RequestId,
+        request: rpc::Call,
+        timeout: Duration,
+    ) -> web3::error::Result<rpc::Value> {
+        self.pool
+            .execute_with_timeout(timeout, |http| {
+                let res = http.send(id, request.clone());
+                async move {
+                    match res.await {
+                        Err(err @ web3::Error::Rpc(_)) => Ok(Err(err)),
+                        res => Ok(Ok(res?)),
+                    }
+                }
+            })
+            .await
+            .map_err(|err| web3::Error::Transport(TransportError::Message(err.to_string())))?
+    }
+}
+
+impl BatchTransport for EthTransport {
+    type Batch = BoxFuture<'static, web3::error::Result<Vec<web3::error::Result<rpc::Value>>>>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let pool = self.pool.clone();
+        let requests: Vec<(RequestId, rpc::Call)> = requests.into_iter().collect();
+        Box::pin(async move {
+            pool.execute(|http| {
+                let res = http.send_batch(requests.clone());
+                async move { Ok(res.await?) }
+            })
+            .await
+            .map_err(|err| web3::Error::Transport(TransportError::Message(err.to_string())))
+        })
+    }
+}
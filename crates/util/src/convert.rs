@@ -66,6 +66,24 @@ impl TryConvert<i32> for Alert {
                     mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosTxOutcome(_) => {
                         AlertSource::CosmosTx
                     }
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosBlockEvents(_) => {
+                        AlertSource::CosmosBlockEvent
+                    }
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosIbcPacket(_) => {
+                        AlertSource::CosmosIbcPacket
+                    }
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosGovernance(_) => {
+                        AlertSource::CosmosGovernance
+                    }
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosStaking(_) => {
+                        AlertSource::CosmosStaking
+                    }
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosValidator(_) => {
+                        AlertSource::CosmosValidator
+                    }
+                    mempools_api::api::cosmos_alert::CosmosAlert::AlertCosmosCw721Transfers(_) => {
+                        AlertSource::CosmosMsg
+                    }
                 }
             }
             mempools_api::api::alert::ChainAlert::CosmosEvmAlert(a) => {
@@ -89,6 +107,24 @@ impl TryConvert<i32> for Alert {
                     mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosTxOutcome(_) => {
                         AlertSource::CosmosTx
                     }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosBlockEvents(_) => {
+                        AlertSource::CosmosBlockEvent
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosIbcPacket(_) => {
+                        AlertSource::CosmosIbcPacket
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosGovernance(_) => {
+                        AlertSource::CosmosGovernance
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosStaking(_) => {
+                        AlertSource::CosmosStaking
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertCosmosValidator(_) => {
+                        AlertSource::CosmosValidator
+                    }
+                    mempools_api::api::cosmos_evm_alert::CosmosEvmAlert::AlertEthNftTransfers(_) => {
+                        AlertSource::EthLog
+                    }
                 }
             }
             mempools_api::api::alert::ChainAlert::EthAlert(a) => {
@@ -106,6 +142,9 @@ impl TryConvert<i32> for Alert {
                     mempools_api::api::eth_alert::EthAlert::AlertEthTxOutcome(_) => {
                         AlertSource::EthTx
                     }
+                    mempools_api::api::eth_alert::EthAlert::AlertEthNftTransfers(_) => {
+                        AlertSource::EthLog
+                    }
                 }
             }
             mempools_api::api::alert::ChainAlert::ArchwayBroadcastAlert(_) => {
@@ -144,6 +183,7 @@ impl TryConvert<Chain> for db_entities::chain::Model {
             chain_data: Some(mempools_api::api::ChainData::decode(
                 hex::decode(chain.chain_data)?.as_slice(),
             )?),
+            crawler_status: None,
         };
         Ok(chain)
     }
@@ -0,0 +1,295 @@
+use std::{
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use super::Result;
+
+// Upper bound for a single request to one endpoint before failing over to the next
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Longest an endpoint is skipped after consecutive failures
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// Weight of the newest sample in the latency and error rate moving averages
+const EWMA_WEIGHT: f64 = 0.2;
+
+/// Interchangeable endpoints of one chain, tried healthiest first
+#[derive(Clone)]
+pub struct EndpointPool<T> {
+    endpoints: Arc<Vec<PoolEndpoint<T>>>,
+}
+
+struct PoolEndpoint<T> {
+    url: String,
+    client: T,
+    health: Mutex<EndpointHealth>,
+}
+
+#[derive(Default)]
+struct EndpointHealth {
+    // Moving average of successful request latency in milliseconds
+    latency_ms: f64,
+    // Moving average of failed requests, between 0 and 1
+    error_rate: f64,
+    consecutive_errors: u32,
+    last_error: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn backing_off(&self) -> bool {
+        match self.last_error {
+            Some(last_error) if self.consecutive_errors > 0 => {
+                let backoff = Duration::from_secs(1 << self.consecutive_errors.min(6));
+                last_error.elapsed() < backoff.min(MAX_BACKOFF)
+            }
+            _ => false,
+        }
+    }
+
+    /// Endpoints that never answered have no latency to rank them by yet
+    fn measured(&self) -> bool {
+        self.latency_ms > 0.0
+    }
+
+    /// Lower is healthier
+    fn score(&self) -> f64 {
+        self.latency_ms * (1.0 + 10.0 * self.error_rate)
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        self.latency_ms = if self.latency_ms == 0.0 {
+            latency_ms
+        } else {
+            self.latency_ms * (1.0 - EWMA_WEIGHT) + latency_ms * EWMA_WEIGHT
+        };
+        self.error_rate *= 1.0 - EWMA_WEIGHT;
+        self.consecutive_errors = 0;
+    }
+
+    fn record_error(&mut self) {
+        self.error_rate = self.error_rate * (1.0 - EWMA_WEIGHT) + EWMA_WEIGHT;
+        self.consecutive_errors += 1;
+        self.last_error = Some(Instant::now());
+    }
+}
+
+impl<T: Clone> EndpointPool<T> {
+    pub fn new(endpoints: Vec<(String, T)>) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err("no endpoints available".into());
+        }
+
+        Ok(Self {
+            endpoints: Arc::new(
+                endpoints
+                    .into_iter()
+                    .map(|(url, client)| PoolEndpoint {
+                        url,
+                        client,
+                        health: Mutex::new(EndpointHealth::default()),
+                    })
+                    .collect(),
+            ),
+        })
+    }
+
+    /// Endpoint indexes ordered from healthiest to least healthy, endpoints that never answered
+    /// come after the measured ones and endpoints that recently failed are only tried once every
+    /// other endpoint did
+    fn ranked(&self) -> Vec<usize> {
+        let mut ranked: Vec<(usize, bool, bool, f64)> = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let health = e.health.lock().unwrap();
+                (i, health.backing_off(), !health.measured(), health.score())
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.1.cmp(&b.1).then(a.2.cmp(&b.2)).then(a.3.total_cmp(&b.3)));
+
+        ranked.into_iter().map(|(i, _, _, _)| i).collect()
+    }
+
+    /// Runs `f` against the endpoints in order of health until one succeeds. Errors returned
+    /// by `f` mark the endpoint as unhealthy, errors that should not trigger a failover have
+    /// to be returned inside `R`
+    pub async fn execute<F, Fut, R>(&self, f: F) -> Result<R>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        self.execute_with_timeout(REQUEST_TIMEOUT, f).await
+    }
+
+    /// Same as `execute`, giving each endpoint `timeout` to answer requests known to be slow
+    pub async fn execute_with_timeout<F, Fut, R>(&self, timeout: Duration, f: F) -> Result<R>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        let mut last_err = None;
+        for i in self.ranked() {
+            let endpoint = &self.endpoints[i];
+            let start = Instant::now();
+            let err = match tokio::time::timeout(timeout, f(endpoint.client.clone())).await {
+                Ok(Ok(res)) => {
+                    endpoint
+                        .health
+                        .lock()
+                        .unwrap()
+                        .record_success(start.elapsed());
+                    return Ok(res);
+                }
+                Ok(Err(err)) => err,
+                Err(_) => "request timed out".into(),
+            };
+
+            warn!("request to endpoint {} failed - {}", endpoint.url, err);
+            endpoint.health.lock().unwrap().record_error();
+            last_err = Some(err);
+        }
+
+        Err(last_err.unwrap_or_else(|| "no endpoints available".into()))
+    }
+}
+
+impl<T> fmt::Debug for EndpointPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.endpoints.iter().map(|e| &e.url))
+            .finish()
+    }
+}
+
+/// Primary endpoint followed by its fallbacks, without blanks or duplicates
+pub fn endpoint_urls(primary: &str, fallbacks: &[String]) -> Vec<String> {
+    let mut urls: Vec<String> = vec![];
+    for url in std::iter::once(primary).chain(fallbacks.iter().map(|u| u.as_str())) {
+        let url = url.trim();
+        if !url.is_empty() && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(urls: &[&str]) -> EndpointPool<usize> {
+        EndpointPool::new(urls.iter().map(|u| u.to_string()).zip(0..).collect()).unwrap()
+    }
+
+    fn health(pool: &EndpointPool<usize>, i: usize) -> std::sync::MutexGuard<'_, EndpointHealth> {
+        pool.endpoints[i].health.lock().unwrap()
+    }
+
+    #[test]
+    fn ranks_faster_endpoints_first() {
+        let pool = pool(&["a", "b", "c"]);
+        health(&pool, 0).record_success(Duration::from_millis(300));
+        health(&pool, 1).record_success(Duration::from_millis(100));
+        health(&pool, 2).record_success(Duration::from_millis(200));
+
+        assert_eq!(pool.ranked(), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn ranks_backing_off_endpoints_last() {
+        let pool = pool(&["a", "b"]);
+        health(&pool, 0).record_success(Duration::from_millis(10));
+        health(&pool, 1).record_success(Duration::from_millis(500));
+        health(&pool, 0).record_error();
+
+        assert_eq!(pool.ranked(), vec![1, 0]);
+    }
+
+    #[test]
+    fn ranks_unmeasured_endpoints_after_measured() {
+        let pool = pool(&["a", "b", "c"]);
+        health(&pool, 1).record_success(Duration::from_millis(500));
+        // An endpoint that only ever failed has no latency but must not look the fastest
+        {
+            let mut health = health(&pool, 0);
+            health.record_error();
+            health.last_error = Some(Instant::now() - MAX_BACKOFF);
+        }
+
+        assert_eq!(pool.ranked(), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn error_rate_outweighs_latency() {
+        let pool = pool(&["a", "b"]);
+        health(&pool, 0).record_success(Duration::from_millis(100));
+        health(&pool, 1).record_success(Duration::from_millis(150));
+        // An old error no longer backs the endpoint off but still counts against it
+        {
+            let mut health = health(&pool, 0);
+            health.record_error();
+            health.last_error = Some(Instant::now() - MAX_BACKOFF);
+        }
+
+        assert_eq!(pool.ranked(), vec![1, 0]);
+    }
+
+    #[test]
+    fn backoff_doubles_per_consecutive_error() {
+        let mut health = EndpointHealth::default();
+        assert!(!health.backing_off());
+
+        health.record_error();
+        health.last_error = Some(Instant::now() - Duration::from_secs(3));
+        assert!(!health.backing_off());
+
+        health.record_error();
+        health.last_error = Some(Instant::now() - Duration::from_secs(3));
+        assert!(health.backing_off());
+
+        health.record_success(Duration::from_millis(10));
+        assert!(!health.backing_off());
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let mut health = EndpointHealth::default();
+        for _ in 0..20 {
+            health.record_error();
+        }
+        health.last_error = Some(Instant::now() - MAX_BACKOFF);
+
+        assert!(!health.backing_off());
+    }
+
+    #[tokio::test]
+    async fn execute_fails_over_to_next_endpoint() {
+        let pool = pool(&["a", "b"]);
+        let res = pool
+            .execute(|i| async move {
+                match i {
+                    0 => Err("endpoint is down".into()),
+                    i => Ok(i),
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res, 1);
+        assert_eq!(pool.ranked(), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn execute_returns_last_error_when_every_endpoint_fails() {
+        let pool = pool(&["a", "b"]);
+        let res: Result<()> = pool.execute(|_| async { Err("down".into()) }).await;
+
+        assert_eq!(res.unwrap_err().to_string(), "down");
+    }
+}
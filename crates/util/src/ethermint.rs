@@ -0,0 +1,587 @@
+use base64::Engine;
+use cosmrs::{
+    proto::{
+        cosmos::base::abci::v1beta1::{AbciMessageLog, Attribute, StringEvent, TxResponse},
+        tendermint::abci::Event,
+    },
+    Any,
+};
+use prost::Message;
+use rlp::RlpStream;
+use serde::Deserialize;
+use web3::{
+    signing::{keccak256, recover},
+    types::{Bytes, Log, Transaction, TransactionReceipt, H160, H256, U256},
+};
+
+use super::Result;
+
+pub const MSG_ETHEREUM_TX: &str = "/ethermint.evm.v1.MsgEthereumTx";
+pub const LEGACY_TX: &str = "/ethermint.evm.v1.LegacyTx";
+pub const ACCESS_LIST_TX: &str = "/ethermint.evm.v1.AccessListTx";
+pub const DYNAMIC_FEE_TX: &str = "/ethermint.evm.v1.DynamicFeeTx";
+
+// Emitted for every evm tx, marked failed when the evm reverts even though the cosmos tx succeeds
+const ETHEREUM_TX_EVENT: &str = "ethereum_tx";
+const ETHEREUM_TX_HASH_ATTR: &str = "ethereumTxHash";
+const ETHEREUM_TX_FAILED_ATTR: &str = "ethereumTxFailed";
+const ETHEREUM_TX_GAS_USED_ATTR: &str = "txGasUsed";
+// Emitted along with it, with one attribute holding each log of the evm tx as json
+const TX_LOG_EVENT: &str = "tx_log";
+const TX_LOG_ATTR: &str = "txLog";
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgEthereumTx {
+    #[prost(message, optional, tag = "1")]
+    pub data: Option<Any>,
+    #[prost(double, tag = "2")]
+    pub size: f64,
+    #[prost(string, tag = "3")]
+    pub hash: String,
+    #[prost(string, tag = "4")]
+    pub from: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct LegacyTx {
+    #[prost(uint64, tag = "1")]
+    pub nonce: u64,
+    #[prost(string, tag = "2")]
+    pub gas_price: String,
+    #[prost(uint64, tag = "3")]
+    pub gas: u64,
+    #[prost(string, tag = "4")]
+    pub to: String,
+    #[prost(string, tag = "5")]
+    pub value: String,
+    #[prost(bytes = "vec", tag = "6")]
+    pub data: Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub v: Vec<u8>,
+    #[prost(bytes = "vec", tag = "8")]
+    pub r: Vec<u8>,
+    #[prost(bytes = "vec", tag = "9")]
+    pub s: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AccessListTx {
+    #[prost(string, tag = "1")]
+    pub chain_id: String,
+    #[prost(uint64, tag = "2")]
+    pub nonce: u64,
+    #[prost(string, tag = "3")]
+    pub gas_price: String,
+    #[prost(uint64, tag = "4")]
+    pub gas: u64,
+    #[prost(string, tag = "5")]
+    pub to: String,
+    #[prost(string, tag = "6")]
+    pub value: String,
+    #[prost(bytes = "vec", tag = "7")]
+    pub data: Vec<u8>,
+    #[prost(message, repeated, tag = "8")]
+    pub accesses: Vec<AccessTuple>,
+    #[prost(bytes = "vec", tag = "9")]
+    pub v: Vec<u8>,
+    #[prost(bytes = "vec", tag = "10")]
+    pub r: Vec<u8>,
+    #[prost(bytes = "vec", tag = "11")]
+    pub s: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct DynamicFeeTx {
+    #[prost(string, tag = "1")]
+    pub chain_id: String,
+    #[prost(uint64, tag = "2")]
+    pub nonce: u64,
+    #[prost(string, tag = "3")]
+    pub gas_tip_cap: String,
+    #[prost(string, tag = "4")]
+    pub gas_fee_cap: String,
+    #[prost(uint64, tag = "5")]
+    pub gas: u64,
+    #[prost(string, tag = "6")]
+    pub to: String,
+    #[prost(string, tag = "7")]
+    pub value: String,
+    #[prost(bytes = "vec", tag = "8")]
+    pub data: Vec<u8>,
+    #[prost(message, repeated, tag = "9")]
+    pub accesses: Vec<AccessTuple>,
+    #[prost(bytes = "vec", tag = "10")]
+    pub v: Vec<u8>,
+    #[prost(bytes = "vec", tag = "11")]
+    pub r: Vec<u8>,
+    #[prost(bytes = "vec", tag = "12")]
+    pub s: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AccessTuple {
+    #[prost(string, tag = "1")]
+    pub address: String,
+    #[prost(string, repeated, tag = "2")]
+    pub storage_keys: Vec<String>,
+}
+
+/// The evm tx wrapped by a `MsgEthereumTx`, with its sender recovered from the signature
+pub fn decode_ethereum_tx(msg: &Any) -> Result<Transaction> {
+    if msg.type_url != MSG_ETHEREUM_TX {
+        return Err("not an ethereum tx msg".into());
+    }
+    let msg = MsgEthereumTx::decode(msg.value.as_slice())?;
+    let data = EthTxData::from_any(&msg.data.ok_or("could not find eth tx data")?)?;
+
+    let hash = match msg.hash.is_empty() {
+        true => data.hash(),
+        false => msg.hash.parse()?,
+    };
+    let (gas_price, max_priority_fee_per_gas, max_fee_per_gas) = match data.tx_type {
+        DYNAMIC_FEE_TX_TYPE => (None, Some(data.gas_tip_cap), Some(data.gas_price)),
+        _ => (Some(data.gas_price), None, None),
+    };
+
+    Ok(Transaction {
+        hash,
+        nonce: data.nonce.into(),
+        from: Some(data.sender()?),
+        to: data.to,
+        value: data.value,
+        gas_price,
+        gas: data.gas.into(),
+        input: Bytes(data.data.clone()),
+        v: Some(data.v.into()),
+        r: Some(data.r),
+        s: Some(data.s),
+        transaction_type: Some(data.tx_type.into()),
+        max_priority_fee_per_gas,
+        max_fee_per_gas,
+        ..Default::default()
+    })
+}
+
+/// Hash the signature of the evm tx in `data` is made over. Legacy txs are replay protected
+/// when their `v` is set to the chain id based value before signing
+pub fn signing_hash(data: &Any) -> Result<H256> {
+    Ok(EthTxData::from_any(data)?.signing_hash())
+}
+
+/// Hash of the signed evm tx in `data`, which is the tx hash on the evm side
+pub fn tx_hash(data: &Any) -> Result<H256> {
+    Ok(EthTxData::from_any(data)?.hash())
+}
+
+/// Receipt of an executed evm tx, built from the events of its msg as the cosmos side does not
+/// return evm receipts. `tx_events` are the events of the whole cosmos tx, used when the msg log
+/// is missing
+pub fn ethereum_tx_receipt(
+    tx: &Transaction,
+    msg_log: Option<&AbciMessageLog>,
+    tx_events: &[Event],
+) -> TransactionReceipt {
+    let events = ethereum_tx_events(tx.hash, msg_log, tx_events);
+    let event = events.iter().find(|e| e.r#type == ETHEREUM_TX_EVENT);
+    let attr = |key: &str| {
+        event.and_then(|e| {
+            e.attributes
+                .iter()
+                .find(|a| a.key == key)
+                .map(|a| a.value.clone())
+        })
+    };
+    let failed = attr(ETHEREUM_TX_FAILED_ATTR).is_some();
+
+    TransactionReceipt {
+        transaction_hash: tx.hash,
+        from: tx.from.unwrap_or_default(),
+        to: tx.to,
+        gas_used: attr(ETHEREUM_TX_GAS_USED_ATTR).and_then(|g| U256::from_dec_str(&g).ok()),
+        status: Some(if failed { 0 } else { 1 }.into()),
+        transaction_type: tx.transaction_type,
+        logs: tx_logs(tx, &events),
+        ..Default::default()
+    }
+}
+
+// Chains on Cosmos SDK 0.50 and later have no msg logs, the events of the evm tx are then picked
+// out of the events of the cosmos tx by its hash
+fn ethereum_tx_events(
+    tx_hash: H256,
+    msg_log: Option<&AbciMessageLog>,
+    tx_events: &[Event],
+) -> Vec<StringEvent> {
+    if let Some(msg_log) = msg_log.filter(|l| !l.events.is_empty()) {
+        return msg_log.events.clone();
+    }
+
+    let hash = format!("{:#x}", tx_hash);
+    tx_events
+        .iter()
+        .filter_map(|event| {
+            let attributes = event.attributes.iter().map(|a| Attribute {
+                key: String::from_utf8_lossy(&a.key).to_string(),
+                value: String::from_utf8_lossy(&a.value).to_string(),
+            });
+            let attributes: Vec<Attribute> = match event.r#type.as_str() {
+                ETHEREUM_TX_EVENT => {
+                    let attributes: Vec<Attribute> = attributes.collect();
+                    let matched = attributes.iter().any(|a| {
+                        a.key == ETHEREUM_TX_HASH_ATTR && a.value.eq_ignore_ascii_case(&hash)
+                    });
+                    if !matched {
+                        return None;
+                    }
+                    attributes
+                }
+                TX_LOG_EVENT => attributes
+                    .filter(|a| {
+                        a.key == TX_LOG_ATTR
+                            && serde_json::from_str::<TxLog>(&a.value)
+                                .map(|log| log.tx_hash == Some(tx_hash))
+                                .unwrap_or_default()
+                    })
+                    .collect(),
+                _ => return None,
+            };
+
+            (!attributes.is_empty()).then(|| StringEvent {
+                r#type: event.r#type.clone(),
+                attributes,
+            })
+        })
+        .collect()
+}
+
+// Log of an evm tx as Ethermint emits it, bytes are base64 encoded the way go encodes them
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TxLog {
+    address: H160,
+    #[serde(default)]
+    topics: Vec<H256>,
+    #[serde(default)]
+    data: Option<String>,
+    #[serde(default)]
+    log_index: u64,
+    #[serde(default, rename = "transactionHash")]
+    tx_hash: Option<H256>,
+}
+
+// Logs that fail to decode are left out of the receipt
+fn tx_logs(tx: &Transaction, events: &[StringEvent]) -> Vec<Log> {
+    events
+        .iter()
+        .filter(|e| e.r#type == TX_LOG_EVENT)
+        .flat_map(|e| &e.attributes)
+        .filter(|a| a.key == TX_LOG_ATTR)
+        .filter_map(|a| serde_json::from_str::<TxLog>(&a.value).ok())
+        .filter_map(|log| {
+            let data = match log.data {
+                Some(data) => base64::prelude::BASE64_STANDARD.decode(data).ok()?,
+                None => vec![],
+            };
+            Some(Log {
+                address: log.address,
+                topics: log.topics,
+                data: Bytes(data),
+                block_hash: None,
+                block_number: None,
+                transaction_hash: Some(tx.hash),
+                transaction_index: None,
+                log_index: Some(log.log_index.into()),
+                transaction_log_index: None,
+                log_type: None,
+                removed: Some(false),
+            })
+        })
+        .collect()
+}
+
+/// Hex form of a bech32 address, Ethermint accounts have the same bytes in both formats
+pub fn evm_address(address: &str) -> String {
+    match address.parse::<cosmrs::AccountId>() {
+        Ok(account) => format!("0x{}", hex::encode(account.to_bytes())),
+        Err(_) => address.to_ascii_lowercase(),
+    }
+}
+
+/// Whether an evm tx in the cosmos tx reverted, the cosmos tx itself still succeeds then. The
+/// events of the tx are checked too as chains on Cosmos SDK 0.50 and later have no msg logs
+pub fn ethereum_tx_failed(tx_resp: &TxResponse) -> bool {
+    let failed_in_logs = tx_resp.logs.iter().flat_map(|l| &l.events).any(|e| {
+        e.r#type == ETHEREUM_TX_EVENT
+            && e.attributes
+                .iter()
+                .any(|a| a.key == ETHEREUM_TX_FAILED_ATTR)
+    });
+
+    failed_in_logs
+        || tx_resp.events.iter().any(|e| {
+            e.r#type == ETHEREUM_TX_EVENT
+                && e.attributes
+                    .iter()
+                    .any(|a| a.key.as_ref() == ETHEREUM_TX_FAILED_ATTR.as_bytes())
+        })
+}
+
+const LEGACY_TX_TYPE: u64 = 0;
+const ACCESS_LIST_TX_TYPE: u64 = 1;
+const DYNAMIC_FEE_TX_TYPE: u64 = 2;
+
+/// Fields of the evm tx types Ethermint wraps. `gas_price` is the fee cap of dynamic fee txs
+struct EthTxData {
+    tx_type: u64,
+    chain_id: Option<u64>,
+    nonce: u64,
+    gas_tip_cap: U256,
+    gas_price: U256,
+    gas: u64,
+    to: Option<H160>,
+    value: U256,
+    data: Vec<u8>,
+    accesses: Vec<AccessTuple>,
+    v: u64,
+    r: U256,
+    s: U256,
+}
+
+impl EthTxData {
+    fn from_any(data: &Any) -> Result<Self> {
+        match data.type_url.as_str() {
+            LEGACY_TX => {
+                let tx = LegacyTx::decode(data.value.as_slice())?;
+                let v = parse_u64_bytes(&tx.v)?;
+                Ok(Self {
+                    tx_type: LEGACY_TX_TYPE,
+                    // Pre EIP-155 txs sign with a v of 27 or 28 and no chain id
+                    chain_id: (v >= 35).then(|| (v - 35) / 2),
+                    nonce: tx.nonce,
+                    gas_tip_cap: U256::zero(),
+                    gas_price: parse_int(&tx.gas_price)?,
+                    gas: tx.gas,
+                    to: parse_to(&tx.to)?,
+                    value: parse_int(&tx.value)?,
+                    data: tx.data,
+                    accesses: vec![],
+                    v,
+                    r: U256::from_big_endian(&tx.r),
+                    s: U256::from_big_endian(&tx.s),
+                })
+            }
+            ACCESS_LIST_TX => {
+                let tx = AccessListTx::decode(data.value.as_slice())?;
+                Ok(Self {
+                    tx_type: ACCESS_LIST_TX_TYPE,
+                    chain_id: Some(tx.chain_id.parse()?),
+                    nonce: tx.nonce,
+                    gas_tip_cap: U256::zero(),
+                    gas_price: parse_int(&tx.gas_price)?,
+                    gas: tx.gas,
+                    to: parse_to(&tx.to)?,
+                    value: parse_int(&tx.value)?,
+                    data: tx.data,
+                    accesses: tx.accesses,
+                    v: parse_u64_bytes(&tx.v)?,
+                    r: U256::from_big_endian(&tx.r),
+                    s: U256::from_big_endian(&tx.s),
+                })
+            }
+            DYNAMIC_FEE_TX => {
+                let tx = DynamicFeeTx::decode(data.value.as_slice())?;
+                Ok(Self {
+                    tx_type: DYNAMIC_FEE_TX_TYPE,
+                    chain_id: Some(tx.chain_id.parse()?),
+                    nonce: tx.nonce,
+                    gas_tip_cap: parse_int(&tx.gas_tip_cap)?,
+                    gas_price: parse_int(&tx.gas_fee_cap)?,
+                    gas: tx.gas,
+                    to: parse_to(&tx.to)?,
+                    value: parse_int(&tx.value)?,
+                    data: tx.data,
+                    accesses: tx.accesses,
+                    v: parse_u64_bytes(&tx.v)?,
+                    r: U256::from_big_endian(&tx.r),
+                    s: U256::from_big_endian(&tx.s),
+                })
+            }
+            type_url => Err(format!("unknown eth tx type {}", type_url).into()),
+        }
+    }
+
+    fn sender(&self) -> Result<H160> {
+        let recovery_id = match (self.tx_type, self.chain_id) {
+            (LEGACY_TX_TYPE, Some(chain_id)) => self.v.checked_sub(35 + chain_id * 2),
+            (LEGACY_TX_TYPE, None) => self.v.checked_sub(27),
+            _ => Some(self.v),
+        }
+        .ok_or("invalid eth tx signature")?;
+
+        let mut signature = [0u8; 64];
+        self.r.to_big_endian(&mut signature[..32]);
+        self.s.to_big_endian(&mut signature[32..]);
+
+        Ok(recover(
+            self.signing_hash().as_bytes(),
+            &signature,
+            recovery_id as i32,
+        )?)
+    }
+
+    fn signing_hash(&self) -> H256 {
+        H256(keccak256(&self.encode(false)))
+    }
+
+    fn hash(&self) -> H256 {
+        H256(keccak256(&self.encode(true)))
+    }
+
+    // Typed txs are prefixed with their type, legacy txs are plain rlp lists
+    fn encode(&self, signed: bool) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+        stream.begin_unbounded_list();
+        match self.tx_type {
+            LEGACY_TX_TYPE => {
+                self.append_common(&mut stream);
+                if signed {
+                    self.append_signature(&mut stream);
+                } else if let Some(chain_id) = self.chain_id {
+                    stream.append(&chain_id).append(&0u8).append(&0u8);
+                }
+            }
+            _ => {
+                stream.append(&self.chain_id.unwrap_or_default());
+                self.append_common(&mut stream);
+                stream.begin_list(self.accesses.len());
+                for access in &self.accesses {
+                    let address: H160 = access.address.parse().unwrap_or_default();
+                    let keys: Vec<H256> = access
+                        .storage_keys
+                        .iter()
+                        .map(|k| k.parse().unwrap_or_default())
+                        .collect();
+                    stream.begin_list(2).append(&address).append_list(&keys);
+                }
+                if signed {
+                    self.append_signature(&mut stream);
+                }
+            }
+        }
+        stream.finalize_unbounded_list();
+
+        let mut encoded = vec![];
+        if self.tx_type != LEGACY_TX_TYPE {
+            encoded.push(self.tx_type as u8);
+        }
+        encoded.extend_from_slice(&stream.out());
+
+        encoded
+    }
+
+    fn append_common(&self, stream: &mut RlpStream) {
+        stream.append(&self.nonce);
+        if self.tx_type == DYNAMIC_FEE_TX_TYPE {
+            stream.append(&self.gas_tip_cap);
+        }
+        stream.append(&self.gas_price).append(&self.gas);
+        match &self.to {
+            Some(to) => stream.append(to),
+            None => stream.append_empty_data(),
+        };
+        stream.append(&self.value).append(&self.data);
+    }
+
+    fn append_signature(&self, stream: &mut RlpStream) {
+        stream.append(&self.v).append(&self.r).append(&self.s);
+    }
+}
+
+// Ethermint stores amounts as decimal sdk ints, empty for zero
+fn parse_int(val: &str) -> Result<U256> {
+    match val.is_empty() {
+        true => Ok(U256::zero()),
+        false => Ok(U256::from_dec_str(val)?),
+    }
+}
+
+fn parse_to(val: &str) -> Result<Option<H160>> {
+    match val.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(val.parse()?)),
+    }
+}
+
+fn parse_u64_bytes(val: &[u8]) -> Result<u64> {
+    if val.len() > 8 {
+        return Err("eth tx signature v out of range".into());
+    }
+    Ok(val.iter().fold(0, |acc, b| (acc << 8) | *b as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Signed tx from the EIP-155 spec
+    const EIP155_SIGNED_TX: &str = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    const SENDER: &str = "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+
+    fn eip155_msg() -> Result<Any> {
+        let data = Any {
+            type_url: LEGACY_TX.to_string(),
+            value: LegacyTx {
+                nonce: 9,
+                gas_price: "20000000000".to_string(),
+                gas: 21_000,
+                to: "0x3535353535353535353535353535353535353535".to_string(),
+                value: "1000000000000000000".to_string(),
+                data: vec![],
+                v: vec![37],
+                r: hex::decode("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276")?,
+                s: hex::decode("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83")?,
+            }
+            .encode_to_vec(),
+        };
+
+        Ok(Any {
+            type_url: MSG_ETHEREUM_TX.to_string(),
+            value: MsgEthereumTx {
+                data: Some(data),
+                ..Default::default()
+            }
+            .encode_to_vec(),
+        })
+    }
+
+    #[test]
+    fn decodes_ethermint_tx() -> Result<()> {
+        let tx = decode_ethereum_tx(&eip155_msg()?)?;
+
+        assert_eq!(
+            format!("{:x}", tx.from.ok_or("could not find sender")?),
+            SENDER
+        );
+        assert_eq!(tx.hash, H256(keccak256(&hex::decode(EIP155_SIGNED_TX)?)));
+        assert_eq!(tx.value, 10u64.pow(18).into());
+
+        let cosmos_tx = cosmrs::Tx {
+            body: cosmrs::tx::Body::new(vec![eip155_msg()?], "", 0u32),
+            auth_info: cosmrs::tx::AuthInfo {
+                signer_infos: vec![],
+                fee: cosmrs::tx::Fee::from_amount_and_gas(
+                    cosmrs::Coin::new(0u8.into(), "aevmos")?,
+                    21_000u64,
+                ),
+            },
+            signatures: vec![],
+        };
+        let signers = crate::get_signers_from_tx("evmos".to_string(), cosmos_tx);
+        assert!(signers.contains(&format!("0x{}", SENDER)));
+        let account = cosmrs::AccountId::new("evmos", &hex::decode(SENDER)?)?;
+        assert!(signers.contains(&account.to_string()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+use cosmrs::{
+    proto::cosmos::{
+        base::v1beta1::Coin,
+        gov::v1beta1::{self, VoteOption},
+    },
+    Any,
+};
+use prost::Message;
+
+use super::{legacy_dec, Result};
+
+pub const MSG_SUBMIT_PROPOSAL_V1BETA1: &str = "/cosmos.gov.v1beta1.MsgSubmitProposal";
+pub const MSG_SUBMIT_PROPOSAL_V1: &str = "/cosmos.gov.v1.MsgSubmitProposal";
+pub const MSG_VOTE_V1BETA1: &str = "/cosmos.gov.v1beta1.MsgVote";
+pub const MSG_VOTE_V1: &str = "/cosmos.gov.v1.MsgVote";
+pub const MSG_VOTE_WEIGHTED_V1BETA1: &str = "/cosmos.gov.v1beta1.MsgVoteWeighted";
+pub const MSG_VOTE_WEIGHTED_V1: &str = "/cosmos.gov.v1.MsgVoteWeighted";
+pub const MSG_EXEC_LEGACY_CONTENT_V1: &str = "/cosmos.gov.v1.MsgExecLegacyContent";
+
+pub const QUERY_PROPOSAL_V1: &str = "/cosmos.gov.v1.Query/Proposal";
+pub const QUERY_PROPOSAL_V1BETA1: &str = "/cosmos.gov.v1beta1.Query/Proposal";
+
+// cosmos-sdk-proto only ships the v1beta1 gov module, the v1 msgs are declared here
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgSubmitProposal {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: Vec<Any>,
+    #[prost(message, repeated, tag = "2")]
+    pub initial_deposit: Vec<Coin>,
+    #[prost(string, tag = "3")]
+    pub proposer: String,
+    #[prost(string, tag = "4")]
+    pub metadata: String,
+    // Added in Cosmos SDK 0.47
+    #[prost(string, tag = "5")]
+    pub title: String,
+    #[prost(string, tag = "6")]
+    pub summary: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgExecLegacyContent {
+    #[prost(message, optional, tag = "1")]
+    pub content: Option<Any>,
+    #[prost(string, tag = "2")]
+    pub authority: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgVote {
+    #[prost(uint64, tag = "1")]
+    pub proposal_id: u64,
+    #[prost(string, tag = "2")]
+    pub voter: String,
+    #[prost(enumeration = "VoteOption", tag = "3")]
+    pub option: i32,
+    #[prost(string, tag = "4")]
+    pub metadata: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgVoteWeighted {
+    #[prost(uint64, tag = "1")]
+    pub proposal_id: u64,
+    #[prost(string, tag = "2")]
+    pub voter: String,
+    #[prost(message, repeated, tag = "3")]
+    pub options: Vec<WeightedVoteOption>,
+    #[prost(string, tag = "4")]
+    pub metadata: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct WeightedVoteOption {
+    #[prost(enumeration = "VoteOption", tag = "1")]
+    pub option: i32,
+    #[prost(string, tag = "2")]
+    pub weight: String,
+}
+
+// Every legacy proposal content starts with its title
+#[derive(Clone, PartialEq, Message)]
+struct LegacyContent {
+    #[prost(string, tag = "1")]
+    title: String,
+}
+
+/// Response of the Proposal query of either gov version, which agree on the tally's position
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryProposalResponse {
+    #[prost(message, optional, tag = "1")]
+    pub proposal: Option<Proposal>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Proposal {
+    #[prost(message, optional, tag = "4")]
+    pub final_tally_result: Option<TallyResult>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TallyResult {
+    #[prost(string, tag = "1")]
+    pub yes: String,
+    #[prost(string, tag = "2")]
+    pub abstain: String,
+    #[prost(string, tag = "3")]
+    pub no: String,
+    #[prost(string, tag = "4")]
+    pub no_with_veto: String,
+}
+
+/// Proposal as submitted, its id is only known from the msg's events
+pub struct SubmittedProposal {
+    pub title: String,
+    pub proposer: String,
+}
+
+/// Vote cast by a msg, with the weight given to each option
+pub struct CastVote {
+    pub proposal_id: u64,
+    pub voter: String,
+    pub options: Vec<(VoteOption, String)>,
+}
+
+/// Proposal submitted by `msg`, None when it is not a MsgSubmitProposal
+pub fn decode_submit_proposal(msg: &Any) -> Result<Option<SubmittedProposal>> {
+    let proposal = match msg.type_url.as_str() {
+        MSG_SUBMIT_PROPOSAL_V1BETA1 => {
+            let msg = v1beta1::MsgSubmitProposal::decode(msg.value.as_slice())?;
+            SubmittedProposal {
+                title: legacy_title(msg.content.as_ref())?,
+                proposer: msg.proposer,
+            }
+        }
+        MSG_SUBMIT_PROPOSAL_V1 => {
+            let msg = MsgSubmitProposal::decode(msg.value.as_slice())?;
+            let mut title = msg.title;
+            // Titles of proposals before 0.47 only live in the legacy content they wrap
+            if title.is_empty() {
+                if let Some(exec) = msg
+                    .messages
+                    .iter()
+                    .find(|m| m.type_url == MSG_EXEC_LEGACY_CONTENT_V1)
+                {
+                    let exec = MsgExecLegacyContent::decode(exec.value.as_slice())?;
+                    title = legacy_title(exec.content.as_ref())?;
+                }
+            }
+            SubmittedProposal {
+                title,
+                proposer: msg.proposer,
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(proposal))
+}
+
+fn legacy_title(content: Option<&Any>) -> Result<String> {
+    match content {
+        Some(content) => Ok(LegacyContent::decode(content.value.as_slice())?.title),
+        None => Ok("".to_string()),
+    }
+}
+
+/// Vote cast by `msg`, None when it is not a vote
+pub fn decode_vote(msg: &Any) -> Result<Option<CastVote>> {
+    let vote = match msg.type_url.as_str() {
+        MSG_VOTE_V1BETA1 => {
+            let msg = v1beta1::MsgVote::decode(msg.value.as_slice())?;
+            CastVote {
+                proposal_id: msg.proposal_id,
+                voter: msg.voter,
+                options: vec![(vote_option(msg.option), "1".to_string())],
+            }
+        }
+        MSG_VOTE_V1 => {
+            let msg = MsgVote::decode(msg.value.as_slice())?;
+            CastVote {
+                proposal_id: msg.proposal_id,
+                voter: msg.voter,
+                options: vec![(vote_option(msg.option), "1".to_string())],
+            }
+        }
+        MSG_VOTE_WEIGHTED_V1BETA1 => {
+            let msg = v1beta1::MsgVoteWeighted::decode(msg.value.as_slice())?;
+            CastVote {
+                proposal_id: msg.proposal_id,
+                voter: msg.voter,
+                options: msg
+                    .options
+                    .into_iter()
+                    .map(|o| (vote_option(o.option), legacy_dec(&o.weight)))
+                    .collect(),
+            }
+        }
+        MSG_VOTE_WEIGHTED_V1 => {
+            let msg = MsgVoteWeighted::decode(msg.value.as_slice())?;
+            CastVote {
+                proposal_id: msg.proposal_id,
+                voter: msg.voter,
+                options: msg
+                    .options
+                    .into_iter()
+                    .map(|o| (vote_option(o.option), o.weight))
+                    .collect(),
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(vote))
+}
+
+fn vote_option(option: i32) -> VoteOption {
+    VoteOption::from_i32(option).unwrap_or(VoteOption::Unspecified)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmrs::proto::cosmos::gov::v1beta1::{MsgVoteWeighted, WeightedVoteOption};
+
+    use super::*;
+
+    #[test]
+    fn decodes_weighted_vote() -> Result<()> {
+        // Legacy decimals go over the wire scaled by 10^18
+        let msg = Any {
+            type_url: MSG_VOTE_WEIGHTED_V1BETA1.to_string(),
+            value: MsgVoteWeighted {
+                proposal_id: 12,
+                voter: "cosmos1voter".to_string(),
+                options: vec![
+                    WeightedVoteOption {
+                        option: VoteOption::Yes as i32,
+                        weight: "750000000000000000".to_string(),
+                    },
+                    WeightedVoteOption {
+                        option: VoteOption::NoWithVeto as i32,
+                        weight: "250000000000000000".to_string(),
+                    },
+                ],
+            }
+            .encode_to_vec(),
+        };
+
+        let vote = decode_vote(&msg)?.ok_or("could not find vote")?;
+        assert_eq!(vote.proposal_id, 12);
+        assert_eq!(vote.voter, "cosmos1voter");
+        assert_eq!(
+            vote.options,
+            vec![
+                (VoteOption::Yes, "0.75".to_string()),
+                (VoteOption::NoWithVeto, "0.25".to_string())
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, u8};
+use std::error::Error;
 
 use cosmrs::bip32::secp256k1::sha2::Sha256;
 use cosmrs::{bip32::secp256k1::sha2::Digest, tx::SignerInfo};
@@ -9,7 +9,13 @@ use tonic::Status;
 use web3::types::{H160, H256};
 pub mod clients;
 pub mod convert;
+pub mod endpoints;
+pub mod ethermint;
+pub mod gov;
+pub mod nested;
+pub mod pipeline;
 pub mod service_registry;
+pub mod wasm;
 
 // pub const NOTIFIER_URL: &str = "https://firebase-notifier-eww3betigq-ue.a.run.app";
 
@@ -29,10 +35,12 @@ pub type Result<T> = core::result::Result<T, Box<dyn std::error::Error + Send +
 // pub type Result<T> = anyhow::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 pub trait ToGrpcResult<T> {
+    #[allow(clippy::result_large_err)]
     fn to_grpc_result(self) -> core::result::Result<T, Status>;
 }
 
 impl<T, E: Into<Box<dyn Error + Send + Sync>>> ToGrpcResult<T> for core::result::Result<T, E> {
+    #[allow(clippy::result_large_err)]
     fn to_grpc_result(self) -> core::result::Result<T, Status> {
         self.map_err(|err| {
             let err: Box<dyn Error> = err.into();
@@ -78,6 +86,23 @@ pub fn get_sha256_hash(data: &[u8]) -> String {
     hex::encode(hasher.finalize())
 }
 
+// Legacy sdk.Dec values go over the wire as integers scaled by 10^18
+const LEGACY_DEC_PRECISION: usize = 18;
+
+/// Decimal form of a legacy sdk.Dec as encoded in msgs, e.g. 500000000000000000 becomes 0.5
+pub fn legacy_dec(val: &str) -> String {
+    if val.contains('.') || val.is_empty() {
+        return val.to_string();
+    }
+
+    let padded = format!("{:0>width$}", val, width = LEGACY_DEC_PRECISION + 1);
+    let (int, frac) = padded.split_at(padded.len() - LEGACY_DEC_PRECISION);
+    match frac.trim_end_matches('0') {
+        "" => int.to_string(),
+        frac => format!("{}.{}", int, frac),
+    }
+}
+
 pub trait HashString {
     fn hash_string(&self) -> Result<String>;
 }
@@ -96,6 +121,18 @@ impl HashString for H256 {
 
 pub fn get_signers_from_tx(chain_prefix: String, tx: cosmrs::Tx) -> Vec<String> {
     let mut all_signers = vec![];
+    // Evm txs are signed inside their msgs, their senders match in both address formats
+    for msg in &tx.body.messages {
+        if let Some(from) = ethermint::decode_ethereum_tx(msg)
+            .ok()
+            .and_then(|tx| tx.from)
+        {
+            if let Ok(account) = cosmrs::AccountId::new(&chain_prefix, from.as_bytes()) {
+                all_signers.push(account.to_string());
+            }
+            all_signers.push(format!("{:#x}", from));
+        }
+    }
     for info in tx.auth_info.signer_infos {
         if let Ok(signers) = signer_info_to_account_id(info, chain_prefix.clone()) {
             signers
@@ -0,0 +1,161 @@
+use cosmrs::{
+    proto::cosmos::{authz::v1beta1::MsgExec, base::abci::v1beta1::AbciMessageLog},
+    Any,
+};
+use prost::Message;
+
+use super::Result;
+
+pub const MSG_AUTHZ_EXEC: &str = "/cosmos.authz.v1beta1.MsgExec";
+pub const MSG_GROUP_SUBMIT_PROPOSAL: &str = "/cosmos.group.v1.MsgSubmitProposal";
+pub const MSG_GROUP_VOTE: &str = "/cosmos.group.v1.MsgVote";
+pub const MSG_GROUP_EXEC: &str = "/cosmos.group.v1.MsgExec";
+pub const MSG_ICA_SEND_TX: &str = "/ibc.applications.interchain_accounts.controller.v1.MsgSendTx";
+pub const MSG_INTERTX_SUBMIT_TX: &str = "/intertx.MsgSubmitTx";
+
+pub const QUERY_GROUP_PROPOSAL: &str = "/cosmos.group.v1.Query/Proposal";
+
+const EVENT_GROUP_EXEC: &str = "cosmos.group.v1.EventExec";
+const GROUP_EXEC_SUCCESS: &str = "PROPOSAL_EXECUTOR_RESULT_SUCCESS";
+
+// cosmos-sdk-proto 0.16 ships neither x/group nor interchain accounts, their msgs are declared here
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgGroupSubmitProposal {
+    #[prost(string, tag = "1")]
+    pub group_policy_address: String,
+    #[prost(string, repeated, tag = "2")]
+    pub proposers: Vec<String>,
+    #[prost(string, tag = "3")]
+    pub metadata: String,
+    #[prost(message, repeated, tag = "4")]
+    pub messages: Vec<Any>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryGroupProposalRequest {
+    #[prost(uint64, tag = "1")]
+    pub proposal_id: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryGroupProposalResponse {
+    #[prost(message, optional, tag = "1")]
+    pub proposal: Option<GroupProposal>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GroupProposal {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    #[prost(message, repeated, tag = "12")]
+    pub messages: Vec<Any>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgSendTx {
+    #[prost(string, tag = "1")]
+    pub owner: String,
+    #[prost(string, tag = "2")]
+    pub connection_id: String,
+    #[prost(message, optional, tag = "3")]
+    pub packet_data: Option<InterchainAccountPacketData>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct InterchainAccountPacketData {
+    #[prost(int32, tag = "1")]
+    pub r#type: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+    #[prost(string, tag = "3")]
+    pub memo: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct CosmosTx {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: Vec<Any>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgSubmitTx {
+    #[prost(string, tag = "1")]
+    pub owner: String,
+    #[prost(string, tag = "2")]
+    pub connection_id: String,
+    #[prost(message, optional, tag = "3")]
+    pub msg: Option<Any>,
+}
+
+/// Msgs a wrapper msg executes
+pub enum WrappedMsgs {
+    Msgs(Vec<Any>),
+    /// Msgs of an executed group proposal, which only live in the proposal
+    GroupProposal(u64),
+    /// Msgs an interchain account executes on its host chain rather than on this chain
+    Remote(Vec<Any>),
+}
+
+/// Msgs wrapped by `msg`, None when it is not a wrapper or its msgs were not executed. Group
+/// proposals are only known to be executed once the tx is included, through `msg_log`
+pub fn unwrap_msg(msg: &Any, msg_log: Option<&AbciMessageLog>) -> Result<Option<WrappedMsgs>> {
+    let wrapped = match msg.type_url.as_str() {
+        MSG_AUTHZ_EXEC => WrappedMsgs::Msgs(MsgExec::decode(msg.value.as_slice())?.msgs),
+        MSG_GROUP_SUBMIT_PROPOSAL => match group_executed_proposal(msg_log)? {
+            Some(_) => {
+                WrappedMsgs::Msgs(MsgGroupSubmitProposal::decode(msg.value.as_slice())?.messages)
+            }
+            None => return Ok(None),
+        },
+        // Votes can execute the proposal they complete as well
+        MSG_GROUP_VOTE | MSG_GROUP_EXEC => match group_executed_proposal(msg_log)? {
+            Some(proposal_id) => WrappedMsgs::GroupProposal(proposal_id),
+            None => return Ok(None),
+        },
+        MSG_ICA_SEND_TX => {
+            let packet_data = MsgSendTx::decode(msg.value.as_slice())?
+                .packet_data
+                .ok_or("could not find ica packet data")?;
+            WrappedMsgs::Remote(CosmosTx::decode(packet_data.data.as_slice())?.messages)
+        }
+        MSG_INTERTX_SUBMIT_TX => WrappedMsgs::Remote(
+            MsgSubmitTx::decode(msg.value.as_slice())?
+                .msg
+                .into_iter()
+                .collect(),
+        ),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(wrapped))
+}
+
+// Id of the group proposal a msg executed successfully, attribute values of typed events are
+// json encoded
+fn group_executed_proposal(msg_log: Option<&AbciMessageLog>) -> Result<Option<u64>> {
+    let event = match msg_log
+        .iter()
+        .flat_map(|l| &l.events)
+        .find(|e| e.r#type == EVENT_GROUP_EXEC)
+    {
+        Some(event) => event,
+        None => return Ok(None),
+    };
+    let attr = |key: &str| {
+        event
+            .attributes
+            .iter()
+            .find(|a| a.key == key)
+            .map(|a| a.value.trim_matches('"'))
+    };
+    if attr("result") != Some(GROUP_EXEC_SUCCESS) {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        attr("proposal_id")
+            .ok_or("could not find executed proposal id")?
+            .parse()?,
+    ))
+}
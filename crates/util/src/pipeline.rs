@@ -0,0 +1,148 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Mutex, Notify};
+
+use super::Result;
+
+/// Concurrency limits for the crawler -> filter -> notification pipeline
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PipelineConfig {
+    /// Blocks fetched concurrently per chain crawler
+    pub block_workers: usize,
+    /// Txs fetched concurrently per chain crawler
+    pub tx_workers: usize,
+    pub filter_workers: usize,
+    pub filter_queue_size: usize,
+    pub notification_workers: usize,
+    pub notification_queue_size: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            block_workers: 10,
+            tx_workers: 50,
+            filter_workers: 50,
+            filter_queue_size: 1000,
+            notification_workers: 20,
+            notification_queue_size: 1000,
+        }
+    }
+}
+
+/// Bounded queue drained by a fixed number of workers, `push` waits while the queue is full
+pub struct WorkQueue<T> {
+    sender: mpsc::Sender<T>,
+    receiver: Arc<Mutex<mpsc::Receiver<T>>>,
+}
+
+impl<T> Clone for WorkQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+        }
+    }
+}
+
+impl<T: Send + 'static> WorkQueue<T> {
+    pub fn new(size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(size.max(1));
+        Self {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    pub async fn push(&self, item: T) -> Result<()> {
+        self.sender
+            .send(item)
+            .await
+            .map_err(|_| "work queue is closed")?;
+
+        Ok(())
+    }
+
+    pub fn spawn_workers<F, Fut>(&self, workers: usize, handler: F)
+    where
+        F: Fn(T) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        for _ in 0..workers.max(1) {
+            let receiver = self.receiver.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                loop {
+                    let item = receiver.lock().await.recv().await;
+                    match item {
+                        Some(item) => handler(item).await,
+                        None => break,
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+struct TrackerState {
+    pending: AtomicUsize,
+    idle: Notify,
+}
+
+/// Counts the items a crawler handed to the pipeline that are still waiting to be filtered or
+/// notified, so its cursor only moves past blocks whose notifications went out
+#[derive(Clone, Default)]
+pub struct WorkTracker(Arc<TrackerState>);
+
+impl WorkTracker {
+    pub fn token(&self) -> WorkToken {
+        self.0.pending.fetch_add(1, Ordering::SeqCst);
+        WorkToken(Some(self.0.clone()))
+    }
+
+    /// Waits until every token handed out so far has been dropped
+    pub async fn wait_idle(&self) {
+        loop {
+            let idle = self.0.idle.notified();
+            if self.0.pending.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+/// Travels with a queued item and marks it done once dropped, the default token is not tracked
+#[derive(Default)]
+pub struct WorkToken(Option<Arc<TrackerState>>);
+
+impl Clone for WorkToken {
+    fn clone(&self) -> Self {
+        match &self.0 {
+            Some(state) => {
+                state.pending.fetch_add(1, Ordering::SeqCst);
+                Self(Some(state.clone()))
+            }
+            None => Self(None),
+        }
+    }
+}
+
+impl Drop for WorkToken {
+    fn drop(&mut self) {
+        if let Some(state) = &self.0 {
+            if state.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                state.idle.notify_waiters();
+            }
+        }
+    }
+}
@@ -1,22 +1,32 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use cosmrs::{
-    proto::cosmos::{
-        base::abci::v1beta1::{AbciMessageLog, TxResponse},
-        tx::v1beta1::Tx,
+    proto::{
+        cosmos::{
+            base::{
+                abci::v1beta1::{AbciMessageLog, StringEvent, TxResponse},
+                v1beta1::Coin,
+            },
+            tx::v1beta1::Tx,
+        },
+        tendermint::abci::Event,
     },
     Any,
 };
 use dyn_clone::DynClone;
 use mempools_api::api::{
+    alert_cosmos_governance::Event as GovernanceEvent,
+    alert_cosmos_ibc_packet::Stage as IbcPacketStage,
+    alert_cosmos_staking::Action as StakingAction, alert_cosmos_validator::Event as ValidatorEvent,
     alert_notification_data::AlertNotificationData, AlertSource, BackendUserAlert, CosmosChainData,
-    CreateAlertRequest, CreateChainRequest, CreateChainResponse, EthChainData, GetChainsResponse,
-    TokenMetadata, UpdateChainRequest, UpdateChainResponse,
+    CrawlerCursor, CrawlerStatus, CreateAlertRequest, CreateChainRequest, CreateChainResponse,
+    EthChainData, FailedBlock, GetChainsResponse, TokenMetadata, UpdateChainRequest,
+    UpdateChainResponse,
 };
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use super::Result;
+use super::{ethermint, gov::TallyResult, pipeline::WorkToken, wasm, HashString, Result};
 
 #[derive(Clone)]
 pub struct RegistryServices {
@@ -25,15 +35,22 @@ pub struct RegistryServices {
     pub auth_service: Box<dyn AuthServiceInterface>,
     pub notification_service: Box<dyn NotificationServiceInterface>,
     pub chain_service: Box<dyn ChainServiceInterface>,
+    pub crawler_service: Box<dyn CrawlerServiceInterface>,
 }
 
 #[tonic::async_trait]
 pub trait FilterServiceInterface: DynClone + Send + Sync + 'static {
-    async fn process_alert_source(&self, alert_source: ProcessAlertSourceRequeust) -> Result<()>;
+    /// `work` is held until every notification produced from the alert source is sent
+    async fn process_alert_source(
+        &self,
+        alert_source: ProcessAlertSourceRequeust,
+        work: WorkToken,
+    ) -> Result<()>;
     async fn process_alert_for_alert_source(
         &self,
         alert_source: ProcessAlertSourceRequeust,
         alert: BackendUserAlert,
+        work: WorkToken,
     ) -> Result<()>;
 }
 
@@ -66,6 +83,26 @@ pub trait ChainServiceInterface: DynClone + Send + Sync + 'static {
     async fn update_chain(&self, req: &UpdateChainRequest) -> Result<UpdateChainResponse>;
 }
 
+#[tonic::async_trait]
+pub trait CrawlerServiceInterface: DynClone + Send + Sync + 'static {
+    /// Replays a block range through the crawler, leaving the live cursor as is. Returns the
+    /// blocks that failed
+    async fn backfill_chain(
+        &self,
+        chain_id: String,
+        from_height: u64,
+        to_height: u64,
+        alert_id: Option<String>,
+    ) -> Result<Vec<FailedBlock>>;
+    /// Status of the crawler of every chain, whichever replica runs it
+    async fn get_crawler_statuses(&self) -> Result<Vec<CrawlerStatus>>;
+    /// Paused crawlers keep their cursor and continue from it once resumed
+    async fn set_crawler_paused(&self, chain_id: String, paused: bool) -> Result<()>;
+    /// Moves the cursor once the chain is crawled, right away for running crawlers and on
+    /// resume for paused ones
+    async fn set_crawler_cursor(&self, chain_id: String, cursor: CrawlerCursor) -> Result<()>;
+}
+
 #[tonic::async_trait]
 pub trait NotificationServiceInterface: DynClone + Send + Sync + 'static {
     async fn get_notifications(
@@ -77,12 +114,20 @@ pub trait NotificationServiceInterface: DynClone + Send + Sync + 'static {
         &self,
         notification: Notification,
         alert_owner_jwt: String,
+        work: WorkToken,
     ) -> Result<()>;
     async fn get_statistics(
         &self,
         alert_id: Option<i32>,
         user_id: Option<String>,
     ) -> Result<NotificationStatistics>;
+    /// Sends a retraction for every notification produced from the given txs of an orphaned block
+    async fn retract_notifications(
+        &self,
+        chain_id: String,
+        tx_hashes: Vec<String>,
+        block_hash: String,
+    ) -> Result<()>;
 }
 
 dyn_clone::clone_trait_object!(FilterServiceInterface);
@@ -90,6 +135,7 @@ dyn_clone::clone_trait_object!(AlertServiceInterface);
 dyn_clone::clone_trait_object!(AuthServiceInterface);
 dyn_clone::clone_trait_object!(NotificationServiceInterface);
 dyn_clone::clone_trait_object!(ChainServiceInterface);
+dyn_clone::clone_trait_object!(CrawlerServiceInterface);
 
 #[derive(Clone, Default)]
 pub struct ServiceRegistry(Arc<RwLock<Option<RegistryServices>>>);
@@ -150,6 +196,11 @@ pub struct AlertNotification {
     pub notification: AlertNotificationData,
     pub alert_id: String,
     pub alert_source_id: String,
+    #[serde(default)]
+    pub pending: bool,
+    // Path of the nested msg that matched, see `AlertSourceCosmosMsg::path`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub msg_path: Vec<u64>,
 }
 
 pub struct ResponseTime {
@@ -161,8 +212,15 @@ pub struct ResponseTime {
 pub enum ProcessAlertSourceRequeust {
     CosmosTx(Box<AlertSourceCosmosTx>),
     CosmosMsg(Box<AlertSourceCosmosMsg>),
+    CosmosMsgEvent(Box<AlertSourceCosmosMsgEvent>),
+    CosmosBlockEvent(Box<AlertSourceCosmosBlockEvent>),
+    CosmosIbcPacket(Box<AlertSourceCosmosIbcPacket>),
+    CosmosGovernance(Box<AlertSourceCosmosGovernance>),
+    CosmosStaking(Box<AlertSourceCosmosStaking>),
+    CosmosValidator(Box<AlertSourceCosmosValidator>),
     EthLog(Box<AlertSourceEthLog>),
     EthTx(Box<AlertSourceEthTx>),
+    EthInternalTransfer(Box<AlertSourceEthInternalTransfer>),
     ArchwaysBroadcast {
         chain_id: String,
         message: String,
@@ -175,7 +233,8 @@ pub struct AlertSourceCosmosTx {
     pub chain_id: String,
     pub chain_data: CosmosChainData,
     pub tx: Tx,
-    pub tx_resp: TxResponse,
+    // Missing while the tx is pending
+    pub tx_resp: Option<TxResponse>,
     pub tx_hash: String,
 }
 #[derive(Clone)]
@@ -184,8 +243,220 @@ pub struct AlertSourceCosmosMsg {
     pub chain_data: CosmosChainData,
     pub msg_log: Option<AbciMessageLog>,
     pub msg_index: u64,
+    // Indexes of the msg within each wrapper msg it is nested in, empty for msgs of the tx itself
+    pub path: Vec<u64>,
+    // Nested msg run by an interchain account on its host chain, not on this chain
+    pub remote: bool,
     pub msg: Any,
     pub tx_hash: String,
+    pub pending: bool,
+    // Evm tx wrapped by an Ethermint `MsgEthereumTx`, with its sender recovered
+    pub eth_tx: Option<web3::types::Transaction>,
+    // Events of the whole tx, only kept along with `eth_tx` to build its receipt without a msg log
+    pub tx_events: Vec<Event>,
+}
+
+impl AlertSourceCosmosMsg {
+    /// The wrapped evm tx as the eth crawler sees it, so evm filters can run on the msg
+    pub fn eth_tx_source(&self) -> Result<AlertSourceEthTx> {
+        let tx = self.eth_tx.clone().ok_or("msg does not wrap an eth tx")?;
+        let tx_resp = match self.pending {
+            true => None,
+            false => Some(ethermint::ethereum_tx_receipt(
+                &tx,
+                self.msg_log.as_ref(),
+                &self.tx_events,
+            )),
+        };
+
+        Ok(AlertSourceEthTx {
+            chain_id: self.chain_id.clone(),
+            chain_data: EthChainData::default(),
+            tx_hash: tx.hash.hash_string()?,
+            tx,
+            tx_resp,
+        })
+    }
+
+    /// Sources for the token movements of the included msg, one per log of its evm tx or per
+    /// contract execution in its msg log
+    pub fn event_sources(&self) -> Vec<AlertSourceCosmosMsgEvent> {
+        let events = match (self.eth_tx_source(), &self.msg_log) {
+            (Ok(eth_tx), _) => eth_tx.tx_resp.map(|r| r.logs.len()).unwrap_or_default(),
+            (Err(_), Some(msg_log)) => wasm::wasm_events(msg_log).len(),
+            (Err(_), None) => 0,
+        };
+
+        (0..events as u64)
+            .map(|event_index| AlertSourceCosmosMsgEvent {
+                msg: self.clone(),
+                event_index,
+            })
+            .collect()
+    }
+}
+
+/// Log emitted by the evm tx of an included msg or contract execution run by it, notified on its
+/// own as the eth crawler does for logs
+#[derive(Clone)]
+pub struct AlertSourceCosmosMsgEvent {
+    pub msg: AlertSourceCosmosMsg,
+    pub event_index: u64,
+}
+
+impl AlertSourceCosmosMsgEvent {
+    /// The log as the eth crawler sees it, so evm filters can run on it
+    pub fn eth_log_source(&self) -> Result<AlertSourceEthLog> {
+        let tx = self.msg.eth_tx_source()?;
+        let log = tx
+            .tx_resp
+            .ok_or("pending msgs have no logs")?
+            .logs
+            .get(self.event_index as usize)
+            .cloned()
+            .ok_or("could not find log at index")?;
+
+        Ok(AlertSourceEthLog {
+            chain_id: tx.chain_id,
+            chain_data: tx.chain_data,
+            tx_hash: tx.tx_hash,
+            log,
+            log_index: self.event_index,
+        })
+    }
+
+    /// Attributes of the contract execution
+    pub fn wasm_event(&self) -> Result<HashMap<&str, &str>> {
+        let msg_log = self.msg.msg_log.as_ref().ok_or("could not find msg log")?;
+        Ok(wasm::wasm_events(msg_log)
+            .into_iter()
+            .nth(self.event_index as usize)
+            .ok_or("could not find wasm event at index")?)
+    }
+}
+
+/// Event emitted by a block outside of any tx
+#[derive(Clone)]
+pub struct AlertSourceCosmosBlockEvent {
+    pub chain_id: String,
+    pub chain_data: CosmosChainData,
+    pub height: u64,
+    pub phase: BlockEventPhase,
+    pub event_index: u64,
+    pub event: StringEvent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockEventPhase {
+    BeginBlock,
+    EndBlock,
+    FinalizeBlock,
+}
+
+impl BlockEventPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockEventPhase::BeginBlock => "begin_block",
+            BlockEventPhase::EndBlock => "end_block",
+            BlockEventPhase::FinalizeBlock => "finalize_block",
+        }
+    }
+}
+
+/// Stage of an ICS-20 packet's lifecycle, on the chain the stage happened on
+#[derive(Clone)]
+pub struct AlertSourceCosmosIbcPacket {
+    pub chain_id: String,
+    pub chain_data: CosmosChainData,
+    pub tx_hash: String,
+    pub msg_index: u64,
+    pub stage: IbcPacketStage,
+    pub packet: IbcPacket,
+    // Set when the receiving chain failed to process the packet
+    pub error: Option<String>,
+    // Chain on the other end of the packet, when it is registered
+    pub counterparty_chain_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct IbcPacket {
+    pub sequence: u64,
+    pub src_port: String,
+    pub src_channel: String,
+    pub dst_port: String,
+    pub dst_channel: String,
+    pub sender: String,
+    pub receiver: String,
+    pub denom: String,
+    pub amount: String,
+}
+
+/// Step of a governance proposal, taken by a msg or by the end of one of the proposal's periods
+#[derive(Clone)]
+pub struct AlertSourceCosmosGovernance {
+    pub chain_id: String,
+    pub chain_data: CosmosChainData,
+    pub height: u64,
+    // Missing when a period ended, which happens outside of any tx
+    pub tx_hash: Option<String>,
+    // Index of the msg in the tx, or of the event in the block
+    pub event_index: u64,
+    pub event: GovernanceEvent,
+    pub update: GovernanceUpdate,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GovernanceUpdate {
+    pub proposal_id: u64,
+    pub title: String,
+    pub proposer: String,
+    pub voter: String,
+    // Name and weight of each option of a vote
+    pub options: Vec<(String, String)>,
+    // Outcome of a period that ended, e.g. proposal_passed
+    pub result: String,
+    pub tally: Option<TallyResult>,
+}
+
+/// Delegation moved by a staking msg
+#[derive(Clone)]
+pub struct AlertSourceCosmosStaking {
+    pub chain_id: String,
+    pub chain_data: CosmosChainData,
+    pub tx_hash: String,
+    pub msg_index: u64,
+    pub action: StakingAction,
+    pub delegator: String,
+    pub validator: String,
+    // Validator a redelegation moved to
+    pub validator_dst: Option<String>,
+    pub amount: Option<Coin>,
+}
+
+/// Change to a validator, made by its operator's msgs or by the chain itself
+#[derive(Clone)]
+pub struct AlertSourceCosmosValidator {
+    pub chain_id: String,
+    pub chain_data: CosmosChainData,
+    pub height: u64,
+    // Missing for jailing, slashing and missed blocks, which happen outside of any tx
+    pub tx_hash: Option<String>,
+    // Index of the msg in the tx, or of the event in the block
+    pub event_index: u64,
+    pub event: ValidatorEvent,
+    pub update: ValidatorUpdate,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidatorUpdate {
+    // Operator address
+    pub validator: String,
+    pub moniker: String,
+    pub commission_rate: String,
+    pub slash_reason: String,
+    pub power: String,
+    pub burned_coins: String,
+    pub missed_blocks: u64,
 }
 
 #[derive(Clone)]
@@ -203,7 +474,21 @@ pub struct AlertSourceEthTx {
     pub chain_data: EthChainData,
     pub tx_hash: String,
     pub tx: web3::types::Transaction,
-    pub tx_resp: web3::types::TransactionReceipt,
+    // Missing while the tx is pending
+    pub tx_resp: Option<web3::types::TransactionReceipt>,
+}
+
+/// Native value sent by a contract while executing a tx, found through the tx's trace
+#[derive(Clone)]
+pub struct AlertSourceEthInternalTransfer {
+    pub chain_id: String,
+    pub chain_data: EthChainData,
+    pub tx_hash: String,
+    pub from: web3::types::H160,
+    pub to: web3::types::H160,
+    pub value: web3::types::U256,
+    // Position of the call among the value transferring calls of the tx
+    pub transfer_index: u64,
 }
 
 #[derive(Clone)]
@@ -211,6 +496,7 @@ pub struct AlertSourceContext {
     pub id: String,
     pub chain_id: String,
     pub source_type: AlertSource,
+    pub pending: bool,
 }
 
 impl ProcessAlertSourceRequeust {
@@ -220,26 +506,101 @@ impl ProcessAlertSourceRequeust {
                 id: tx.tx_hash,
                 chain_id: tx.chain_id,
                 source_type: AlertSource::CosmosTx,
+                pending: tx.tx_resp.is_none(),
             },
+            // Evm txs share the id the eth crawler gives them on cosmos evm chains
             ProcessAlertSourceRequeust::CosmosMsg(msg) => AlertSourceContext {
-                id: msg.tx_hash,
+                id: match &msg.eth_tx {
+                    Some(eth_tx) => format!("{:#x}", eth_tx.hash),
+                    None => msg.tx_hash,
+                },
                 chain_id: msg.chain_id,
                 source_type: AlertSource::CosmosMsg,
+                pending: msg.pending,
+            },
+            // Contract executions of a msg are told apart by their index
+            ProcessAlertSourceRequeust::CosmosMsgEvent(event) => AlertSourceContext {
+                id: match &event.msg.eth_tx {
+                    Some(eth_tx) => format!("{:#x}", eth_tx.hash),
+                    None => format!(
+                        "{}/{}/{}",
+                        event.msg.tx_hash, event.msg.msg_index, event.event_index
+                    ),
+                },
+                chain_id: event.msg.chain_id,
+                source_type: AlertSource::CosmosMsg,
+                pending: false,
+            },
+            ProcessAlertSourceRequeust::CosmosBlockEvent(event) => AlertSourceContext {
+                id: format!(
+                    "{}/{}/{}",
+                    event.height,
+                    event.phase.as_str(),
+                    event.event_index
+                ),
+                chain_id: event.chain_id,
+                source_type: AlertSource::CosmosBlockEvent,
+                pending: false,
+            },
+            ProcessAlertSourceRequeust::CosmosIbcPacket(packet) => AlertSourceContext {
+                id: packet.tx_hash,
+                chain_id: packet.chain_id,
+                source_type: AlertSource::CosmosIbcPacket,
+                pending: false,
+            },
+            ProcessAlertSourceRequeust::CosmosGovernance(gov) => AlertSourceContext {
+                id: match gov.tx_hash {
+                    Some(tx_hash) => tx_hash,
+                    None => format!("{}/gov/{}", gov.height, gov.event_index),
+                },
+                chain_id: gov.chain_id,
+                source_type: AlertSource::CosmosGovernance,
+                pending: false,
+            },
+            ProcessAlertSourceRequeust::CosmosStaking(staking) => AlertSourceContext {
+                id: staking.tx_hash,
+                chain_id: staking.chain_id,
+                source_type: AlertSource::CosmosStaking,
+                pending: false,
+            },
+            ProcessAlertSourceRequeust::CosmosValidator(validator) => AlertSourceContext {
+                id: match validator.tx_hash {
+                    Some(tx_hash) => tx_hash,
+                    None => format!(
+                        "{}/validator/{}/{}",
+                        validator.height,
+                        validator.event_index,
+                        validator.event.as_str_name()
+                    ),
+                },
+                chain_id: validator.chain_id,
+                source_type: AlertSource::CosmosValidator,
+                pending: false,
             },
             ProcessAlertSourceRequeust::EthLog(log) => AlertSourceContext {
                 id: log.tx_hash,
                 chain_id: log.chain_id,
                 source_type: AlertSource::EthLog,
+                pending: false,
             },
             ProcessAlertSourceRequeust::EthTx(tx) => AlertSourceContext {
                 id: tx.tx_hash,
                 chain_id: tx.chain_id,
                 source_type: AlertSource::EthTx,
+                pending: tx.tx_resp.is_none(),
+            },
+            // Matched against the same alerts as the tx itself, and retracted along with it
+            ProcessAlertSourceRequeust::EthInternalTransfer(transfer) => AlertSourceContext {
+                id: transfer.tx_hash,
+                chain_id: transfer.chain_id,
+                source_type: AlertSource::EthTx,
+                pending: false,
             },
             ProcessAlertSourceRequeust::ArchwaysBroadcast { chain_id, .. } => AlertSourceContext {
                 id: "".into(),
                 chain_id,
                 source_type: AlertSource::ArchwaysBroadcast,
+                pending: false,
             },
         }
     }
@@ -252,6 +613,14 @@ impl ProcessAlertSourceRequeust {
         }
     }
 
+    pub fn get_cosmos_msg_event(&self) -> Result<AlertSourceCosmosMsgEvent> {
+        if let Self::CosmosMsgEvent(event) = self {
+            Ok(*event.clone())
+        } else {
+            Err("alert source mistmatch".into())
+        }
+    }
+
     pub fn get_cosmos_tx(&self) -> Result<AlertSourceCosmosTx> {
         if let Self::CosmosTx(tx) = self {
             Ok(*tx.clone())
@@ -260,6 +629,85 @@ impl ProcessAlertSourceRequeust {
         }
     }
 
+    pub fn get_cosmos_block_event(&self) -> Result<AlertSourceCosmosBlockEvent> {
+        if let Self::CosmosBlockEvent(event) = self {
+            Ok(*event.clone())
+        } else {
+            Err("alert source mistmatch".into())
+        }
+    }
+
+    pub fn get_cosmos_ibc_packet(&self) -> Result<AlertSourceCosmosIbcPacket> {
+        if let Self::CosmosIbcPacket(packet) = self {
+            Ok(*packet.clone())
+        } else {
+            Err("alert source mistmatch".into())
+        }
+    }
+
+    pub fn get_cosmos_governance(&self) -> Result<AlertSourceCosmosGovernance> {
+        if let Self::CosmosGovernance(gov) = self {
+            Ok(*gov.clone())
+        } else {
+            Err("alert source mistmatch".into())
+        }
+    }
+
+    pub fn get_cosmos_staking(&self) -> Result<AlertSourceCosmosStaking> {
+        if let Self::CosmosStaking(staking) = self {
+            Ok(*staking.clone())
+        } else {
+            Err("alert source mistmatch".into())
+        }
+    }
+
+    pub fn get_cosmos_validator(&self) -> Result<AlertSourceCosmosValidator> {
+        if let Self::CosmosValidator(validator) = self {
+            Ok(*validator.clone())
+        } else {
+            Err("alert source mistmatch".into())
+        }
+    }
+
+    /// Chains whose alerts the source is matched against, IBC packets concern both of their
+    /// chains
+    pub fn alert_chain_ids(&self) -> Vec<String> {
+        // The host chain of an interchain account is not known here
+        if let Self::CosmosMsg(msg) = self {
+            if msg.remote {
+                return vec![];
+            }
+        }
+        let mut chain_ids = vec![self.ctx().chain_id];
+        if let Self::CosmosIbcPacket(packet) = self {
+            if let Some(counterparty) = &packet.counterparty_chain_id {
+                if !chain_ids.contains(counterparty) {
+                    chain_ids.push(counterparty.clone());
+                }
+            }
+        }
+
+        chain_ids
+    }
+
+    /// Alert sources of the alerts the source is matched against, fund monitoring alerts follow
+    /// eth txs and the ERC-20 Transfer logs they emit
+    pub fn alert_sources(&self) -> Vec<AlertSource> {
+        match self {
+            Self::EthLog(_) => vec![AlertSource::EthLog, AlertSource::EthTx],
+            _ => vec![self.ctx().source_type],
+        }
+    }
+
+    /// Path of the msg within the wrapper msgs it is nested in, empty for other sources
+    pub fn msg_path(&self) -> Vec<u64> {
+        match self {
+            Self::CosmosMsg(msg) => msg.path.clone(),
+            Self::CosmosMsgEvent(event) => event.msg.path.clone(),
+            _ => vec![],
+        }
+    }
+
     pub fn get_eth_log(&self) -> Result<AlertSourceEthLog> {
         if let Self::EthLog(log) = self {
             Ok(*log.clone())
@@ -275,4 +723,12 @@ impl ProcessAlertSourceRequeust {
             Err("alert source mistmatch".into())
         }
     }
+
+    pub fn get_eth_internal_transfer(&self) -> Result<AlertSourceEthInternalTransfer> {
+        if let Self::EthInternalTransfer(transfer) = self {
+            Ok(*transfer.clone())
+        } else {
+            Err("alert source mistmatch".into())
+        }
+    }
 }
@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+
+use cosmrs::proto::cosmos::base::abci::v1beta1::AbciMessageLog;
+
+pub const CONTRACT_ADDRESS: &str = "_contract_address";
+
+/// Attributes of each contract execution of a msg. Msg logs merge the wasm events of every
+/// contract execution into one event, the attributes of each execution start with the address
+/// of its contract
+pub fn wasm_events(msg_log: &AbciMessageLog) -> Vec<HashMap<&str, &str>> {
+    let mut events: Vec<HashMap<&str, &str>> = vec![];
+    for attr in msg_log
+        .events
+        .iter()
+        .filter(|e| e.r#type == "wasm")
+        .flat_map(|e| &e.attributes)
+    {
+        if attr.key == CONTRACT_ADDRESS || events.is_empty() {
+            events.push(HashMap::new());
+        }
+        if let Some(event) = events.last_mut() {
+            event.insert(attr.key.as_str(), attr.value.as_str());
+        }
+    }
+
+    events
+}